@@ -1,5 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
-use press_rs::compressor::{compress_raw, decompress_raw};
+use press_rs::compressor::{
+    compress_raw, compress_raw_with_options, decompress_raw, Codec, CompressOptions, Decompressor,
+    EntropyCoding, DEFAULT_PARALLEL_BLOCK_SIZE,
+};
 use press_rs::packager::{pack_entries, unpack_to_entries, FileEntry};
 
 use rand::Rng;
@@ -9,12 +12,38 @@ fn generate_data(size: usize) -> Vec<u8> {
     (0..size).map(|_| rng.random()).collect()
 }
 
+fn generate_text(size: usize) -> Vec<u8> {
+    const WORDS: [&str; 12] = [
+        "the",
+        "archive",
+        "stores",
+        "files",
+        "and",
+        "folders",
+        "compressed",
+        "with",
+        "lzw",
+        "codes",
+        "of",
+        "data",
+    ];
+    let mut rng = rand::rng();
+    let mut text = Vec::with_capacity(size + 16);
+    while text.len() < size {
+        text.extend_from_slice(WORDS[rng.random_range(0..WORDS.len())].as_bytes());
+        text.push(b' ');
+    }
+    text.truncate(size);
+    text
+}
+
 fn generate_entries(count: usize, file_size: usize) -> Vec<FileEntry> {
     (0..count)
         .map(|i| FileEntry {
             name: format!("file_{}.bin", i),
             data: generate_data(file_size),
             is_dir: false,
+            ..Default::default()
         })
         .collect()
 }
@@ -29,7 +58,79 @@ fn bench_compress_raw(c: &mut Criterion) {
         group.bench_with_input(
             criterion::BenchmarkId::from_parameter(size),
             &data,
-            |b, data| b.iter(|| compress_raw(black_box(data))),
+            |b, data| b.iter(|| compress_raw(black_box(data)).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn bench_compress_bwt(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compress_bwt");
+
+    for size in [1024, 100 * 1024, 1024 * 1024].iter() {
+        let data = generate_text(*size);
+        let options = CompressOptions {
+            codec: Codec::Bwt,
+            ..Default::default()
+        };
+        let compressed = compress_raw_with_options(&data, &options).unwrap();
+        println!(
+            "compress_bwt/{}: ratio {:.3} (LZW {:.3})",
+            size,
+            compressed.len() as f64 / *size as f64,
+            compress_raw(&data).unwrap().len() as f64 / *size as f64
+        );
+
+        group.throughput(Throughput::Bytes(*size as u64));
+        group.bench_with_input(
+            criterion::BenchmarkId::new("compress", size),
+            &data,
+            |b, data| b.iter(|| compress_raw_with_options(black_box(data), &options).unwrap()),
+        );
+        group.bench_with_input(
+            criterion::BenchmarkId::new("decompress", size),
+            &compressed,
+            |b, data| b.iter(|| decompress_raw(black_box(data)).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+fn bench_compress_parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compress_parallel");
+    group.sample_size(10);
+    let size = 32 * 1024 * 1024;
+    let data = generate_text(size);
+    let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut thread_counts = vec![1];
+    if available > 1 {
+        thread_counts.push(available);
+    }
+
+    group.throughput(Throughput::Bytes(size as u64));
+    for threads in thread_counts {
+        let options = CompressOptions {
+            parallel_block_size: Some(DEFAULT_PARALLEL_BLOCK_SIZE),
+            threads,
+            ..Default::default()
+        };
+        let compressed = compress_raw_with_options(&data, &options).unwrap();
+
+        group.bench_with_input(
+            criterion::BenchmarkId::new("compress", threads),
+            &data,
+            |b, data| b.iter(|| compress_raw_with_options(black_box(data), &options).unwrap()),
+        );
+        group.bench_with_input(
+            criterion::BenchmarkId::new("decompress", threads),
+            &compressed,
+            |b, data| {
+                b.iter(|| {
+                    let mut decompressor =
+                        Decompressor::with_threads(black_box(data.as_slice()), threads).unwrap();
+                    std::io::copy(&mut decompressor, &mut std::io::sink()).unwrap()
+                })
+            },
         );
     }
     group.finish();
@@ -40,18 +141,59 @@ fn bench_decompress_raw(c: &mut Criterion) {
 
     for size in [1024, 100 * 1024].iter() {
         let original_data = generate_data(*size);
-        let compressed_data = compress_raw(&original_data);
+        let compressed_data = compress_raw(&original_data).unwrap();
 
         group.throughput(Throughput::Bytes(*size as u64));
         group.bench_with_input(
             criterion::BenchmarkId::from_parameter(size),
             &compressed_data,
-            |b, data| b.iter(|| decompress_raw(black_box(data))),
+            |b, data| b.iter(|| decompress_raw(black_box(data)).unwrap()),
         );
     }
     group.finish();
 }
 
+fn bench_entropy_coding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("entropy_coding");
+    let size = 1024 * 1024;
+
+    for (kind, data) in [
+        ("text", generate_text(size)),
+        ("random", generate_data(size)),
+    ] {
+        for (name, entropy) in [
+            ("none", EntropyCoding::None),
+            ("static", EntropyCoding::StaticHuffman),
+            ("adaptive", EntropyCoding::AdaptiveHuffman),
+        ] {
+            let options = CompressOptions {
+                entropy,
+                ..Default::default()
+            };
+            let compressed = compress_raw_with_options(&data, &options).unwrap();
+            println!(
+                "entropy_coding/{}/{}: ratio {:.3}",
+                kind,
+                name,
+                compressed.len() as f64 / size as f64
+            );
+
+            group.throughput(Throughput::Bytes(size as u64));
+            group.bench_with_input(
+                criterion::BenchmarkId::new(format!("compress_{}", kind), name),
+                &data,
+                |b, data| b.iter(|| compress_raw_with_options(black_box(data), &options).unwrap()),
+            );
+            group.bench_with_input(
+                criterion::BenchmarkId::new(format!("decompress_{}", kind), name),
+                &compressed,
+                |b, data| b.iter(|| decompress_raw(black_box(data)).unwrap()),
+            );
+        }
+    }
+    group.finish();
+}
+
 fn bench_pack_entries(c: &mut Criterion) {
     let mut group = c.benchmark_group("pack_entries");
 
@@ -60,23 +202,26 @@ fn bench_pack_entries(c: &mut Criterion) {
             name: "test1.txt".to_string(),
             data: generate_data(1024),
             is_dir: false,
+            ..Default::default()
         },
         FileEntry {
             name: "photos/image.bin".to_string(),
             data: generate_data(10 * 1024),
             is_dir: false,
+            ..Default::default()
         },
         FileEntry {
             name: "folder/".to_string(),
             data: vec![],
             is_dir: true,
+            ..Default::default()
         },
     ];
 
     group.bench_function("pack_small_entries", |b| {
         b.iter_with_setup(
             || entries.clone(),
-            |entries| pack_entries(black_box(entries)),
+            |entries| pack_entries(black_box(entries)).unwrap(),
         )
     });
 
@@ -90,11 +235,12 @@ fn bench_unpack_entries(c: &mut Criterion) {
         name: "test.txt".to_string(),
         data: generate_data(5 * 1024),
         is_dir: false,
+        ..Default::default()
     }];
-    let archive = pack_entries(entries);
+    let archive = pack_entries(entries).unwrap();
 
     group.bench_function("unpack_small_archive", |b| {
-        b.iter(|| unpack_to_entries(black_box(archive.clone())))
+        b.iter(|| unpack_to_entries(black_box(archive.clone())).unwrap())
     });
 
     group.finish();
@@ -112,8 +258,8 @@ fn bench_full_compression(c: &mut Criterion) {
         b.iter_with_setup(
             || entries.clone(),
             |entries| {
-                let packed = pack_entries(entries);
-                compress_raw(black_box(&packed))
+                let packed = pack_entries(entries).unwrap();
+                compress_raw(black_box(&packed)).unwrap()
             },
         )
     });
@@ -125,16 +271,16 @@ fn bench_full_decompression(c: &mut Criterion) {
     let mut group = c.benchmark_group("full_decompression");
 
     let entries = generate_entries(10, 10 * 1024);
-    let packed = pack_entries(entries);
-    let compressed = compress_raw(&packed);
+    let packed = pack_entries(entries).unwrap();
+    let compressed = compress_raw(&packed).unwrap();
     let total_size = packed.len() as u64;
 
     group.throughput(Throughput::Bytes(total_size));
 
     group.bench_function("decompress_and_unpack_100kb", |b| {
         b.iter(|| {
-            let decompressed = decompress_raw(black_box(&compressed));
-            unpack_to_entries(black_box(decompressed))
+            let decompressed = decompress_raw(black_box(&compressed)).unwrap();
+            unpack_to_entries(black_box(decompressed)).unwrap()
         })
     });
 
@@ -144,7 +290,10 @@ fn bench_full_decompression(c: &mut Criterion) {
 criterion_group!(
     benches,
     bench_compress_raw,
+    bench_compress_bwt,
+    bench_compress_parallel,
     bench_decompress_raw,
+    bench_entropy_coding,
     bench_pack_entries,
     bench_unpack_entries,
     bench_full_compression,
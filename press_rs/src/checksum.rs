@@ -0,0 +1,175 @@
+//! Checksums used to verify archive integrity.
+
+/// Lookup table for the reflected CRC-32 polynomial (IEEE 802.3, as used by zlib and gzip).
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Incremental CRC-32 hasher.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::checksum::Crc32;
+///
+/// let mut crc = Crc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.finalize(), 0xCBF4_3926);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Creates a hasher for an empty input.
+    pub fn new() -> Crc32 {
+        Crc32 { state: !0 }
+    }
+
+    /// Feeds `data` into the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        let mut crc = self.state;
+        for &byte in data {
+            crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        self.state = crc;
+    }
+
+    /// Returns the checksum of all data fed so far.
+    pub fn finalize(&self) -> u32 {
+        !self.state
+    }
+}
+
+/// Computes the CRC-32 of `data` in one go.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.finalize()
+}
+
+/// The largest prime below 2^16, the modulus of Adler-32.
+const ADLER_MODULUS: u32 = 65_521;
+
+/// Number of bytes that can be summed before the Adler-32 sums must be reduced
+/// to stay below 2^32.
+const ADLER_BLOCK_SIZE: usize = 5552;
+
+/// Incremental Adler-32 hasher, the checksum of the zlib format (RFC 1950).
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::checksum::Adler32;
+///
+/// let mut adler = Adler32::new();
+/// adler.update(b"Wiki");
+/// adler.update(b"pedia");
+/// assert_eq!(adler.finalize(), 0x11E6_0398);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Adler32 {
+    /// Creates a hasher for an empty input.
+    pub fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    /// Feeds `data` into the checksum.
+    pub fn update(&mut self, data: &[u8]) {
+        for block in data.chunks(ADLER_BLOCK_SIZE) {
+            for &byte in block {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= ADLER_MODULUS;
+            self.b %= ADLER_MODULUS;
+        }
+    }
+
+    /// Returns the checksum of all data fed so far.
+    pub fn finalize(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+}
+
+/// Computes the Adler-32 of `data` in one go.
+pub fn adler32(data: &[u8]) -> u32 {
+    let mut adler = Adler32::new();
+    adler.update(data);
+    adler.finalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn test_crc32_incremental_matches_one_shot() {
+        // Arrange
+        let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let mut crc = Crc32::new();
+
+        // Act
+        for chunk in data.chunks(333) {
+            crc.update(chunk);
+        }
+
+        // Assert
+        assert_eq!(crc.finalize(), crc32(&data));
+    }
+
+    #[test]
+    fn test_adler32_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        // The sums wrap around the modulus many times
+        assert_eq!(adler32(&[0xFF; 100_000]), 0x149A_302C);
+    }
+}
@@ -0,0 +1,714 @@
+//! Block sorting compression in the style of bzip2: the Burrows–Wheeler
+//! transform, move-to-front coding, run-length coding of zeros and Huffman
+//! coding.
+
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+
+use crate::compressor::compress::BitWriter;
+use crate::compressor::decompress::BitReader;
+use crate::compressor::deflate::{reversed_codes, CodeLengthTable};
+use crate::compressor::huffman::{self, HuffmanDecoder, MAX_CODE_LENGTH};
+use crate::compressor::inflate::read_code_lengths;
+use crate::compressor::BitOrder;
+use crate::Error;
+
+/// The supported block sizes of [`BwtEncoder`], in units of 100 000 bytes
+/// (like `bzip2 -1` to `bzip2 -9`).
+pub const BWT_BLOCK_SIZE_RANGE: RangeInclusive<u8> = 1..=9;
+
+/// The default block size of [`BwtEncoder`], in units of 100 000 bytes.
+pub const DEFAULT_BWT_BLOCK_SIZE: u8 = 9;
+
+/// The unit of the block size.
+const BLOCK_SIZE_UNIT: usize = 100_000;
+
+/// Bit width of the block length and the primary index, enough for the largest block.
+const BLOCK_FIELD_WIDTH: u8 = 20;
+
+/// Symbol adding 1 × its weight to a run of zeros.
+const RUN_A: u16 = 0;
+
+/// Symbol adding 2 × its weight to a run of zeros.
+const RUN_B: u16 = 1;
+
+/// The symbol ending a block. Move-to-front positions 1 to 255 are symbols 2 to 256.
+const END_OF_BLOCK: u16 = 257;
+
+/// Number of symbols of the Huffman code.
+const SYMBOLS: usize = END_OF_BLOCK as usize + 1;
+
+/// Fails with [`Error::InvalidOptions`] unless `block_size` is in [`BWT_BLOCK_SIZE_RANGE`].
+pub(crate) fn check_block_size(block_size: u8) -> Result<(), Error> {
+    if BWT_BLOCK_SIZE_RANGE.contains(&block_size) {
+        Ok(())
+    } else {
+        Err(Error::InvalidOptions(format!(
+            "BWT block size must be between {} and {} (× 100 000 bytes), got {}",
+            BWT_BLOCK_SIZE_RANGE.start(),
+            BWT_BLOCK_SIZE_RANGE.end(),
+            block_size
+        )))
+    }
+}
+
+/// Returns the start of every suffix of `data`, sorted. A suffix that is a
+/// prefix of another sorts first, as if `data` ended with a unique smallest byte.
+///
+/// Built by prefix doubling: each round sorts the suffixes by their first
+/// `2k` bytes with two stable counting sorts by the ranks of the first `k`
+/// bytes, so the whole sort takes `O(n log n)` even for long runs.
+pub(crate) fn suffix_array(data: &[u8]) -> Vec<u32> {
+    let n = data.len();
+    let mut sa: Vec<u32> = (0..n as u32).collect();
+    sa.sort_by_key(|&i| data[i as usize]);
+    if n < 2 {
+        return sa;
+    }
+    let mut rank: Vec<u32> = data.iter().map(|&byte| byte as u32).collect();
+    let mut next_rank = vec![0u32; n];
+    let mut by_second_key = Vec::with_capacity(n);
+    let mut counts = vec![0usize; n.max(256) + 1];
+
+    let mut k = 1;
+    loop {
+        // Order by the rank of the second half; suffixes without one come first
+        by_second_key.clear();
+        by_second_key.extend(n.saturating_sub(k) as u32..n as u32);
+        by_second_key.extend(
+            sa.iter()
+                .filter(|&&i| i as usize >= k)
+                .map(|&i| i - k as u32),
+        );
+
+        // Stable counting sort by the rank of the first half
+        counts.fill(0);
+        for &i in &by_second_key {
+            counts[rank[i as usize] as usize + 1] += 1;
+        }
+        for r in 1..counts.len() {
+            counts[r] += counts[r - 1];
+        }
+        for &i in &by_second_key {
+            let slot = &mut counts[rank[i as usize] as usize];
+            sa[*slot] = i;
+            *slot += 1;
+        }
+
+        let key = |i: u32| {
+            let second = (i as usize + k < n).then(|| rank[i as usize + k]);
+            (rank[i as usize], second)
+        };
+        next_rank[sa[0] as usize] = 0;
+        for j in 1..n {
+            let same = key(sa[j - 1]) == key(sa[j]);
+            next_rank[sa[j] as usize] = next_rank[sa[j - 1] as usize] + !same as u32;
+        }
+        std::mem::swap(&mut rank, &mut next_rank);
+        if rank[sa[n - 1] as usize] as usize == n - 1 {
+            break;
+        }
+        k *= 2;
+    }
+    sa
+}
+
+/// Returns the Burrows–Wheeler transform of `data` and the primary index.
+///
+/// The transform is the last column of the sorted rotations of `data` followed
+/// by an end marker. The marker itself is left out; the primary index is the
+/// row it would be in.
+pub(crate) fn transform(data: &[u8]) -> (Vec<u8>, usize) {
+    let n = data.len();
+    let mut last = Vec::with_capacity(n);
+    let mut primary = 0;
+    // The row of the end marker alone comes first and ends with the last byte
+    if let Some(&byte) = data.last() {
+        last.push(byte);
+    }
+    for (row, &start) in suffix_array(data).iter().enumerate() {
+        match start {
+            0 => primary = row + 1,
+            _ => last.push(data[start as usize - 1]),
+        }
+    }
+    (last, primary)
+}
+
+/// Reverses [`transform`].
+///
+/// Fails with [`Error::CorruptCodeStream`] if `primary` is out of range or the
+/// transform does not describe a single string.
+pub(crate) fn inverse_transform(last: &[u8], primary: usize) -> Result<Vec<u8>, Error> {
+    let n = last.len();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    if primary == 0 || primary > n {
+        return Err(Error::CorruptCodeStream(format!(
+            "invalid primary index {} for a block of {} bytes",
+            primary, n
+        )));
+    }
+    let byte_at = |row: usize| last[if row < primary { row } else { row - 1 }];
+
+    // The row each row's rotation moves to when rotated right by one byte
+    let mut starts = [0usize; 256];
+    for &byte in last {
+        starts[byte as usize] += 1;
+    }
+    let mut total = 1;
+    for start in starts.iter_mut() {
+        let count = *start;
+        *start = total;
+        total += count;
+    }
+    let mut next = vec![0u32; n + 1];
+    for (row, slot) in next.iter_mut().enumerate() {
+        if row != primary {
+            let byte = byte_at(row) as usize;
+            *slot = starts[byte] as u32;
+            starts[byte] += 1;
+        }
+    }
+
+    let mut output = vec![0u8; n];
+    let mut row = 0;
+    for position in (0..n).rev() {
+        if row == primary {
+            return Err(Error::CorruptCodeStream(
+                "block transform is not a single rotation".to_string(),
+            ));
+        }
+        output[position] = byte_at(row);
+        row = next[row] as usize;
+    }
+    Ok(output)
+}
+
+/// Replaces each byte by its position in a list of recently used bytes, moving
+/// the byte to the front of the list.
+fn move_to_front(data: &[u8]) -> Vec<u8> {
+    let mut order: [u8; 256] = std::array::from_fn(|i| i as u8);
+    data.iter()
+        .map(|&byte| {
+            let position = order.iter().position(|&b| b == byte).unwrap_or(0);
+            order.copy_within(..position, 1);
+            order[0] = byte;
+            position as u8
+        })
+        .collect()
+}
+
+/// Reverses [`move_to_front`].
+fn inverse_move_to_front(positions: &[u8]) -> Vec<u8> {
+    let mut order: [u8; 256] = std::array::from_fn(|i| i as u8);
+    positions
+        .iter()
+        .map(|&position| {
+            let byte = order[position as usize];
+            order.copy_within(..position as usize, 1);
+            order[0] = byte;
+            byte
+        })
+        .collect()
+}
+
+/// Turns move-to-front positions into symbols: runs of zeros become
+/// [`RUN_A`]/[`RUN_B`] digits of the run length in bijective base 2, other
+/// positions `p` become symbol `p + 1`.
+fn run_length_encode(positions: &[u8]) -> Vec<u16> {
+    let mut symbols = Vec::with_capacity(positions.len() / 2 + 1);
+    let mut run = 0usize;
+    for &position in positions {
+        if position == 0 {
+            run += 1;
+            continue;
+        }
+        push_run(&mut symbols, run);
+        run = 0;
+        symbols.push(position as u16 + 1);
+    }
+    push_run(&mut symbols, run);
+    symbols.push(END_OF_BLOCK);
+    symbols
+}
+
+fn push_run(symbols: &mut Vec<u16>, mut run: usize) {
+    while run > 0 {
+        if run & 1 == 1 {
+            symbols.push(RUN_A);
+            run = (run - 1) / 2;
+        } else {
+            symbols.push(RUN_B);
+            run = (run - 2) / 2;
+        }
+    }
+}
+
+/// Writes the low `width` bits of `value`, which may be wider than 16 bits.
+fn write_field<W: Write>(writer: &mut BitWriter<W>, value: usize, width: u8) -> io::Result<()> {
+    writer.write(value as u16, width.min(16))?;
+    if width > 16 {
+        writer.write((value >> 16) as u16, width - 16)?;
+    }
+    Ok(())
+}
+
+/// Reads a value written by [`write_field`].
+fn read_field<R: Read>(reader: &mut BitReader<R>, width: u8) -> io::Result<usize> {
+    let mut value = reader.read_one(width.min(16))? as usize;
+    if width > 16 {
+        value |= (reader.read_one(width - 16)? as usize) << 16;
+    }
+    Ok(value)
+}
+
+/// Streaming encoder for block sorting compression, the pipeline of bzip2.
+///
+/// The input is cut into blocks of up to [`BWT_BLOCK_SIZE_RANGE`] × 100 000
+/// bytes. Each block is sorted with the Burrows–Wheeler transform (built from
+/// a suffix array), which groups bytes by the context that follows them, then
+/// move-to-front coded, so those groups become runs of small numbers. Runs of
+/// zeros are run-length coded and the result is Huffman coded with a code
+/// stored in front of the block. Large text compresses much better than with
+/// LZW, at the cost of speed and of memory for a whole block.
+///
+/// The stream is a press_rs format of its own; it is read by [`BwtDecoder`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{BwtDecoder, BwtEncoder};
+/// use std::io::{Read, Write};
+///
+/// let mut encoder = BwtEncoder::new(Vec::new());
+/// encoder.write_all(b"banana bandana banana bandana").unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// let mut decompressed = String::new();
+/// BwtDecoder::new(compressed.as_slice()).read_to_string(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, "banana bandana banana bandana");
+/// ```
+pub struct BwtEncoder<W>
+where
+    W: Write,
+{
+    writer: BitWriter<W>,
+    block: Vec<u8>,
+    block_size: usize,
+}
+
+impl<W> BwtEncoder<W>
+where
+    W: Write,
+{
+    /// Creates an encoder with blocks of [`DEFAULT_BWT_BLOCK_SIZE`] × 100 000 bytes.
+    pub fn new(writer: W) -> Self {
+        Self::with_block_size(writer, DEFAULT_BWT_BLOCK_SIZE).expect("the default size is valid")
+    }
+
+    /// Creates an encoder with blocks of `block_size` × 100 000 bytes (1 to 9).
+    ///
+    /// Larger blocks usually compress better but need more memory and time
+    /// per block.
+    pub fn with_block_size(writer: W, block_size: u8) -> Result<Self, Error> {
+        check_block_size(block_size)?;
+        let block_size = block_size as usize * BLOCK_SIZE_UNIT;
+        Ok(Self {
+            writer: BitWriter::new(writer, BitOrder::LsbFirst),
+            block: Vec::with_capacity(block_size),
+            block_size,
+        })
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Writes the last block and the end of the stream, then returns the
+    /// underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.block.is_empty() {
+            self.write_block()?;
+        }
+        write_field(&mut self.writer, 0, BLOCK_FIELD_WIDTH)?;
+        let mut writer = self.writer.into_inner()?;
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    /// Writes the buffered block: its length, the primary index, the code
+    /// lengths of the block and the coded symbols.
+    fn write_block(&mut self) -> io::Result<()> {
+        let (last, primary) = transform(&self.block);
+        let symbols = run_length_encode(&move_to_front(&last));
+
+        let mut frequencies = [0u32; SYMBOLS];
+        for &symbol in &symbols {
+            frequencies[symbol as usize] += 1;
+        }
+        let lengths = huffman::code_lengths(&frequencies, MAX_CODE_LENGTH);
+        let codes = reversed_codes(&lengths);
+
+        write_field(&mut self.writer, self.block.len(), BLOCK_FIELD_WIDTH)?;
+        write_field(&mut self.writer, primary, BLOCK_FIELD_WIDTH)?;
+        CodeLengthTable::new(&lengths).write_to(&mut self.writer)?;
+        for symbol in symbols {
+            self.writer
+                .write(codes[symbol as usize], lengths[symbol as usize])?;
+        }
+        self.block.clear();
+        Ok(())
+    }
+}
+
+impl<W> Write for BwtEncoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(self.block_size - self.block.len());
+        self.block.extend_from_slice(&buf[..count]);
+        if self.block.len() == self.block_size {
+            self.write_block()?;
+        }
+        Ok(count)
+    }
+
+    /// Writes all complete bytes of the finished blocks to the underlying writer.
+    ///
+    /// The current block stays buffered until it is full or until
+    /// [`BwtEncoder::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.write_pending()?;
+        self.writer.get_mut().flush()
+    }
+}
+
+/// Streaming decoder for streams written by [`BwtEncoder`].
+///
+/// Each block is decoded as a whole, so the decoder holds up to 900 000
+/// bytes of output and a few bytes of index per byte. It pulls the input one
+/// byte at a time and never reads past the end of the stream, so wrap
+/// unbuffered sources (files, sockets) in a [`BufReader`](std::io::BufReader).
+///
+/// Malformed input is reported as an [`std::io::Error`] of kind
+/// [`InvalidData`](io::ErrorKind::InvalidData) wrapping [`Error::CorruptCodeStream`].
+pub struct BwtDecoder<R>
+where
+    R: Read,
+{
+    reader: BitReader<R>,
+    /// The decoded bytes of the current block.
+    block: Vec<u8>,
+    /// Index in `block` of the next byte to return.
+    read_position: usize,
+    finished: bool,
+}
+
+impl<R> BwtDecoder<R>
+where
+    R: Read,
+{
+    /// Creates a decoder that reads the compressed stream from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BitReader::new(reader, BitOrder::LsbFirst),
+            block: Vec::new(),
+            read_position: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Once the end of the stream was reached, the reader is positioned right
+    /// after the last byte of the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Decodes the next block into `block`, or marks the stream as finished.
+    fn decode_block(&mut self) -> Result<(), Error> {
+        let length = read_field(&mut self.reader, BLOCK_FIELD_WIDTH).map_err(end_of_input)?;
+        if length == 0 {
+            self.finished = true;
+            return Ok(());
+        }
+        let max_length = *BWT_BLOCK_SIZE_RANGE.end() as usize * BLOCK_SIZE_UNIT;
+        if length > max_length {
+            return Err(Error::CorruptCodeStream(format!(
+                "block of {} bytes is larger than {} bytes",
+                length, max_length
+            )));
+        }
+        let primary = read_field(&mut self.reader, BLOCK_FIELD_WIDTH).map_err(end_of_input)?;
+        let lengths = read_code_lengths(&mut self.reader, SYMBOLS).map_err(|e| match e {
+            Error::Io(e) => end_of_input(e),
+            e => e,
+        })?;
+        let decoder = HuffmanDecoder::new(&lengths)?;
+
+        let mut positions = Vec::with_capacity(length);
+        let (mut run, mut weight) = (0usize, 1usize);
+        loop {
+            let symbol = decoder
+                .decode(|| self.reader.read_one(1))
+                .map_err(|e| match e {
+                    Error::Io(e) => end_of_input(e),
+                    e => e,
+                })?;
+            if symbol == RUN_A || symbol == RUN_B {
+                run += weight << symbol;
+                weight <<= 1;
+                if positions.len() + run > length {
+                    return Err(Error::CorruptCodeStream(
+                        "block is longer than its length".to_string(),
+                    ));
+                }
+                continue;
+            }
+            positions.extend(std::iter::repeat_n(0, run));
+            (run, weight) = (0, 1);
+            if symbol == END_OF_BLOCK {
+                break;
+            }
+            if positions.len() == length {
+                return Err(Error::CorruptCodeStream(
+                    "block is longer than its length".to_string(),
+                ));
+            }
+            positions.push((symbol - 1) as u8);
+        }
+        if positions.len() != length {
+            return Err(Error::CorruptCodeStream(
+                "block is shorter than its length".to_string(),
+            ));
+        }
+
+        self.block = inverse_transform(&inverse_move_to_front(&positions), primary)?;
+        self.read_position = 0;
+        Ok(())
+    }
+}
+
+/// Reports running out of input as a corrupt stream.
+fn end_of_input(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        Error::CorruptCodeStream("stream ended before the end of the last block".to_string())
+    } else {
+        e.into()
+    }
+}
+
+impl<R> Read for BwtDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.read_position == self.block.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            self.decode_block()?;
+        }
+
+        let count = buf.len().min(self.block.len() - self.read_position);
+        buf[..count].copy_from_slice(&self.block[self.read_position..self.read_position + count]);
+        self.read_position += count;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rng, Rng};
+
+    fn compress(data: &[u8], block_size: u8) -> Vec<u8> {
+        let mut encoder = BwtEncoder::with_block_size(Vec::new(), block_size).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        BwtDecoder::new(data).read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    /// Sorts the suffixes by comparing them directly.
+    fn naive_suffix_array(data: &[u8]) -> Vec<u32> {
+        let mut sa: Vec<u32> = (0..data.len() as u32).collect();
+        sa.sort_by(|&a, &b| data[a as usize..].cmp(&data[b as usize..]));
+        sa
+    }
+
+    mod transform {
+        use super::*;
+
+        #[test]
+        fn test_suffix_array_matches_naive_sort() {
+            // Arrange
+            let mut rng = rng();
+            let inputs: Vec<Vec<u8>> = vec![
+                b"banana".to_vec(),
+                b"mississippi".to_vec(),
+                vec![b'a'; 1000],
+                b"abab".repeat(300),
+                (0..2000).map(|_| rng.random_range(b'a'..=b'c')).collect(),
+                vec![7],
+                vec![],
+            ];
+
+            for data in inputs {
+                // Act
+                let sa = suffix_array(&data);
+
+                // Assert
+                assert_eq!(sa, naive_suffix_array(&data), "{:?}", data);
+            }
+        }
+
+        #[test]
+        fn test_transform_of_banana() {
+            // Act
+            let (last, primary) = transform(b"banana");
+
+            // Assert
+            // Rows of "banana$": $banana, a$banan, ana$ban, anana$b, banana$,
+            // na$bana, nana$ba; the end marker is left out of the last column
+            assert_eq!(last, b"annbaa");
+            assert_eq!(primary, 4);
+        }
+
+        #[test]
+        fn test_inverse_transform_round_trip() {
+            // Arrange
+            let mut rng = rng();
+            let data: Vec<u8> = (0..5000).map(|_| rng.random_range(0..4u8)).collect();
+
+            // Act
+            let (last, primary) = transform(&data);
+            let restored = inverse_transform(&last, primary).unwrap();
+
+            // Assert
+            assert_eq!(restored, data);
+        }
+
+        #[test]
+        fn test_invalid_primary_index_is_rejected() {
+            assert!(matches!(
+                inverse_transform(b"abc", 0),
+                Err(Error::CorruptCodeStream(_))
+            ));
+            assert!(matches!(
+                inverse_transform(b"abc", 4),
+                Err(Error::CorruptCodeStream(_))
+            ));
+        }
+
+        #[test]
+        fn test_move_to_front_and_runs() {
+            // Arrange
+            let data = b"aaabbbbba";
+
+            // Act
+            let positions = move_to_front(data);
+            let symbols = run_length_encode(&positions);
+
+            // Assert
+            assert_eq!(positions, [97, 0, 0, 98, 0, 0, 0, 0, 1]);
+            assert_eq!(inverse_move_to_front(&positions), data);
+            // Runs of 2 and 4 zeros are RUN_B (2) and RUN_B RUN_A (2 + 2 × 1)
+            assert_eq!(symbols, [98, RUN_B, 99, RUN_B, RUN_A, 2, END_OF_BLOCK]);
+        }
+    }
+
+    mod stream {
+        use super::*;
+
+        #[test]
+        fn test_round_trip() {
+            // Arrange
+            let text = b"It was the best of times, it was the worst of times. ".repeat(400);
+
+            // Act
+            let compressed = compress(&text, 9);
+
+            // Assert
+            assert!(compressed.len() < text.len() / 20);
+            assert_eq!(decompress(&compressed).unwrap(), text);
+        }
+
+        #[test]
+        fn test_empty_and_single_byte() {
+            assert_eq!(decompress(&compress(b"", 1)).unwrap(), b"");
+            assert_eq!(decompress(&compress(b"x", 1)).unwrap(), b"x");
+        }
+
+        #[test]
+        fn test_many_blocks_round_trip() {
+            // Arrange
+            let mut rng = rng();
+            let data: Vec<u8> = (0..250_000)
+                .map(|_| rng.random_range(b'a'..=b'h'))
+                .collect();
+
+            // Act
+            let compressed = compress(&data, 1);
+
+            // Assert
+            assert_eq!(decompress(&compressed).unwrap(), data);
+        }
+
+        #[test]
+        fn test_reader_is_left_after_the_stream() {
+            // Arrange
+            let mut data = compress(b"payload", 1);
+            data.extend_from_slice(b"next");
+            let mut decoder = BwtDecoder::new(data.as_slice());
+            let mut output = Vec::new();
+
+            // Act
+            decoder.read_to_end(&mut output).unwrap();
+            let mut rest = Vec::new();
+            decoder.into_inner().read_to_end(&mut rest).unwrap();
+
+            // Assert
+            assert_eq!(output, b"payload");
+            assert_eq!(rest, b"next");
+        }
+
+        #[test]
+        fn test_truncated_stream_is_rejected() {
+            // Arrange
+            let compressed = compress(&b"truncated block ".repeat(50), 1);
+
+            // Act
+            let result = decompress(&compressed[..compressed.len() / 2]);
+
+            // Assert
+            assert!(matches!(result, Err(Error::CorruptCodeStream(_))));
+        }
+
+        #[test]
+        fn test_invalid_block_size_is_rejected() {
+            assert!(matches!(
+                BwtEncoder::with_block_size(Vec::new(), 0),
+                Err(Error::InvalidOptions(_))
+            ));
+            assert!(matches!(
+                BwtEncoder::with_block_size(Vec::new(), 10),
+                Err(Error::InvalidOptions(_))
+            ));
+        }
+    }
+}
@@ -1,40 +1,136 @@
-use crate::compressor::{MAX_CODE_WIDTH, MAX_ENTRY_COUNT};
+use std::io::{self, Write};
 
-use super::INITIAL_CODE_WIDTH;
+use crate::compressor::entropy::EntropyEncoder;
+use crate::compressor::{
+    BitOrder, CompressOptions, EntropyCoding, ResetPolicy, CODE_WIDTH_RANGE, DEFAULT_MAX_CODE_WIDTH,
+};
+use crate::Error;
 
-struct BitWriter {
+use super::{Dialect, INITIAL_CODE_WIDTH};
+
+/// Number of complete bytes buffered by [`BitWriter`] before they are written out.
+const OUTPUT_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Number of input bytes between compression ratio checks of
+/// [`ResetPolicy::OnRatioDrop`], as in `compress(1)`.
+const CHECK_GAP: u64 = 10_000;
+
+pub(crate) struct BitWriter<W>
+where
+    W: Write,
+{
     buffer: u32,
     cursor: u8,
-    output: Vec<u8>,
+    pending: Vec<u8>,
+    output: W,
+    bit_order: BitOrder,
+    /// Total number of bits written so far.
+    bits_written: u64,
 }
 
-impl BitWriter {
-    fn new() -> Self {
+impl<W> BitWriter<W>
+where
+    W: Write,
+{
+    pub(crate) fn new(output: W, bit_order: BitOrder) -> Self {
         Self {
             buffer: 0,
             cursor: 0,
-            output: Vec::new(),
+            pending: Vec::with_capacity(OUTPUT_CHUNK_SIZE),
+            output,
+            bit_order,
+            bits_written: 0,
         }
     }
-    fn write(&mut self, code: u16, width: u8) {
+
+    #[inline(always)]
+    pub(crate) fn write(&mut self, code: u16, width: u8) -> io::Result<()> {
         let mask = (1 << width) - 1;
-        self.buffer |= (code as u32 & mask) << self.cursor;
-        self.cursor += width;
+        self.bits_written += width as u64;
+
+        match self.bit_order {
+            BitOrder::LsbFirst => {
+                self.buffer |= (code as u32 & mask) << self.cursor;
+                self.cursor += width;
+
+                while self.cursor >= 8 {
+                    let byte = self.buffer as u8;
+                    self.buffer >>= 8;
+                    self.cursor -= 8;
+
+                    self.pending.push(byte);
+                }
+            }
+            BitOrder::MsbFirst => {
+                // The pending bits are the lowest `cursor` bits of the buffer
+                self.buffer = (self.buffer << width) | (code as u32 & mask);
+                self.cursor += width;
+
+                while self.cursor >= 8 {
+                    self.cursor -= 8;
+                    let byte = (self.buffer >> self.cursor) as u8;
+                    self.buffer &= (1 << self.cursor) - 1;
+
+                    self.pending.push(byte);
+                }
+            }
+        }
+
+        if self.pending.len() >= OUTPUT_CHUNK_SIZE {
+            self.write_pending()?;
+        }
+        Ok(())
+    }
 
-        while self.cursor >= 8 {
-            let byte = self.buffer as u8;
-            self.buffer >>= 8;
-            self.cursor -= 8;
+    /// Returns the total number of bits written so far.
+    pub(crate) fn bits_written(&self) -> u64 {
+        self.bits_written
+    }
 
-            self.output.push(byte);
+    /// Pads the partial byte with zero bits, so the next bit starts a new byte.
+    pub(crate) fn align_to_byte(&mut self) -> io::Result<()> {
+        if self.cursor > 0 {
+            self.write(0, 8 - self.cursor)?;
         }
+        Ok(())
+    }
+
+    /// Writes all complete bytes to the output, keeping the partial byte buffered.
+    pub(crate) fn write_pending(&mut self) -> io::Result<()> {
+        self.output.write_all(&self.pending)?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub(crate) fn get_ref(&self) -> &W {
+        &self.output
     }
-    fn flush(&mut self) {
+
+    /// Returns a mutable reference to the underlying writer.
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    /// Flushes everything and returns the underlying writer.
+    pub(crate) fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+
+    /// Pads the partial byte with zero bits and writes everything to the output.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
         if self.cursor > 0 {
-            self.output.push(self.buffer as u8);
+            let byte = match self.bit_order {
+                BitOrder::LsbFirst => self.buffer as u8,
+                BitOrder::MsbFirst => (self.buffer << (8 - self.cursor)) as u8,
+            };
+            self.pending.push(byte);
             self.cursor = 0;
             self.buffer = 0;
         }
+        self.write_pending()
     }
 }
 
@@ -48,21 +144,34 @@ enum PrefixTreeNode {
 struct PrefixTree {
     nodes: Vec<PrefixTreeNode>,
     code_count: usize,
+    /// Index of the first word after the literals and the special codes.
+    first_free_code: usize,
 }
 
 impl PrefixTree {
-    fn new(code_size: u8) -> Self {
-        let mut nodes = Vec::with_capacity(MAX_ENTRY_COUNT);
+    fn new(code_size: u8, first_free_code: u16, max_entry_count: usize) -> Self {
+        let mut nodes = Vec::with_capacity(max_entry_count);
         let code_count = 1 << code_size;
-        nodes.resize(code_count + 2, PrefixTreeNode::NoChild);
-        Self { nodes, code_count }
+        let first_free_code = first_free_code as usize;
+        nodes.resize(first_free_code, PrefixTreeNode::NoChild);
+        Self {
+            nodes,
+            code_count,
+            first_free_code,
+        }
     }
 
     #[inline(always)]
     fn reset(&mut self) {
         self.nodes.clear();
         self.nodes
-            .resize(self.code_count + 2, PrefixTreeNode::NoChild)
+            .resize(self.first_free_code, PrefixTreeNode::NoChild)
+    }
+
+    /// Returns the index the next added word will get.
+    #[inline(always)]
+    fn next_index(&self) -> usize {
+        self.nodes.len()
     }
 
     #[inline(always)]
@@ -124,54 +233,338 @@ impl PrefixTree {
             }
         };
         self.nodes.push(PrefixTreeNode::NoChild);
-        return new_index;
+        new_index
     }
 }
 
-pub fn lzw_compress(data: &[u8]) -> Vec<u8> {
-    let mut tree = PrefixTree::new(INITIAL_CODE_WIDTH);
-    let mut writer = BitWriter::new();
+/// Streaming LZW encoder that writes the code stream to an underlying writer.
+///
+/// Codes are emitted to the writer as soon as they are produced, so the memory
+/// used by the encoder does not depend on the size of the input. The stream is
+/// only complete after [`finish`](LzwEncoder::finish) has written the
+/// end-of-information code and the final partial byte.
+///
+/// The encoder buffers small chunks of output internally, but it issues many
+/// small writes overall, so wrap unbuffered sinks (files, sockets) in a
+/// [`BufWriter`](std::io::BufWriter).
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::LzwEncoder;
+/// use std::io::Write;
+///
+/// let mut encoder = LzwEncoder::new(Vec::new());
+/// encoder.write_all(b"TOBEORNOTTOBEORTOBEORNOT").unwrap();
+/// let compressed = encoder.finish().unwrap();
+/// # assert!(!compressed.is_empty());
+/// ```
+pub struct LzwEncoder<W>
+where
+    W: Write,
+{
+    writer: BitWriter<W>,
+    tree: PrefixTree,
+    prefix_index: Option<u16>,
+    write_size: u8,
+    size_increase_mask: u32,
+    /// The widest code the encoder writes.
+    widest_code: u8,
+    /// Dictionary size at the maximum code width.
+    max_entry_count: usize,
+    reset_policy: ResetPolicy,
+    dialect: Dialect,
+    /// 1 if the code width increases one code early, otherwise 0.
+    early_change: u32,
+    /// Bit position where the current group of codes started, see [`Dialect::padded_groups`].
+    group_start: u64,
+    /// Number of input bytes consumed so far.
+    bytes_in: u64,
+    /// Input position of the next compression ratio check.
+    checkpoint: u64,
+    /// Best compression ratio seen since the last reset, scaled by 256.
+    best_ratio: u64,
+    /// The Huffman stage the codes are written through, see [`EntropyCoding`].
+    entropy: Option<Box<EntropyEncoder>>,
+}
 
-    let mut bytes = data.iter();
-    let k = bytes.next();
-    if k.is_none() {
-        return vec![];
+impl<W> LzwEncoder<W>
+where
+    W: Write,
+{
+    /// Creates an encoder that writes the compressed stream to `writer`, using
+    /// codes of up to [`DEFAULT_MAX_CODE_WIDTH`] bits.
+    pub fn new(writer: W) -> Self {
+        Self::build(writer, DEFAULT_MAX_CODE_WIDTH, Dialect::PRESS_RS)
     }
 
-    let mut write_size = INITIAL_CODE_WIDTH + 1;
-    let clear_code = 1 << INITIAL_CODE_WIDTH;
-    let end_of_information = (1 << INITIAL_CODE_WIDTH) + 1;
-    let mut size_increase_mask = 1 << write_size;
+    /// Creates an encoder using codes of up to `max_code_width` bits (9 to 16).
+    ///
+    /// Wider codes allow a bigger dictionary, which usually compresses large
+    /// inputs better. The decoder must be created with the same width, see
+    /// [`LzwDecoder::with_max_code_width`](crate::compressor::LzwDecoder::with_max_code_width).
+    pub fn with_max_code_width(writer: W, max_code_width: u8) -> Result<Self, Error> {
+        let options = CompressOptions {
+            max_code_width,
+            ..Default::default()
+        };
+        Self::with_options(writer, &options)
+    }
+
+    /// Creates an encoder using the code width, [`ResetPolicy`], [`BitOrder`],
+    /// early change and [`EntropyCoding`] settings from `options`.
+    ///
+    /// Fails with [`Error::InvalidOptions`] if a Huffman stage is combined
+    /// with [`BitOrder::MsbFirst`].
+    pub fn with_options(writer: W, options: &CompressOptions) -> Result<Self, Error> {
+        Self::with_dialect(writer, options, Dialect::PRESS_RS)
+    }
 
-    writer.write(clear_code, write_size);
+    /// Creates an encoder that writes the code stream of another format.
+    pub(crate) fn with_dialect(
+        writer: W,
+        options: &CompressOptions,
+        dialect: Dialect,
+    ) -> Result<Self, Error> {
+        check_code_width(options.max_code_width)?;
+        check_entropy(options, dialect)?;
+        let mut encoder = Self::build(writer, options.max_code_width, dialect);
+        encoder.reset_policy = options.reset_policy;
+        encoder.writer.bit_order = options.bit_order;
+        encoder.early_change = options.early_change as u32;
+        encoder.entropy = EntropyEncoder::new(options.entropy).map(Box::new);
+        Ok(encoder)
+    }
 
-    let mut prefix_index = *k.unwrap() as u16;
+    fn build(writer: W, max_code_width: u8, dialect: Dialect) -> Self {
+        let write_size = dialect.literal_width + 1;
+        let max_entry_count = 1 << max_code_width;
+        Self {
+            writer: BitWriter::new(writer, BitOrder::LsbFirst),
+            tree: PrefixTree::new(
+                INITIAL_CODE_WIDTH,
+                dialect.first_free_code(),
+                max_entry_count,
+            ),
+            prefix_index: None,
+            write_size,
+            size_increase_mask: 1 << write_size,
+            widest_code: dialect.widest_code(max_code_width),
+            max_entry_count,
+            reset_policy: ResetPolicy::default(),
+            dialect,
+            early_change: 0,
+            group_start: 0,
+            bytes_in: 0,
+            checkpoint: CHECK_GAP,
+            best_ratio: 0,
+            entropy: None,
+        }
+    }
 
-    for byte in bytes {
-        if let Some(child_index) = tree.find_word(prefix_index, *byte) {
-            prefix_index = child_index;
-        } else {
-            let index_of_new_entry = tree.add(prefix_index, *byte);
-            writer.write(prefix_index, write_size);
-            prefix_index = *byte as u16;
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer.output
+    }
 
-            if index_of_new_entry == size_increase_mask {
-                if write_size < MAX_CODE_WIDTH {
-                    write_size += 1;
+    /// Writes the pending code, the end-of-information code and the final
+    /// partial byte, then returns the underlying writer.
+    ///
+    /// An encoder that received no data writes nothing, so empty input is
+    /// encoded as an empty stream.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(prefix_index) = self.prefix_index {
+            self.write_code(prefix_index)?;
+            if self.dialect.has_end_code {
+                self.write_code(self.dialect.end_code())?;
+            }
+        }
+        self.finish_bits()
+    }
+
+    /// Writes `code` with the current width, or through the Huffman stage.
+    fn write_code(&mut self, code: u16) -> io::Result<()> {
+        match &mut self.entropy {
+            Some(entropy) => entropy.write_code(&mut self.writer, code, self.write_size),
+            None => self.writer.write(code, self.write_size),
+        }
+    }
+
+    /// Pads the current group of codes to a multiple of eight codes if the
+    /// dialect requires it. Called before the code width changes.
+    fn end_code_group(&mut self) -> io::Result<()> {
+        if self.dialect.padded_groups {
+            let group_bits = 8 * self.write_size as u64;
+            while !(self.writer.bits_written - self.group_start).is_multiple_of(group_bits) {
+                self.writer.write(0, self.write_size)?;
+            }
+            self.group_start = self.writer.bits_written;
+        }
+        Ok(())
+    }
+
+    /// Decides whether the full dictionary should be discarded.
+    fn should_reset(&mut self) -> bool {
+        match self.reset_policy {
+            ResetPolicy::WhenFull => true,
+            ResetPolicy::OnRatioDrop => {
+                if self.bytes_in < self.checkpoint {
+                    return false;
+                }
+                self.checkpoint = self.bytes_in + CHECK_GAP;
+
+                let bytes_out = (self.writer.bits_written / 8).max(1);
+                let ratio = (self.bytes_in << 8) / bytes_out;
+                if ratio > self.best_ratio {
+                    self.best_ratio = ratio;
+                    false
                 } else {
-                    writer.write(clear_code, MAX_CODE_WIDTH);
-                    write_size = INITIAL_CODE_WIDTH + 1;
-                    tree.reset();
+                    self.best_ratio = 0;
+                    true
+                }
+            }
+        }
+    }
+
+    /// Like [`finish`](Self::finish), but an empty input is encoded as a clear
+    /// code followed by the end-of-information code, so the stream end can be
+    /// found when other data follows it.
+    pub(crate) fn finish_terminated(mut self) -> io::Result<W> {
+        if self.prefix_index.is_none() {
+            self.write_code(self.dialect.clear_code())?;
+            self.write_code(self.dialect.end_code())?;
+        }
+        self.finish()
+    }
+
+    fn finish_bits(mut self) -> io::Result<W> {
+        if let Some(entropy) = &mut self.entropy {
+            entropy.finish(&mut self.writer)?;
+        }
+        self.writer.flush()?;
+        self.writer.output.flush()?;
+        Ok(self.writer.output)
+    }
+}
+
+impl<W> Write for LzwEncoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.dialect.literal_width < INITIAL_CODE_WIDTH {
+            let literal_count = self.dialect.clear_code();
+            if let Some(&byte) = buf.iter().find(|&&byte| byte as u16 >= literal_count) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "value {} does not fit in {}-bit literals",
+                        byte, self.dialect.literal_width
+                    ),
+                ));
+            }
+        }
+
+        let mut bytes = buf.iter();
+
+        let mut prefix_index = match self.prefix_index {
+            Some(index) => index,
+            None => match bytes.next() {
+                Some(&first) => {
+                    if self.dialect.leading_clear {
+                        self.write_code(self.dialect.clear_code())?;
+                    }
+                    self.bytes_in += 1;
+                    first as u16
+                }
+                None => return Ok(0),
+            },
+        };
+
+        for &byte in bytes {
+            self.bytes_in += 1;
+            if let Some(child_index) = self.tree.find_word(prefix_index, byte) {
+                prefix_index = child_index;
+            } else {
+                self.write_code(prefix_index)?;
+
+                let next_index = self.tree.next_index();
+                if next_index as u32 + self.early_change == self.size_increase_mask
+                    && self.write_size < self.widest_code
+                {
+                    // The next word does not fit the current code width
+                    self.end_code_group()?;
+                    self.write_size += 1;
+                    self.size_increase_mask = 1 << self.write_size;
+                }
+
+                if next_index < self.max_entry_count {
+                    self.tree.add(prefix_index, byte);
+                } else if self.should_reset() {
+                    // The dictionary is full at the maximum code width
+                    self.write_code(self.dialect.clear_code())?;
+                    self.end_code_group()?;
+                    self.write_size = self.dialect.literal_width + 1;
+                    self.size_increase_mask = 1 << self.write_size;
+                    self.tree.reset();
                 }
-                size_increase_mask = 1 << write_size;
+                prefix_index = byte as u16;
             }
         }
+
+        self.prefix_index = Some(prefix_index);
+        Ok(buf.len())
     }
-    writer.write(prefix_index, write_size);
-    writer.write(end_of_information, write_size);
 
-    writer.flush();
-    return writer.output;
+    /// Writes all complete bytes produced so far to the underlying writer.
+    ///
+    /// The trailing partial byte stays buffered until [`LzwEncoder::finish`],
+    /// as does the current block of [`EntropyCoding::StaticHuffman`].
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.write_pending()?;
+        self.writer.output.flush()
+    }
+}
+
+/// Fails with [`Error::InvalidOptions`] unless `max_code_width` is in [`CODE_WIDTH_RANGE`].
+pub(crate) fn check_code_width(max_code_width: u8) -> Result<(), Error> {
+    if CODE_WIDTH_RANGE.contains(&max_code_width) {
+        Ok(())
+    } else {
+        Err(Error::InvalidOptions(format!(
+            "maximum code width must be between {} and {} bits, got {}",
+            CODE_WIDTH_RANGE.start(),
+            CODE_WIDTH_RANGE.end(),
+            max_code_width
+        )))
+    }
+}
+
+/// Fails with [`Error::InvalidOptions`] if `options` ask for a Huffman stage
+/// the code stream cannot carry.
+pub(crate) fn check_entropy(options: &CompressOptions, dialect: Dialect) -> Result<(), Error> {
+    if options.entropy == EntropyCoding::None {
+        return Ok(());
+    }
+    if dialect != Dialect::PRESS_RS {
+        return Err(Error::InvalidOptions(
+            "a Huffman stage is only supported in press_rs streams".to_string(),
+        ));
+    }
+    if options.bit_order == BitOrder::MsbFirst {
+        return Err(Error::InvalidOptions(
+            "a Huffman stage requires LSB-first bit order".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub fn lzw_compress(data: &[u8]) -> Vec<u8> {
+    let mut encoder = LzwEncoder::new(Vec::new());
+    encoder
+        .write_all(data)
+        .expect("Writing to a Vec cannot fail");
+    encoder.finish().expect("Writing to a Vec cannot fail")
 }
 
 #[cfg(test)]
@@ -184,14 +577,14 @@ mod tests {
         #[test]
         fn test_write_aligned_bytes() {
             // Arrange
-            let mut writer = BitWriter::new();
+            let mut writer = BitWriter::new(Vec::new(), BitOrder::LsbFirst);
             let val1: u16 = 0xFFF; // 1111 1111 1111
             let val2: u16 = 0x0; // 0000
 
             // Act
-            writer.write(val1, 12);
-            writer.write(val2, 4);
-            writer.flush();
+            writer.write(val1, 12).unwrap();
+            writer.write(val2, 4).unwrap();
+            writer.flush().unwrap();
 
             // Assert
             // Expected layout: [11111111] [00001111]
@@ -203,13 +596,13 @@ mod tests {
         #[test]
         fn test_write_spanning_boundaries() {
             // Arrange
-            let mut writer = BitWriter::new();
+            let mut writer = BitWriter::new(Vec::new(), BitOrder::LsbFirst);
 
             // Act
-            writer.write(0b111, 3);
-            writer.write(0b101, 3);
-            writer.write(0b001, 3);
-            writer.flush();
+            writer.write(0b111, 3).unwrap();
+            writer.write(0b101, 3).unwrap();
+            writer.write(0b001, 3).unwrap();
+            writer.flush().unwrap();
 
             // Assert
             // 0-2: 111 (7)
@@ -224,6 +617,22 @@ mod tests {
             assert_eq!(writer.output[0], 0x6F);
             assert_eq!(writer.output[1], 0x00);
         }
+
+        #[test]
+        fn test_write_msb_first() {
+            // Arrange
+            let mut writer = BitWriter::new(Vec::new(), BitOrder::MsbFirst);
+
+            // Act
+            writer.write(0b111, 3).unwrap();
+            writer.write(0b101, 3).unwrap();
+            writer.write(0b001, 3).unwrap();
+            writer.flush().unwrap();
+
+            // Assert
+            // Codes fill each byte from the top: [111 101 00] [1 0000000]
+            assert_eq!(writer.output, vec![0xF4, 0x80]);
+        }
     }
 
     mod prefix_tree {
@@ -232,7 +641,7 @@ mod tests {
         #[test]
         fn test_add_and_find_sequence() {
             // Arrange
-            let mut tree = PrefixTree::new(8);
+            let mut tree = PrefixTree::new(8, 258, 1 << 12);
             let root_char = b'A' as u16;
             let next_char = b'B';
 
@@ -251,7 +660,7 @@ mod tests {
         #[test]
         fn test_reset_behavior() {
             // Arrange
-            let mut tree = PrefixTree::new(8);
+            let mut tree = PrefixTree::new(8, 258, 1 << 12);
             tree.add(b'A' as u16, b'B');
 
             // Act
@@ -305,4 +714,57 @@ mod tests {
             assert!(output.len() < input.len() / 20); // At least 5%
         }
     }
+
+    mod lzw_encoder {
+        use super::*;
+
+        #[test]
+        fn test_chunked_writes_match_one_shot() {
+            // Arrange
+            let input: Vec<u8> = b"the quick brown fox jumps over the lazy dog "
+                .iter()
+                .cycle()
+                .take(50_000)
+                .copied()
+                .collect();
+            let expected = lzw_compress(&input);
+
+            // Act
+            let mut encoder = LzwEncoder::new(Vec::new());
+            for chunk in input.chunks(7) {
+                encoder.write_all(chunk).unwrap();
+            }
+            let output = encoder.finish().unwrap();
+
+            // Assert
+            assert_eq!(output, expected);
+        }
+
+        #[test]
+        fn test_flush_emits_complete_bytes_only() {
+            // Arrange
+            let input = vec![b'A'; 100_000];
+            let mut encoder = LzwEncoder::new(Vec::new());
+
+            // Act
+            encoder.write_all(&input).unwrap();
+            encoder.flush().unwrap();
+            let flushed = encoder.get_ref().len();
+            let output = encoder.finish().unwrap();
+
+            // Assert
+            assert!(flushed > 0);
+            assert!(flushed < output.len());
+            assert_eq!(&output, &lzw_compress(&input));
+        }
+
+        #[test]
+        fn test_finish_without_data_writes_nothing() {
+            // Act
+            let output = LzwEncoder::new(Vec::new()).finish().unwrap();
+
+            // Assert
+            assert!(output.is_empty());
+        }
+    }
 }
@@ -0,0 +1,1011 @@
+use std::io::{self, Read, Write};
+
+use crate::checksum::Crc32;
+use crate::compressor::bwt::check_block_size;
+use crate::compressor::compress::{check_code_width, check_entropy};
+use crate::compressor::index::write_index;
+use crate::compressor::parallel::{check_parallel_block_size, BlockDecoder, BlockEncoder};
+use crate::compressor::{
+    BitOrder, BwtDecoder, BwtEncoder, CompressOptions, DeflateDecoder, DeflateEncoder, Dialect,
+    EntropyCoding, LzwDecoder, LzwEncoder, BWT_BLOCK_SIZE_RANGE, CODE_WIDTH_RANGE,
+    DEFAULT_BWT_BLOCK_SIZE, DEFAULT_MAX_CODE_WIDTH,
+};
+use crate::packager::IndexEntry;
+use crate::Error;
+
+/// The bytes every press_rs archive starts with.
+pub const MAGIC: [u8; 4] = *b"PRES";
+
+/// The container format version written by this crate.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// The size of the [`StreamHeader`] in bytes.
+pub const HEADER_SIZE: usize = MAGIC.len() + 4;
+
+/// Header flag: the payload is followed by the CRC-32 of the uncompressed data
+/// as 4 little-endian bytes.
+pub const FLAG_CRC32: u8 = 0x01;
+
+/// Header flag: the LZW codes are packed MSB-first, see [`BitOrder::MsbFirst`].
+pub const FLAG_MSB_FIRST: u8 = 0x02;
+
+/// Header flag: the LZW code width increases one code early, see
+/// [`CompressOptions::early_change`].
+pub const FLAG_EARLY_CHANGE: u8 = 0x04;
+
+/// Header flag: the LZW codes are Huffman coded in blocks, see
+/// [`EntropyCoding::StaticHuffman`].
+pub const FLAG_HUFFMAN: u8 = 0x08;
+
+/// Header flag: the LZW codes are coded with an adaptive Huffman code, see
+/// [`EntropyCoding::AdaptiveHuffman`].
+pub const FLAG_ADAPTIVE_HUFFMAN: u8 = 0x10;
+
+/// Header flag: the payload is split into independently compressed blocks,
+/// see [`CompressOptions::parallel_block_size`].
+pub const FLAG_BLOCKS: u8 = 0x20;
+
+/// Header flag: the archive ends with an index of its blocks and entries, see
+/// [`CompressOptions::index`]. Only valid together with [`FLAG_BLOCKS`].
+pub const FLAG_INDEX: u8 = 0x40;
+
+/// Flag bits understood by this version of the format.
+const KNOWN_FLAGS: u8 = FLAG_CRC32
+    | FLAG_MSB_FIRST
+    | FLAG_EARLY_CHANGE
+    | FLAG_HUFFMAN
+    | FLAG_ADAPTIVE_HUFFMAN
+    | FLAG_BLOCKS
+    | FLAG_INDEX;
+
+/// The window size in bits stored in the header of [`Codec::Deflate`] payloads.
+const DEFLATE_WINDOW_BITS: u8 = 15;
+
+/// The compression algorithm used for the payload following the [`StreamHeader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Codec {
+    /// Variable-width LZW, see [`LzwEncoder`](crate::compressor::LzwEncoder).
+    Lzw,
+    /// LZ77 with Huffman coding (RFC 1951), see
+    /// [`DeflateEncoder`](crate::compressor::DeflateEncoder).
+    Deflate,
+    /// Block sorting with the Burrows–Wheeler transform, see
+    /// [`BwtEncoder`](crate::compressor::BwtEncoder).
+    Bwt,
+}
+
+impl Codec {
+    /// Returns the id stored in the stream header.
+    pub fn id(self) -> u8 {
+        match self {
+            Codec::Lzw => 0,
+            Codec::Deflate => 1,
+            Codec::Bwt => 2,
+        }
+    }
+
+    /// Parses a codec id, failing with [`Error::UnsupportedCodec`] for unknown ids.
+    pub fn from_id(id: u8) -> Result<Codec, Error> {
+        match id {
+            0 => Ok(Codec::Lzw),
+            1 => Ok(Codec::Deflate),
+            2 => Ok(Codec::Bwt),
+            _ => Err(Error::UnsupportedCodec(id)),
+        }
+    }
+}
+
+/// The container header written in front of every compressed archive.
+///
+/// The layout is the [`MAGIC`] bytes followed by the format version, the codec
+/// id, a flags byte (see [`FLAG_CRC32`]) and the maximum LZW code width (the
+/// window size in bits for [`Codec::Deflate`], the block size for
+/// [`Codec::Bwt`]). Files that do not start with the magic bytes are rejected
+/// with [`Error::NotAnArchive`], newer formats with [`Error::UnsupportedVersion`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{Codec, StreamHeader};
+///
+/// let mut bytes = Vec::new();
+/// StreamHeader::new(Codec::Lzw).write_to(&mut bytes).unwrap();
+///
+/// let header = StreamHeader::read_from(&mut bytes.as_slice()).unwrap();
+/// assert_eq!(header.codec, Codec::Lzw);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamHeader {
+    /// The container format version.
+    pub version: u8,
+
+    /// The codec the payload is compressed with.
+    pub codec: Codec,
+
+    /// Format flags, see [`FLAG_CRC32`].
+    pub flags: u8,
+
+    /// The maximum bit width of the LZW codes in the payload, the window size
+    /// in bits for [`Codec::Deflate`] or the block size in units of 100 000
+    /// bytes for [`Codec::Bwt`].
+    pub max_code_width: u8,
+}
+
+impl StreamHeader {
+    /// Creates a header for the current format version, with the CRC-32 trailer enabled.
+    pub fn new(codec: Codec) -> StreamHeader {
+        StreamHeader {
+            version: FORMAT_VERSION,
+            codec,
+            flags: FLAG_CRC32,
+            max_code_width: DEFAULT_MAX_CODE_WIDTH,
+        }
+    }
+
+    /// Returns `true` if the payload is followed by a CRC-32 trailer.
+    pub fn has_crc32(&self) -> bool {
+        self.flags & FLAG_CRC32 != 0
+    }
+
+    /// Returns `true` if the payload is split into independently compressed
+    /// blocks.
+    pub fn has_blocks(&self) -> bool {
+        self.flags & FLAG_BLOCKS != 0
+    }
+
+    /// Returns `true` if the archive ends with an index of its blocks and
+    /// entries, see [`IndexedArchive`](crate::compressor::IndexedArchive).
+    pub fn has_index(&self) -> bool {
+        self.flags & FLAG_INDEX != 0
+    }
+
+    /// Returns the options needed to decode the payload. The size of
+    /// independently compressed blocks is not recorded.
+    pub fn compress_options(&self) -> CompressOptions {
+        CompressOptions {
+            max_code_width: self.max_code_width,
+            bit_order: if self.flags & FLAG_MSB_FIRST != 0 {
+                BitOrder::MsbFirst
+            } else {
+                BitOrder::LsbFirst
+            },
+            early_change: self.flags & FLAG_EARLY_CHANGE != 0,
+            entropy: if self.flags & FLAG_HUFFMAN != 0 {
+                EntropyCoding::StaticHuffman
+            } else if self.flags & FLAG_ADAPTIVE_HUFFMAN != 0 {
+                EntropyCoding::AdaptiveHuffman
+            } else {
+                EntropyCoding::None
+            },
+            bwt_block_size: match self.codec {
+                Codec::Bwt => self.max_code_width,
+                _ => DEFAULT_BWT_BLOCK_SIZE,
+            },
+            codec: self.codec,
+            ..Default::default()
+        }
+    }
+
+    /// Serializes the header.
+    pub fn to_bytes(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[..MAGIC.len()].copy_from_slice(&MAGIC);
+        bytes[MAGIC.len()] = self.version;
+        bytes[MAGIC.len() + 1] = self.codec.id();
+        bytes[MAGIC.len() + 2] = self.flags;
+        bytes[MAGIC.len() + 3] = self.max_code_width;
+        bytes
+    }
+
+    /// Writes the header to `writer`.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), Error> {
+        writer.write_all(&self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Reads and validates a header from `reader`, leaving it at the start of the payload.
+    pub fn read_from(reader: &mut impl Read) -> Result<StreamHeader, Error> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        reader.take(HEADER_SIZE as u64).read_to_end(&mut bytes)?;
+
+        if bytes.len() < MAGIC.len() || bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::NotAnArchive);
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(Error::CorruptCodeStream(
+                "stream header is truncated".to_string(),
+            ));
+        }
+
+        let version = bytes[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let codec = Codec::from_id(bytes[MAGIC.len() + 1])?;
+        let flags = bytes[MAGIC.len() + 2];
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(Error::CorruptCodeStream(format!(
+                "unknown stream header flags {:#04x}",
+                flags
+            )));
+        }
+        if flags & FLAG_HUFFMAN != 0 && flags & FLAG_ADAPTIVE_HUFFMAN != 0 {
+            return Err(Error::CorruptCodeStream(
+                "both Huffman stages are selected".to_string(),
+            ));
+        }
+        if flags & FLAG_INDEX != 0 && flags & FLAG_BLOCKS == 0 {
+            return Err(Error::CorruptCodeStream(
+                "index of a payload without blocks".to_string(),
+            ));
+        }
+        let max_code_width = bytes[MAGIC.len() + 3];
+        match codec {
+            Codec::Lzw if !CODE_WIDTH_RANGE.contains(&max_code_width) => {
+                return Err(Error::CorruptCodeStream(format!(
+                    "invalid maximum code width {}",
+                    max_code_width
+                )));
+            }
+            Codec::Deflate if max_code_width != DEFLATE_WINDOW_BITS => {
+                return Err(Error::CorruptCodeStream(format!(
+                    "invalid DEFLATE window size of {} bits",
+                    max_code_width
+                )));
+            }
+            Codec::Bwt if !BWT_BLOCK_SIZE_RANGE.contains(&max_code_width) => {
+                return Err(Error::CorruptCodeStream(format!(
+                    "invalid BWT block size {}",
+                    max_code_width
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(StreamHeader {
+            version,
+            codec,
+            flags,
+            max_code_width,
+        })
+    }
+}
+
+/// The encoder of the codec selected in [`CompressOptions::codec`].
+enum PayloadEncoder<W>
+where
+    W: Write,
+{
+    Lzw(LzwEncoder<W>),
+    Deflate(Box<DeflateEncoder<W>>),
+    Bwt(BwtEncoder<W>),
+    Blocks(BlockEncoder<W>),
+}
+
+impl<W> PayloadEncoder<W>
+where
+    W: Write,
+{
+    /// Creates the encoder of `options.codec`, splitting the payload into
+    /// blocks if `options.parallel_block_size` is set.
+    fn new(writer: W, options: &CompressOptions) -> Result<Self, Error> {
+        if options.parallel_block_size.is_some() {
+            return Ok(PayloadEncoder::Blocks(BlockEncoder::new(writer, options)?));
+        }
+        Ok(match options.codec {
+            Codec::Lzw => PayloadEncoder::Lzw(LzwEncoder::with_options(writer, options)?),
+            Codec::Deflate => PayloadEncoder::Deflate(Box::new(DeflateEncoder::new(writer))),
+            Codec::Bwt => {
+                PayloadEncoder::Bwt(BwtEncoder::with_block_size(writer, options.bwt_block_size)?)
+            }
+        })
+    }
+
+    /// Finishes the payload and returns the underlying writer.
+    fn finish(self) -> Result<W, Error> {
+        Ok(match self {
+            PayloadEncoder::Lzw(encoder) => encoder.finish_terminated()?,
+            PayloadEncoder::Deflate(encoder) => encoder.finish()?,
+            PayloadEncoder::Bwt(encoder) => encoder.finish()?,
+            PayloadEncoder::Blocks(encoder) => encoder.finish()?,
+        })
+    }
+}
+
+impl<W> Write for PayloadEncoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            PayloadEncoder::Lzw(encoder) => encoder.write(buf),
+            PayloadEncoder::Deflate(encoder) => encoder.write(buf),
+            PayloadEncoder::Bwt(encoder) => encoder.write(buf),
+            PayloadEncoder::Blocks(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            PayloadEncoder::Lzw(encoder) => encoder.flush(),
+            PayloadEncoder::Deflate(encoder) => encoder.flush(),
+            PayloadEncoder::Bwt(encoder) => encoder.flush(),
+            PayloadEncoder::Blocks(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// The decoder of the codec recorded in the [`StreamHeader`].
+enum PayloadDecoder<R>
+where
+    R: Read,
+{
+    Lzw(LzwDecoder<R>),
+    Deflate(DeflateDecoder<R>),
+    Bwt(BwtDecoder<R>),
+    Blocks(BlockDecoder<R>),
+}
+
+impl<R> PayloadDecoder<R>
+where
+    R: Read,
+{
+    /// Creates the decoder of the codec in `header`.
+    fn new(reader: R, header: &StreamHeader) -> Result<Self, Error> {
+        Ok(match header.codec {
+            Codec::Lzw => PayloadDecoder::Lzw(LzwDecoder::with_options(
+                reader,
+                &header.compress_options(),
+            )?),
+            Codec::Deflate => PayloadDecoder::Deflate(DeflateDecoder::new(reader)),
+            Codec::Bwt => PayloadDecoder::Bwt(BwtDecoder::new(reader)),
+        })
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    fn get_mut(&mut self) -> &mut R {
+        match self {
+            PayloadDecoder::Lzw(decoder) => decoder.get_mut(),
+            PayloadDecoder::Deflate(decoder) => decoder.get_mut(),
+            PayloadDecoder::Bwt(decoder) => decoder.get_mut(),
+            PayloadDecoder::Blocks(decoder) => decoder.get_mut(),
+        }
+    }
+}
+
+impl<R> Read for PayloadDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            PayloadDecoder::Lzw(decoder) => decoder.read(buf),
+            PayloadDecoder::Deflate(decoder) => decoder.read(buf),
+            PayloadDecoder::Bwt(decoder) => decoder.read(buf),
+            PayloadDecoder::Blocks(decoder) => decoder.read(buf),
+        }
+    }
+}
+
+/// Compresses one independent block with the codec in `options`.
+pub(crate) fn encode_block(data: &[u8], options: &CompressOptions) -> Result<Vec<u8>, Error> {
+    let mut encoder = PayloadEncoder::new(Vec::new(), options)?;
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Decompresses one independent block written with the codec in `header`.
+pub(crate) fn decode_block(data: &[u8], header: &StreamHeader) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    PayloadDecoder::new(data, header)?.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Streaming compressor writing a complete archive: the [`StreamHeader`], the
+/// compressed payload and the CRC-32 trailer.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{decompress_raw, Compressor};
+/// use std::io::Write;
+///
+/// let mut compressor = Compressor::new(Vec::new()).unwrap();
+/// compressor.write_all(b"hello hello hello").unwrap();
+/// let archive = compressor.finish().unwrap();
+///
+/// assert_eq!(decompress_raw(&archive).unwrap(), b"hello hello hello");
+/// ```
+pub struct Compressor<W>
+where
+    W: Write,
+{
+    encoder: PayloadEncoder<W>,
+    crc: Crc32,
+    index: bool,
+}
+
+impl<W> Compressor<W>
+where
+    W: Write,
+{
+    /// Writes the stream header to `writer` and returns a compressor for the payload.
+    pub fn new(writer: W) -> Result<Self, Error> {
+        Self::with_options(writer, &CompressOptions::default())
+    }
+
+    /// Like [`new`](Self::new), but compresses according to `options`.
+    pub fn with_options(mut writer: W, options: &CompressOptions) -> Result<Self, Error> {
+        let mut header = StreamHeader::new(options.codec);
+        match options.codec {
+            Codec::Lzw => {
+                header.max_code_width = options.max_code_width;
+                if options.bit_order == BitOrder::MsbFirst {
+                    header.flags |= FLAG_MSB_FIRST;
+                }
+                if options.early_change {
+                    header.flags |= FLAG_EARLY_CHANGE;
+                }
+                header.flags |= match options.entropy {
+                    EntropyCoding::None => 0,
+                    EntropyCoding::StaticHuffman => FLAG_HUFFMAN,
+                    EntropyCoding::AdaptiveHuffman => FLAG_ADAPTIVE_HUFFMAN,
+                };
+                // Validate the options before anything is written
+                check_code_width(options.max_code_width)?;
+                check_entropy(options, Dialect::PRESS_RS)?;
+            }
+            Codec::Deflate => header.max_code_width = DEFLATE_WINDOW_BITS,
+            Codec::Bwt => {
+                header.max_code_width = options.bwt_block_size;
+                check_block_size(options.bwt_block_size)?;
+            }
+        }
+        if let Some(block_size) = options.parallel_block_size {
+            check_parallel_block_size(block_size)?;
+            header.flags |= FLAG_BLOCKS;
+        }
+        if options.index {
+            if options.parallel_block_size.is_none() {
+                return Err(Error::InvalidOptions(
+                    "the index needs a parallel block size".to_string(),
+                ));
+            }
+            header.flags |= FLAG_INDEX;
+        }
+        header.write_to(&mut writer)?;
+
+        let encoder = PayloadEncoder::new(writer, options)?;
+        Ok(Self {
+            encoder,
+            crc: Crc32::new(),
+            index: options.index,
+        })
+    }
+
+    /// Finishes the payload, writes the CRC-32 trailer and returns the underlying writer.
+    ///
+    /// With [`CompressOptions::index`], the trailer is followed by an index of
+    /// the blocks without any entries.
+    pub fn finish(self) -> Result<W, Error> {
+        self.finish_with_entries(&[])
+    }
+
+    /// Like [`finish`](Self::finish), but records `entries` in the trailing
+    /// index, e.g. the index returned by
+    /// [`ArchiveWriter::finish_with_index`](crate::packager::ArchiveWriter::finish_with_index).
+    ///
+    /// Fails with [`Error::InvalidOptions`] if entries are passed to a
+    /// compressor without [`CompressOptions::index`].
+    pub fn finish_with_entries(self, entries: &[IndexEntry]) -> Result<W, Error> {
+        if !self.index && !entries.is_empty() {
+            return Err(Error::InvalidOptions(
+                "entries can only be recorded with an index".to_string(),
+            ));
+        }
+        let crc = self.crc.finalize();
+        let mut writer = match self.encoder {
+            PayloadEncoder::Blocks(encoder) if self.index => {
+                let (mut writer, payload_size, blocks) = encoder.finish_with_blocks()?;
+                writer.write_all(&crc.to_le_bytes())?;
+                // The index starts right after the checksum trailer
+                let index_offset = (HEADER_SIZE + 4) as u64 + payload_size;
+                write_index(&mut writer, index_offset, &blocks, entries)?;
+                writer
+            }
+            encoder => {
+                let mut writer = encoder.finish()?;
+                writer.write_all(&crc.to_le_bytes())?;
+                writer
+            }
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W> Write for Compressor<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.encoder.write(buf)?;
+        self.crc.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Streaming decompressor reading a complete archive: it validates the
+/// [`StreamHeader`], decodes the payload and, once the payload has been read to
+/// the end, verifies the CRC-32 trailer.
+///
+/// A mismatch is reported as an [`io::Error`] wrapping [`Error::ChecksumMismatch`].
+pub struct Decompressor<R>
+where
+    R: Read,
+{
+    header: StreamHeader,
+    decoder: PayloadDecoder<R>,
+    crc: Crc32,
+    verified: bool,
+}
+
+impl<R> Decompressor<R>
+where
+    R: Read,
+{
+    /// Reads and validates the stream header from `reader`.
+    ///
+    /// Payloads split into blocks are decompressed on as many threads as
+    /// [`std::thread::available_parallelism`] reports.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        Self::with_threads(reader, 0)
+    }
+
+    /// Like [`new`](Self::new), but decompresses payloads split into blocks on
+    /// `threads` threads (the available parallelism if 0).
+    pub fn with_threads(mut reader: R, threads: usize) -> Result<Self, Error> {
+        let header = StreamHeader::read_from(&mut reader)?;
+        let decoder = if header.has_blocks() {
+            PayloadDecoder::Blocks(BlockDecoder::new(reader, header, threads))
+        } else {
+            PayloadDecoder::new(reader, &header)?
+        };
+        Ok(Self {
+            header,
+            decoder,
+            crc: Crc32::new(),
+            verified: false,
+        })
+    }
+
+    /// Returns the header of the archive.
+    pub fn header(&self) -> &StreamHeader {
+        &self.header
+    }
+
+    fn verify_trailer(&mut self) -> Result<(), Error> {
+        self.verified = true;
+        if !self.header.has_crc32() {
+            return Ok(());
+        }
+
+        let mut trailer = [0u8; 4];
+        self.decoder
+            .get_mut()
+            .read_exact(&mut trailer)
+            .map_err(|e| {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    Error::CorruptCodeStream("checksum trailer is missing".to_string())
+                } else {
+                    Error::Io(e)
+                }
+            })?;
+        if u32::from_le_bytes(trailer) != self.crc.finalize() {
+            return Err(Error::ChecksumMismatch { entry: None });
+        }
+        Ok(())
+    }
+}
+
+impl<R> Read for Decompressor<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.decoder.read(buf)?;
+        self.crc.update(&buf[..read]);
+        if read == 0 && !buf.is_empty() && !self.verified {
+            self.verify_trailer()?;
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::compress_raw_with_options;
+
+    #[test]
+    fn test_header_round_trip() {
+        // Arrange
+        let header = StreamHeader::new(Codec::Lzw);
+
+        // Act
+        let bytes = header.to_bytes();
+        let decoded = StreamHeader::read_from(&mut &bytes[..]).unwrap();
+
+        // Assert
+        assert_eq!(&bytes[..4], b"PRES");
+        assert_eq!(decoded, header);
+    }
+
+    #[test]
+    fn test_foreign_data_is_not_an_archive() {
+        for data in [&b""[..], b"PR", b"PK\x03\x04 zip file", &[0x01, 0x00, 0x01]] {
+            // Act
+            let result = StreamHeader::read_from(&mut &data[..]);
+
+            // Assert
+            assert!(matches!(result, Err(Error::NotAnArchive)), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn test_newer_version_is_unsupported() {
+        // Arrange
+        let mut bytes = StreamHeader::new(Codec::Lzw).to_bytes();
+        bytes[4] = FORMAT_VERSION + 1;
+
+        // Act
+        let result = StreamHeader::read_from(&mut &bytes[..]);
+
+        // Assert
+        assert!(matches!(result, Err(Error::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1));
+    }
+
+    #[test]
+    fn test_unknown_codec_and_flags_are_rejected() {
+        // Arrange
+        let mut unknown_codec = StreamHeader::new(Codec::Lzw).to_bytes();
+        unknown_codec[5] = 0xEE;
+        let mut unknown_flags = StreamHeader::new(Codec::Lzw).to_bytes();
+        unknown_flags[6] |= 0x80;
+
+        // Act
+        let codec_result = StreamHeader::read_from(&mut &unknown_codec[..]);
+        let flags_result = StreamHeader::read_from(&mut &unknown_flags[..]);
+
+        // Assert
+        assert!(matches!(codec_result, Err(Error::UnsupportedCodec(0xEE))));
+        assert!(matches!(flags_result, Err(Error::CorruptCodeStream(_))));
+    }
+
+    #[test]
+    fn test_code_width_is_recorded() {
+        // Arrange
+        let options = CompressOptions {
+            max_code_width: 16,
+            ..Default::default()
+        };
+        let mut compressor = Compressor::with_options(Vec::new(), &options).unwrap();
+        compressor.write_all(b"wide codes").unwrap();
+        let archive = compressor.finish().unwrap();
+        let mut invalid_width = StreamHeader::new(Codec::Lzw).to_bytes();
+        invalid_width[7] = 17;
+
+        // Act
+        let header = StreamHeader::read_from(&mut archive.as_slice()).unwrap();
+        let invalid_result = StreamHeader::read_from(&mut &invalid_width[..]);
+
+        // Assert
+        assert_eq!(header.max_code_width, 16);
+        assert!(matches!(invalid_result, Err(Error::CorruptCodeStream(_))));
+    }
+
+    #[test]
+    fn test_bit_order_and_early_change_are_recorded() {
+        // Arrange
+        let text = b"TIFF style codes in a press_rs archive ".repeat(200);
+        let mut compressor =
+            Compressor::with_options(Vec::new(), &CompressOptions::tiff()).unwrap();
+        compressor.write_all(&text).unwrap();
+        let archive = compressor.finish().unwrap();
+
+        // Act
+        let mut decompressor = Decompressor::new(archive.as_slice()).unwrap();
+        let mut output = Vec::new();
+        decompressor.read_to_end(&mut output).unwrap();
+
+        // Assert
+        let options = decompressor.header().compress_options();
+        assert_eq!(options.bit_order, BitOrder::MsbFirst);
+        assert!(options.early_change);
+        assert_eq!(output, text);
+    }
+
+    #[test]
+    fn test_deflate_codec_round_trip() {
+        // Arrange
+        let text = b"a press_rs archive compressed with DEFLATE ".repeat(200);
+        let options = CompressOptions {
+            codec: Codec::Deflate,
+            ..Default::default()
+        };
+        let mut compressor = Compressor::with_options(Vec::new(), &options).unwrap();
+        compressor.write_all(&text).unwrap();
+        let archive = compressor.finish().unwrap();
+
+        // Act
+        let mut decompressor = Decompressor::new(archive.as_slice()).unwrap();
+        let mut output = Vec::new();
+        decompressor.read_to_end(&mut output).unwrap();
+
+        // Assert
+        assert_eq!(decompressor.header().codec, Codec::Deflate);
+        assert_eq!(archive[5], 1);
+        assert_eq!(output, text);
+        let crc = u32::from_le_bytes(archive[archive.len() - 4..].try_into().unwrap());
+        assert_eq!(crc, crate::checksum::crc32(&text));
+    }
+
+    #[test]
+    fn test_invalid_deflate_window_is_rejected() {
+        // Arrange
+        let mut bytes = StreamHeader::new(Codec::Deflate).to_bytes();
+        bytes[7] = 12;
+
+        // Act
+        let result = StreamHeader::read_from(&mut &bytes[..]);
+
+        // Assert
+        assert!(matches!(result, Err(Error::CorruptCodeStream(_))));
+    }
+
+    #[test]
+    fn test_bwt_codec_round_trip() {
+        // Arrange
+        let text = b"a press_rs archive sorted into blocks by the BWT ".repeat(200);
+        let options = CompressOptions {
+            codec: Codec::Bwt,
+            bwt_block_size: 3,
+            ..Default::default()
+        };
+        let mut compressor = Compressor::with_options(Vec::new(), &options).unwrap();
+        compressor.write_all(&text).unwrap();
+        let archive = compressor.finish().unwrap();
+
+        // Act
+        let mut decompressor = Decompressor::new(archive.as_slice()).unwrap();
+        let mut output = Vec::new();
+        decompressor.read_to_end(&mut output).unwrap();
+
+        // Assert
+        assert_eq!(decompressor.header().codec, Codec::Bwt);
+        assert_eq!(decompressor.header().compress_options().bwt_block_size, 3);
+        assert_eq!(&archive[5..8], &[2, FLAG_CRC32, 3]);
+        assert_eq!(output, text);
+    }
+
+    #[test]
+    fn test_invalid_bwt_block_size_is_rejected() {
+        // Arrange
+        let mut bytes = StreamHeader::new(Codec::Bwt).to_bytes();
+        bytes[7] = 10;
+        let options = CompressOptions {
+            codec: Codec::Bwt,
+            bwt_block_size: 0,
+            ..Default::default()
+        };
+
+        // Act
+        let read = StreamHeader::read_from(&mut &bytes[..]);
+        let written = Compressor::with_options(Vec::new(), &options);
+
+        // Assert
+        assert!(matches!(read, Err(Error::CorruptCodeStream(_))));
+        assert!(matches!(written, Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn test_parallel_blocks_round_trip() {
+        // Arrange
+        let text = b"an archive compressed in independent blocks ".repeat(2000);
+        let options = CompressOptions {
+            parallel_block_size: Some(8192),
+            threads: 4,
+            ..Default::default()
+        };
+        let archive = compress_raw_with_options(&text, &options).unwrap();
+
+        // Act
+        let mut decompressor = Decompressor::with_threads(archive.as_slice(), 2).unwrap();
+        let mut output = Vec::new();
+        decompressor.read_to_end(&mut output).unwrap();
+
+        // Assert
+        assert!(decompressor.header().has_blocks());
+        assert_eq!(archive[6], FLAG_CRC32 | FLAG_BLOCKS);
+        assert_eq!(output, text);
+        let crc = u32::from_le_bytes(archive[archive.len() - 4..].try_into().unwrap());
+        assert_eq!(crc, crate::checksum::crc32(&text));
+    }
+
+    #[test]
+    fn test_damaged_trailer_after_blocks_is_checksum_mismatch() {
+        // Arrange
+        let text = b"0123456789".repeat(5000);
+        let options = CompressOptions {
+            codec: Codec::Deflate,
+            parallel_block_size: Some(4096),
+            ..Default::default()
+        };
+        let mut archive = compress_raw_with_options(&text, &options).unwrap();
+        let last = archive.len() - 1;
+        archive[last] ^= 0xFF;
+
+        // Act
+        let result = Decompressor::new(archive.as_slice())
+            .unwrap()
+            .read_to_end(&mut Vec::new());
+
+        // Assert
+        assert!(matches!(
+            Error::from(result.unwrap_err()),
+            Error::ChecksumMismatch { entry: None }
+        ));
+    }
+
+    #[test]
+    fn test_huffman_stages_round_trip() {
+        // Arrange
+        let words = [
+            "lzw", "codes", "with", "a", "huffman", "stage", "in", "blocks", "of",
+        ];
+        // Words picked by a linear congruential generator, so the text does not repeat
+        let mut state = 1u32;
+        let text: Vec<u8> = (0..20_000)
+            .flat_map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                [words[(state >> 16) as usize % words.len()], " "]
+            })
+            .collect::<String>()
+            .into_bytes();
+
+        for (entropy, flag) in [
+            (EntropyCoding::StaticHuffman, FLAG_HUFFMAN),
+            (EntropyCoding::AdaptiveHuffman, FLAG_ADAPTIVE_HUFFMAN),
+        ] {
+            let options = CompressOptions {
+                entropy,
+                ..Default::default()
+            };
+
+            // Act
+            let archive = compress_raw_with_options(&text, &options).unwrap();
+            let mut decompressor = Decompressor::new(archive.as_slice()).unwrap();
+            let mut output = Vec::new();
+            decompressor.read_to_end(&mut output).unwrap();
+
+            // Assert
+            assert_eq!(decompressor.header().flags & flag, flag);
+            assert_eq!(decompressor.header().compress_options().entropy, entropy);
+            assert_eq!(output, text);
+        }
+    }
+
+    #[test]
+    fn test_huffman_stages_shrink_random_data() {
+        // Arrange
+        // Plain LZW spends more than 8 bits on each literal of random data
+        let data: Vec<u8> = (0..100_000).map(|_| rand::random()).collect();
+        let plain = compress_raw_with_options(&data, &CompressOptions::default()).unwrap();
+
+        for entropy in [EntropyCoding::StaticHuffman, EntropyCoding::AdaptiveHuffman] {
+            let options = CompressOptions {
+                entropy,
+                ..Default::default()
+            };
+
+            // Act
+            let archive = compress_raw_with_options(&data, &options).unwrap();
+
+            // Assert
+            assert!(archive.len() < plain.len() * 9 / 10, "{:?}", entropy);
+        }
+    }
+
+    #[test]
+    fn test_huffman_stage_needs_lsb_first_codes() {
+        // Arrange
+        let options = CompressOptions {
+            entropy: EntropyCoding::AdaptiveHuffman,
+            ..CompressOptions::tiff()
+        };
+
+        // Act
+        let result = Compressor::with_options(Vec::new(), &options);
+
+        // Assert
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn test_both_huffman_flags_are_rejected() {
+        // Arrange
+        let mut bytes = StreamHeader::new(Codec::Lzw).to_bytes();
+        bytes[6] |= FLAG_HUFFMAN | FLAG_ADAPTIVE_HUFFMAN;
+
+        // Act
+        let result = StreamHeader::read_from(&mut &bytes[..]);
+
+        // Assert
+        assert!(matches!(result, Err(Error::CorruptCodeStream(_))));
+    }
+
+    mod checksum_trailer {
+        use super::*;
+        use std::io::Cursor;
+
+        fn compress(data: &[u8]) -> Vec<u8> {
+            let mut compressor = Compressor::new(Vec::new()).unwrap();
+            compressor.write_all(data).unwrap();
+            compressor.finish().unwrap()
+        }
+
+        fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+            let mut output = Vec::new();
+            Decompressor::new(Cursor::new(data))?.read_to_end(&mut output)?;
+            Ok(output)
+        }
+
+        #[test]
+        fn test_round_trip_with_trailer() {
+            for input in [&b""[..], b"a", b"TOBEORNOTTOBEORTOBEORNOT"] {
+                // Act
+                let archive = compress(input);
+
+                // Assert
+                assert_eq!(decompress(&archive).unwrap(), input);
+                let crc = u32::from_le_bytes(archive[archive.len() - 4..].try_into().unwrap());
+                assert_eq!(crc, crate::checksum::crc32(input));
+            }
+        }
+
+        #[test]
+        fn test_damaged_trailer_is_checksum_mismatch() {
+            // Arrange
+            let mut archive = compress(b"some payload");
+            let last = archive.len() - 1;
+            archive[last] ^= 0x01;
+
+            // Act
+            let result = decompress(&archive);
+
+            // Assert
+            assert!(matches!(
+                result,
+                Err(Error::ChecksumMismatch { entry: None })
+            ));
+        }
+
+        #[test]
+        fn test_missing_trailer_is_corrupt() {
+            // Arrange
+            let mut archive = compress(b"some payload");
+            archive.truncate(archive.len() - 4);
+
+            // Act
+            let result = decompress(&archive);
+
+            // Assert
+            assert!(matches!(result, Err(Error::CorruptCodeStream(_))));
+        }
+    }
+}
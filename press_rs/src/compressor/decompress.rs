@@ -1,13 +1,16 @@
 use std::{
     cmp::Ordering,
-    fs::File,
-    io::{BufReader, Read},
-    path::Path,
+    io::{ErrorKind, Read},
 };
 
-use crate::compressor::{INITIAL_CODE_WIDTH, MAX_CODE_WIDTH, MAX_ENTRY_COUNT};
+use crate::compressor::compress::{check_code_width, check_entropy};
+use crate::compressor::entropy::EntropyDecoder;
+use crate::compressor::{
+    BitOrder, CompressOptions, Dialect, DEFAULT_MAX_CODE_WIDTH, INITIAL_CODE_WIDTH,
+};
+use crate::Error;
 
-struct BitReader<R>
+pub(crate) struct BitReader<R>
 where
     R: Read,
 {
@@ -15,154 +18,414 @@ where
     buffer: u32,
     read_buffer: [u8; 1],
     cursor: u8,
+    bit_order: BitOrder,
 }
 
 impl<R> BitReader<R>
 where
     R: Read,
 {
-    fn new(read: R) -> Self {
+    pub(crate) fn new(read: R, bit_order: BitOrder) -> Self {
         Self {
             read,
             buffer: 0,
             read_buffer: [0; 1],
             cursor: 0,
+            bit_order,
         }
     }
 
     #[inline(always)]
-    fn read_one(&mut self, width: u8) -> Result<u16, std::io::Error> {
+    pub(crate) fn read_one(&mut self, width: u8) -> Result<u16, std::io::Error> {
         while self.cursor < width {
             match self.read.read_exact(&mut self.read_buffer[..]) {
                 Ok(_) => {}
                 Err(e) => return Err(e),
             }
-            self.buffer |= (self.read_buffer[0] as u32) << self.cursor;
+            let byte = self.read_buffer[0] as u32;
+            self.buffer = match self.bit_order {
+                BitOrder::LsbFirst => self.buffer | byte << self.cursor,
+                BitOrder::MsbFirst => self.buffer << 8 | byte,
+            };
             self.cursor += 8;
         }
 
         let mask = (1 << width) - 1;
-        let data = (self.buffer & mask) as u16;
-        self.buffer >>= width;
         self.cursor -= width;
-        Ok(data)
+        let data = match self.bit_order {
+            BitOrder::LsbFirst => {
+                let data = self.buffer & mask;
+                self.buffer >>= width;
+                data
+            }
+            // The unread bits are the lowest `cursor` bits of the buffer
+            BitOrder::MsbFirst => {
+                let data = (self.buffer >> self.cursor) & mask;
+                self.buffer &= (1 << self.cursor) - 1;
+                data
+            }
+        };
+        Ok(data as u16)
+    }
+
+    /// Discards the bits left in the current byte.
+    pub(crate) fn align_to_byte(&mut self) {
+        self.buffer = 0;
+        self.cursor = 0;
+    }
+
+    /// Reads whole bytes after [`align_to_byte`](Self::align_to_byte).
+    pub(crate) fn read_bytes(&mut self, buf: &mut [u8]) -> Result<(), std::io::Error> {
+        debug_assert_eq!(self.cursor, 0, "the reader is not byte aligned");
+        self.read.read_exact(buf)
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.read
+    }
+
+    pub(crate) fn into_inner(self) -> R {
+        self.read
     }
 }
 
-/// Core LZW decompression logic that works with any Read source
-pub fn lzw_decompress_from_reader<R: Read>(reader: R) -> Vec<u8> {
-    const MAX_TABLE_SIZE: usize = MAX_ENTRY_COUNT - 1;
-    const MAX_STACK_SIZE: usize = MAX_TABLE_SIZE;
+/// Streaming LZW decoder that reads a code stream from an underlying reader.
+///
+/// Codes are decoded lazily: each call to [`read`](Read::read) decodes only as
+/// many codes as needed to fill the caller's buffer, and a word that does not
+/// fit is kept and emitted by the next call. Decoding stops at the
+/// end-of-information code.
+///
+/// The decoder pulls the input one byte at a time, so wrap unbuffered sources
+/// (files, sockets) in a [`BufReader`](std::io::BufReader).
+///
+/// Malformed input is reported as an [`std::io::Error`] of kind
+/// [`InvalidData`](ErrorKind::InvalidData) wrapping [`Error::CorruptCodeStream`];
+/// converting it back with `Error::from` recovers the original variant.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{LzwDecoder, LzwEncoder};
+/// use std::io::{Read, Write};
+///
+/// let mut encoder = LzwEncoder::new(Vec::new());
+/// encoder.write_all(b"TOBEORNOTTOBEORTOBEORNOT").unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// let mut decoder = LzwDecoder::new(compressed.as_slice());
+/// let mut decompressed = String::new();
+/// decoder.read_to_string(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, "TOBEORNOTTOBEORTOBEORNOT");
+/// ```
+pub struct LzwDecoder<R>
+where
+    R: Read,
+{
+    reader: BitReader<R>,
+    prefix: Vec<u16>,
+    suffix: Vec<u8>,
+    length: Vec<usize>,
+    decoding_stack: Vec<u8>,
+    /// Length of the last decoded word held in `decoding_stack`.
+    word_length: usize,
+    /// Number of bytes of the last decoded word already returned to the caller.
+    word_position: usize,
+    read_size: u8,
+    size_increase_mask: u32,
+    /// The widest code in the stream.
+    widest_code: u8,
+    /// Number of dictionary entries at the maximum code width.
+    table_size: u32,
+    next_index: u32,
+    previous_code: Option<u16>,
+    dialect: Dialect,
+    /// 1 if the code width increases one code early, otherwise 0.
+    early_change: u32,
+    /// Number of codes read since the code width last changed, see [`Dialect::padded_groups`].
+    codes_in_group: u32,
+    stream_started: bool,
+    finished: bool,
+    /// The Huffman stage the codes are read through, see
+    /// [`EntropyCoding`](crate::compressor::EntropyCoding).
+    entropy: Option<EntropyDecoder>,
+}
 
-    let mut prefix: [u16; MAX_TABLE_SIZE] = [0; MAX_TABLE_SIZE];
-    let mut suffix: [u8; MAX_TABLE_SIZE] = [0; MAX_TABLE_SIZE];
-    let mut length: [usize; MAX_TABLE_SIZE] = [0; MAX_TABLE_SIZE];
-    let mut decoding_stack: [u8; MAX_STACK_SIZE] = [0; MAX_STACK_SIZE];
+impl<R> LzwDecoder<R>
+where
+    R: Read,
+{
+    /// Creates a decoder that reads the compressed stream from `reader`, using
+    /// codes of up to [`DEFAULT_MAX_CODE_WIDTH`] bits.
+    pub fn new(reader: R) -> Self {
+        Self::build(reader, DEFAULT_MAX_CODE_WIDTH, Dialect::PRESS_RS)
+    }
 
-    let mut reader = BitReader::new(reader);
-    let mut output = Vec::new();
+    /// Creates a decoder for a stream written with codes of up to
+    /// `max_code_width` bits (9 to 16).
+    pub fn with_max_code_width(reader: R, max_code_width: u8) -> Result<Self, Error> {
+        Self::with_dialect(reader, max_code_width, Dialect::PRESS_RS)
+    }
+
+    /// Creates a decoder for a stream written with the given code width,
+    /// [`BitOrder`], early change and
+    /// [`EntropyCoding`](crate::compressor::EntropyCoding) settings. The reset
+    /// policy of `options` does not matter for decoding.
+    ///
+    /// Use [`CompressOptions::tiff`] to decode TIFF strips and PDF `LZWDecode` streams.
+    pub fn with_options(reader: R, options: &CompressOptions) -> Result<Self, Error> {
+        check_entropy(options, Dialect::PRESS_RS)?;
+        let mut decoder = Self::with_dialect(reader, options.max_code_width, Dialect::PRESS_RS)?;
+        decoder.reader.bit_order = options.bit_order;
+        decoder.early_change = options.early_change as u32;
+        decoder.entropy = EntropyDecoder::new(options.entropy);
+        Ok(decoder)
+    }
+
+    /// Creates a decoder that reads the code stream of another format.
+    pub(crate) fn with_dialect(
+        reader: R,
+        max_code_width: u8,
+        dialect: Dialect,
+    ) -> Result<Self, Error> {
+        check_code_width(max_code_width)?;
+        Ok(Self::build(reader, max_code_width, dialect))
+    }
 
-    for code in 0..1 << INITIAL_CODE_WIDTH {
-        suffix[code as usize] = code as u8;
-        length[code as usize] = 1;
+    fn build(reader: R, max_code_width: u8, dialect: Dialect) -> Self {
+        let table_size = 1usize << max_code_width;
+        let mut suffix = vec![0; table_size];
+        let mut length = vec![0; table_size];
+        for code in 0..1 << INITIAL_CODE_WIDTH {
+            suffix[code] = code as u8;
+            length[code] = 1;
+        }
+
+        let read_size = dialect.literal_width + 1;
+        Self {
+            reader: BitReader::new(reader, BitOrder::LsbFirst),
+            prefix: vec![0; table_size],
+            suffix,
+            length,
+            // A word is at most as long as the number of dictionary entries
+            decoding_stack: vec![0; table_size],
+            word_length: 0,
+            word_position: 0,
+            read_size,
+            size_increase_mask: 1 << read_size,
+            widest_code: dialect.widest_code(max_code_width),
+            table_size: table_size as u32,
+            next_index: dialect.first_free_code() as u32,
+            previous_code: None,
+            dialect,
+            early_change: 0,
+            codes_in_group: 0,
+            stream_started: false,
+            finished: false,
+            entropy: None,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Once the end of the stream was reached, the reader is positioned right
+    /// after the last byte of the code stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader.read
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader.read
     }
 
-    let mut read_size = INITIAL_CODE_WIDTH + 1;
-    let clear_code = 1 << INITIAL_CODE_WIDTH;
-    let end_of_information = clear_code + 1;
+    /// Decodes codes until a new word is in `decoding_stack`.
+    ///
+    /// Returns `false` once the end of the stream is reached.
+    fn decode_next_word(&mut self) -> Result<bool, Error> {
+        loop {
+            let code = match self.read_code() {
+                Ok(c) => c,
+                // An empty input is the encoding of an empty payload
+                Err(e)
+                    if e.kind() == ErrorKind::UnexpectedEof
+                        && (!self.stream_started || !self.dialect.has_end_code) =>
+                {
+                    return Ok(false)
+                }
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    return Err(Error::CorruptCodeStream(
+                        "stream ended before the end-of-information code".to_string(),
+                    ))
+                }
+                Err(e) => return Err(e.into()),
+            };
+            self.stream_started = true;
+            self.codes_in_group = self.codes_in_group.wrapping_add(1);
+
+            if code == self.dialect.clear_code() && self.dialect.has_clear_code {
+                self.skip_group_padding()?;
+                self.read_size = self.dialect.literal_width + 1;
+                self.size_increase_mask = 1 << self.read_size;
+                self.next_index = self.dialect.first_free_code() as u32;
+                self.previous_code = None;
+                continue;
+            } else if code == self.dialect.end_code() && self.dialect.has_end_code {
+                return Ok(false);
+            }
 
-    let mut size_increase_mask = 1 << read_size;
-    let mut next_index = clear_code + 2;
-    let mut previous_code: Option<u16> = None;
-    let mut word_length = 0;
+            self.decode_code(code)?;
+            self.word_position = 0;
+            return Ok(true);
+        }
+    }
 
-    loop {
-        let code = match reader.read_one(read_size) {
-            Ok(c) => c,
-            Err(_) => break, // EOF reached
+    /// Reads the next code with the current width, or through the Huffman stage.
+    fn read_code(&mut self) -> Result<u16, std::io::Error> {
+        match &mut self.entropy {
+            Some(entropy) => entropy.read_code(&mut self.reader, self.read_size),
+            None => self.reader.read_one(self.read_size),
+        }
+    }
+
+    fn decode_code(&mut self, code: u16) -> Result<(), Error> {
+        let Some(previous_code) = self.previous_code else {
+            if code >= self.dialect.clear_code() {
+                return Err(Error::CorruptCodeStream(format!(
+                    "code {} cannot start a sequence",
+                    code
+                )));
+            }
+            self.previous_code = Some(code);
+            self.decoding_stack[0] = code as u8;
+            self.word_length = 1;
+            return Ok(());
         };
 
-        if code == clear_code {
-            read_size = INITIAL_CODE_WIDTH + 1;
-            size_increase_mask = 1 << read_size;
-            next_index = clear_code + 2;
-            previous_code = None;
-            continue;
-        } else if code == end_of_information {
-            break;
-        } else if previous_code == None {
-            output.push(suffix[code as usize]);
-            previous_code = Some(code);
-            decoding_stack[0] = code as u8;
-            word_length = 1;
-            continue;
-        }
-
-        let initial_code = code;
-
-        match code.cmp(&next_index) {
+        let next_index = self.next_index;
+        match (code as u32).cmp(&next_index) {
             Ordering::Greater => {
-                panic!("Invalid code: {}", code);
+                return Err(invalid_code(code, next_index));
+            }
+            Ordering::Equal if next_index >= self.table_size => {
+                return Err(invalid_code(code, next_index));
             }
             Ordering::Equal => {
-                // KwKwK fix
-                decoding_stack[word_length] = decoding_stack[0];
-                word_length += 1;
+                // KwKwK fix: the previous word is still in the stack
+                self.decoding_stack[self.word_length] = self.decoding_stack[0];
+                self.word_length += 1;
             }
             Ordering::Less => {
-                word_length = length[code as usize];
-                let mut stack_top = word_length;
+                self.word_length = self.length[code as usize];
+                let mut stack_top = self.word_length;
                 let mut temp_code = code;
+                let literal_count = self.dialect.clear_code();
 
-                while temp_code >= clear_code {
+                while temp_code >= literal_count {
                     stack_top -= 1;
                     if stack_top == 0 {
                         break;
                     }
-                    decoding_stack[stack_top] = suffix[temp_code as usize];
-                    temp_code = prefix[temp_code as usize];
+                    self.decoding_stack[stack_top] = self.suffix[temp_code as usize];
+                    temp_code = self.prefix[temp_code as usize];
                 }
-                decoding_stack[0] = temp_code as u8;
+                self.decoding_stack[0] = temp_code as u8;
+            }
+        }
+
+        if next_index < self.table_size {
+            let index = next_index as usize;
+            self.prefix[index] = previous_code;
+            self.suffix[index] = self.decoding_stack[0];
+            self.length[index] = self.length[previous_code as usize] + 1;
+            self.next_index += 1;
+
+            if self.next_index + self.early_change == self.size_increase_mask
+                && self.read_size < self.widest_code
+            {
+                self.skip_group_padding()?;
+                self.read_size += 1;
+                self.size_increase_mask = 1 << self.read_size;
             }
         }
 
-        output.extend_from_slice(&decoding_stack[0..word_length]);
+        self.previous_code = Some(code);
+        Ok(())
+    }
+
+    /// Skips the zero codes that pad the current group of codes to a multiple
+    /// of eight codes, if the dialect has them. Called before the code width changes.
+    fn skip_group_padding(&mut self) -> Result<(), Error> {
+        if self.dialect.padded_groups {
+            while !self.codes_in_group.is_multiple_of(8) {
+                match self.reader.read_one(self.read_size) {
+                    Ok(_) => self.codes_in_group += 1,
+                    // The stream may end right after the last code
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(Error::Io(e)),
+                }
+            }
+            self.codes_in_group = 0;
+        }
+        Ok(())
+    }
+}
 
-        if (next_index as usize) < MAX_TABLE_SIZE {
-            prefix[next_index as usize] = previous_code.unwrap();
-            suffix[next_index as usize] = decoding_stack[0];
-            length[next_index as usize] = length[previous_code.unwrap() as usize] + 1;
-            next_index += 1;
+impl<R> Read for LzwDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut written = 0;
+
+        while written < buf.len() {
+            if self.word_position < self.word_length {
+                let count = (buf.len() - written).min(self.word_length - self.word_position);
+                buf[written..written + count].copy_from_slice(
+                    &self.decoding_stack[self.word_position..self.word_position + count],
+                );
+                self.word_position += count;
+                written += count;
+                continue;
+            }
 
-            if next_index == size_increase_mask && read_size < MAX_CODE_WIDTH {
-                read_size += 1;
-                size_increase_mask = 1 << read_size;
+            if self.finished {
+                break;
+            }
+            if !self.decode_next_word()? {
+                self.finished = true;
             }
         }
 
-        previous_code = Some(initial_code);
+        Ok(written)
     }
+}
 
-    output
+/// Core LZW decompression logic that works with any Read source
+pub fn lzw_decompress_from_reader<R: Read>(reader: R) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    LzwDecoder::new(reader).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+fn invalid_code(code: u16, next_index: u32) -> Error {
+    Error::CorruptCodeStream(format!(
+        "invalid code {} (next free code is {})",
+        code, next_index
+    ))
 }
 
 /// Decompress from a byte slice (in-memory)
-pub fn lzw_decompress_bytes(data: &[u8]) -> Vec<u8> {
+pub fn lzw_decompress_bytes(data: &[u8]) -> Result<Vec<u8>, Error> {
     use std::io::Cursor;
     lzw_decompress_from_reader(Cursor::new(data))
 }
 
-/// Convenience function for decompressing from a file path
-pub fn lzw_decompress(path: impl AsRef<Path>) -> Vec<u8> {
-    let file = File::open(path).expect("Cannot open file");
-    lzw_decompress_from_reader(BufReader::new(file))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use std::io::Cursor;
+    use std::fs::{self, File};
+    use std::io::{BufReader, Cursor};
 
     mod bit_reader {
         use super::*;
@@ -172,7 +435,7 @@ mod tests {
             // Arrange
             let data = vec![0xFF, 0x0F];
             let cursor = Cursor::new(data);
-            let mut reader = BitReader::new(cursor);
+            let mut reader = BitReader::new(cursor, BitOrder::LsbFirst);
 
             // Act
             let val_12bits = reader.read_one(12).unwrap();
@@ -188,7 +451,7 @@ mod tests {
             // Arrange
             let data = vec![0xAA]; // 10101010
             let cursor = Cursor::new(data);
-            let mut reader = BitReader::new(cursor);
+            let mut reader = BitReader::new(cursor, BitOrder::LsbFirst);
 
             // Act
             let val1 = reader.read_one(4);
@@ -200,6 +463,19 @@ mod tests {
             assert!(val2.is_ok());
             assert!(val3.is_err()); // EOF
         }
+
+        #[test]
+        fn test_read_msb_first() {
+            // Arrange
+            let data = vec![0xF4, 0x80]; // [111 101 00] [1 0000000]
+            let mut reader = BitReader::new(Cursor::new(data), BitOrder::MsbFirst);
+
+            // Act
+            let values: Vec<u16> = (0..3).map(|_| reader.read_one(3).unwrap()).collect();
+
+            // Assert
+            assert_eq!(values, vec![0b111, 0b101, 0b001]);
+        }
     }
 
     mod lzw_logic {
@@ -215,7 +491,8 @@ mod tests {
             let temp_file_path = dir.path().join(format!("{}.lzw", name));
             fs::write(&temp_file_path, &compressed).expect("Failed to write temp file");
 
-            let decompressed = lzw_decompress(temp_file_path.to_str().unwrap());
+            let file = File::open(&temp_file_path).expect("Failed to open temp file");
+            let decompressed = lzw_decompress_from_reader(BufReader::new(file)).unwrap();
 
             assert_eq!(
                 input,
@@ -230,7 +507,7 @@ mod tests {
         // Helper for in-memory round-trip tests
         fn run_round_trip_memory(name: &str, input: &[u8]) {
             let compressed = lzw_compress(input);
-            let decompressed = lzw_decompress_bytes(&compressed);
+            let decompressed = lzw_decompress_bytes(&compressed).unwrap();
 
             assert_eq!(
                 input,
@@ -290,9 +567,328 @@ mod tests {
             let data = b"TESTDATA";
             let compressed = lzw_compress(data);
             let cursor = Cursor::new(compressed);
-            let decompressed = lzw_decompress_from_reader(cursor);
+            let decompressed = lzw_decompress_from_reader(cursor).unwrap();
 
             assert_eq!(data.as_slice(), decompressed.as_slice());
         }
     }
+
+    mod corrupt_input {
+        use super::*;
+        use crate::compressor::compress::lzw_compress;
+
+        #[test]
+        fn test_code_beyond_table_is_rejected() {
+            // Arrange
+            // Clear code (256) followed by 'A' (65) and an unassigned code (400), 9 bits each
+            let mut bits: u32 = 256 | (65 << 9) | (400 << 18);
+            let mut data = Vec::new();
+            for _ in 0..4 {
+                data.push(bits as u8);
+                bits >>= 8;
+            }
+
+            // Act
+            let result = lzw_decompress_bytes(&data);
+
+            // Assert
+            assert!(matches!(result, Err(Error::CorruptCodeStream(_))));
+        }
+
+        #[test]
+        fn test_truncated_stream_is_rejected() {
+            // Arrange
+            let compressed = lzw_compress(b"TOBEORNOTTOBEORTOBEORNOT");
+            let truncated = &compressed[..compressed.len() / 2];
+
+            // Act
+            let result = lzw_decompress_bytes(truncated);
+
+            // Assert
+            assert!(matches!(result, Err(Error::CorruptCodeStream(_))));
+        }
+    }
+
+    mod lzw_decoder {
+        use super::*;
+        use crate::compressor::compress::lzw_compress;
+        use rand::{rng, Rng};
+
+        fn read_in_chunks(compressed: &[u8], chunk_size: usize) -> Vec<u8> {
+            let mut decoder = LzwDecoder::new(compressed);
+            let mut output = Vec::new();
+            let mut buf = vec![0u8; chunk_size];
+            loop {
+                let n = decoder.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                output.extend_from_slice(&buf[..n]);
+            }
+            output
+        }
+
+        #[test]
+        fn test_single_byte_reads_across_words() {
+            // Arrange
+            // Long runs produce long words and exercise the KwKwK case
+            let mut input = vec![b'A'; 5_000];
+            input.extend_from_slice(b"TOBEORNOTTOBEORTOBEORNOT");
+
+            // Act
+            let output = read_in_chunks(&lzw_compress(&input), 1);
+
+            // Assert
+            assert_eq!(output, input);
+        }
+
+        #[test]
+        fn test_small_buffers_across_clear_codes() {
+            // Arrange
+            let mut input = vec![0u8; 60_000];
+            rng().fill(&mut input[..]);
+
+            // Act
+            let output = read_in_chunks(&lzw_compress(&input), 13);
+
+            // Assert
+            assert_eq!(output, input);
+        }
+
+        #[test]
+        fn test_read_after_end_returns_zero() {
+            // Arrange
+            let compressed = lzw_compress(b"ABC");
+            let mut decoder = LzwDecoder::new(compressed.as_slice());
+            let mut output = Vec::new();
+
+            // Act
+            decoder.read_to_end(&mut output).unwrap();
+            let extra = decoder.read(&mut [0u8; 8]).unwrap();
+
+            // Assert
+            assert_eq!(output, b"ABC");
+            assert_eq!(extra, 0);
+        }
+
+        #[test]
+        fn test_corrupt_stream_error_round_trips() {
+            // Arrange
+            let garbage = [0xFFu8; 16];
+            let mut decoder = LzwDecoder::new(&garbage[..]);
+
+            // Act
+            let error = decoder.read(&mut [0u8; 64]).unwrap_err();
+
+            // Assert
+            assert_eq!(error.kind(), ErrorKind::InvalidData);
+            assert!(matches!(Error::from(error), Error::CorruptCodeStream(_)));
+        }
+    }
+
+    mod code_widths {
+        use super::*;
+        use crate::compressor::LzwEncoder;
+        use rand::{rng, Rng};
+        use std::io::Write;
+
+        /// Text over a small alphabet that keeps adding dictionary entries.
+        fn varied_text(len: usize) -> Vec<u8> {
+            let mut rng = rng();
+            (0..len)
+                .map(|_| b"abcdefgh \n"[rng.random_range(0..10)])
+                .collect()
+        }
+
+        fn compress_with_width(data: &[u8], width: u8) -> Vec<u8> {
+            let mut encoder = LzwEncoder::with_max_code_width(Vec::new(), width).unwrap();
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+
+        #[test]
+        fn test_round_trip_at_every_width() {
+            // Arrange
+            // Enough input to fill and reset a 16-bit dictionary
+            let input = varied_text(400_000);
+
+            for width in 9..=16 {
+                // Act
+                let compressed = compress_with_width(&input, width);
+                let mut decoder =
+                    LzwDecoder::with_max_code_width(compressed.as_slice(), width).unwrap();
+                let mut output = Vec::new();
+                decoder.read_to_end(&mut output).unwrap();
+
+                // Assert
+                assert!(output == input, "round trip failed at width {}", width);
+            }
+        }
+
+        #[test]
+        fn test_default_width_matches_twelve_bits() {
+            // Arrange
+            let input = varied_text(50_000);
+
+            // Act
+            let compressed = compress_with_width(&input, 12);
+
+            // Assert
+            assert_eq!(
+                compressed,
+                crate::compressor::compress::lzw_compress(&input)
+            );
+        }
+
+        #[test]
+        fn test_invalid_width_is_rejected() {
+            for width in [0, 8, 17, 32] {
+                // Act
+                let encoder = LzwEncoder::with_max_code_width(Vec::new(), width);
+                let decoder = LzwDecoder::with_max_code_width(&[][..], width);
+
+                // Assert
+                assert!(matches!(encoder, Err(Error::InvalidOptions(_))));
+                assert!(matches!(decoder, Err(Error::InvalidOptions(_))));
+            }
+        }
+    }
+
+    mod reset_policy {
+        use super::*;
+        use crate::compressor::{CompressOptions, LzwEncoder, ResetPolicy};
+        use rand::{rng, Rng};
+        use std::io::Write;
+
+        fn text_over(alphabet: &[u8], len: usize) -> Vec<u8> {
+            let mut rng = rng();
+            (0..len)
+                .map(|_| alphabet[rng.random_range(0..alphabet.len())])
+                .collect()
+        }
+
+        fn compress_with(data: &[u8], max_code_width: u8, reset_policy: ResetPolicy) -> Vec<u8> {
+            let options = CompressOptions {
+                max_code_width,
+                reset_policy,
+                ..Default::default()
+            };
+            let mut encoder = LzwEncoder::with_options(Vec::new(), &options).unwrap();
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+
+        fn decompress_with(data: &[u8], max_code_width: u8) -> Vec<u8> {
+            let mut decoder = LzwDecoder::with_max_code_width(data, max_code_width).unwrap();
+            let mut output = Vec::new();
+            decoder.read_to_end(&mut output).unwrap();
+            output
+        }
+
+        #[test]
+        fn test_ratio_reset_round_trip_when_data_changes() {
+            // Arrange
+            // The dictionary built for the first half is useless for the second
+            let mut input = text_over(b"abcdefgh \n", 150_000);
+            input.extend(text_over(b"IJKLMNOP.,", 150_000));
+
+            for width in [9, 12, 16] {
+                // Act
+                let compressed = compress_with(&input, width, ResetPolicy::OnRatioDrop);
+                let output = decompress_with(&compressed, width);
+
+                // Assert
+                assert!(output == input, "round trip failed at width {}", width);
+            }
+        }
+
+        #[test]
+        fn test_full_dictionary_is_kept_for_uniform_data() {
+            // Arrange
+            let input = text_over(b"abcd", 200_000);
+
+            // Act
+            let when_full = compress_with(&input, 9, ResetPolicy::WhenFull);
+            let on_ratio_drop = compress_with(&input, 9, ResetPolicy::OnRatioDrop);
+
+            // Assert
+            assert!(on_ratio_drop.len() < when_full.len());
+            assert_eq!(decompress_with(&on_ratio_drop, 9), input);
+        }
+    }
+
+    mod tiff_variant {
+        use super::*;
+        use crate::compressor::{compress_bare_with_options, decompress_bare_with_options};
+        use rand::{rng, Rng};
+
+        /// The LZWDecode example from the PDF reference.
+        const PDF_EXAMPLE: [u8; 9] = [0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01];
+
+        #[test]
+        fn test_pdf_reference_example() {
+            // Act
+            let encoded =
+                compress_bare_with_options(b"-----A---B", &CompressOptions::tiff()).unwrap();
+            let decoded = decompress_bare_with_options(&PDF_EXAMPLE, &CompressOptions::tiff());
+
+            // Assert
+            assert_eq!(encoded, PDF_EXAMPLE);
+            assert_eq!(decoded.unwrap(), b"-----A---B");
+        }
+
+        #[test]
+        fn test_round_trip_with_and_without_early_change() {
+            // Arrange
+            let mut rng = rng();
+            let input: Vec<u8> = (0..200_000)
+                .map(|_| b"abcdefgh \n"[rng.random_range(0..10)])
+                .collect();
+
+            for max_code_width in [9, 12, 16] {
+                for early_change in [false, true] {
+                    let options = CompressOptions {
+                        max_code_width,
+                        early_change,
+                        ..CompressOptions::tiff()
+                    };
+
+                    // Act
+                    let encoded = compress_bare_with_options(&input, &options).unwrap();
+                    let decoded = decompress_bare_with_options(&encoded, &options).unwrap();
+
+                    // Assert
+                    assert!(
+                        decoded == input,
+                        "round trip failed at width {} with early change {}",
+                        max_code_width,
+                        early_change
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn test_early_change_switches_width_one_code_earlier() {
+            // Arrange
+            // 300 distinct pairs add 300 entries, crossing 511 and 512
+            let input: Vec<u8> = (0..300u32)
+                .flat_map(|i| [(i % 256) as u8, (i / 256) as u8 + b'a'])
+                .collect();
+            let late = CompressOptions {
+                early_change: false,
+                ..CompressOptions::tiff()
+            };
+
+            // Act
+            let early_stream =
+                compress_bare_with_options(&input, &CompressOptions::tiff()).unwrap();
+            let late_stream = compress_bare_with_options(&input, &late).unwrap();
+
+            // Assert
+            assert_ne!(early_stream, late_stream);
+            let misread = decompress_bare_with_options(&early_stream, &late);
+            assert!(misread.map_or(true, |output| output != input));
+        }
+    }
 }
@@ -0,0 +1,738 @@
+use std::io::{self, Write};
+
+use crate::compressor::compress::BitWriter;
+use crate::compressor::huffman::{self, MAX_CODE_LENGTH};
+use crate::compressor::BitOrder;
+
+/// The size of the sliding window matches may refer back into.
+pub(crate) const WINDOW_SIZE: usize = 32 * 1024;
+
+/// The shortest match that can be encoded.
+const MIN_MATCH: usize = 3;
+
+/// The longest match that can be encoded.
+const MAX_MATCH: usize = 258;
+
+/// The literal/length symbol ending a block.
+pub(crate) const END_OF_BLOCK: u16 = 256;
+
+/// Number of literal/length symbols that may appear in a block.
+pub(crate) const LITERAL_SYMBOLS: usize = 286;
+
+/// Number of distance symbols that may appear in a block.
+pub(crate) const DISTANCE_SYMBOLS: usize = 30;
+
+/// Block type of uncompressed blocks.
+pub(crate) const BLOCK_STORED: u16 = 0;
+
+/// Block type of blocks using the fixed Huffman codes.
+pub(crate) const BLOCK_FIXED: u16 = 1;
+
+/// Block type of blocks carrying their own Huffman codes.
+pub(crate) const BLOCK_DYNAMIC: u16 = 2;
+
+/// The shortest length of each length symbol (257 to 285).
+pub(crate) const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+
+/// Number of extra bits following each length symbol.
+pub(crate) const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// The shortest distance of each distance symbol.
+pub(crate) const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+
+/// Number of extra bits following each distance symbol.
+pub(crate) const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// The order in which the code length code lengths are stored.
+pub(crate) const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// The longest code of the code length alphabet.
+const MAX_CODE_LENGTH_CODE: u8 = 7;
+
+/// Number of symbols after which a block is written.
+const BLOCK_SYMBOLS: usize = 16 * 1024;
+
+/// The most input bytes a block covers, so it always fits a single stored block.
+const MAX_BLOCK_LENGTH: usize = u16::MAX as usize;
+
+const HASH_BITS: u32 = 15;
+
+/// Marks an empty hash chain slot.
+const NIL: usize = usize::MAX;
+
+/// Number of earlier positions compared before the longest match so far is taken.
+const MAX_CHAIN: usize = 128;
+
+/// Matches at least this long are taken without searching further.
+const NICE_MATCH: usize = 128;
+
+/// Matches at least this long are taken without checking whether the next
+/// position starts a longer one.
+const LAZY_MATCH: usize = 32;
+
+/// Three-byte matches further back than this cost more than their literals.
+const TOO_FAR: usize = 4096;
+
+/// Returns the code lengths of the fixed literal/length code.
+pub(crate) fn fixed_literal_lengths() -> [u8; 288] {
+    let mut lengths = [8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths
+}
+
+/// Returns the code lengths of the fixed distance code.
+pub(crate) fn fixed_distance_lengths() -> [u8; 32] {
+    [5; 32]
+}
+
+/// Returns the symbol index (0 to 28, not 257 to 285) of a match length.
+fn length_symbol(length: u16) -> usize {
+    LENGTH_BASES.partition_point(|&base| base <= length) - 1
+}
+
+fn distance_symbol(distance: u16) -> usize {
+    DISTANCE_BASES.partition_point(|&base| base <= distance) - 1
+}
+
+/// Returns the canonical codes for `lengths`, bit-reversed for LSB-first writing.
+pub(crate) fn reversed_codes(lengths: &[u8]) -> Vec<u16> {
+    huffman::canonical_codes(lengths)
+        .into_iter()
+        .zip(lengths)
+        .map(|(code, &length)| huffman::reverse_bits(code, length))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symbol {
+    Literal(u8),
+    Match { length: u16, distance: u16 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Match {
+    length: usize,
+    distance: usize,
+}
+
+impl Match {
+    const NONE: Match = Match {
+        length: 0,
+        distance: 0,
+    };
+}
+
+/// The code lengths of a dynamic block.
+struct DynamicHeader {
+    literal_count: usize,
+    distance_count: usize,
+    table: CodeLengthTable,
+}
+
+impl DynamicHeader {
+    fn new(literal_lengths: &[u8], distance_lengths: &[u8]) -> DynamicHeader {
+        let used = |lengths: &[u8]| lengths.iter().rposition(|&length| length > 0).unwrap_or(0) + 1;
+        let literal_count = used(literal_lengths).max(257);
+        let distance_count = used(distance_lengths);
+
+        let mut lengths = literal_lengths[..literal_count].to_vec();
+        lengths.extend_from_slice(&distance_lengths[..distance_count]);
+
+        DynamicHeader {
+            literal_count,
+            distance_count,
+            table: CodeLengthTable::new(&lengths),
+        }
+    }
+
+    /// Returns the size of the header in bits, without the block type.
+    fn cost(&self) -> u64 {
+        5 + 5 + self.table.cost()
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut BitWriter<W>) -> io::Result<()> {
+        writer.write((self.literal_count - 257) as u16, 5)?;
+        writer.write((self.distance_count - 1) as u16, 5)?;
+        self.table.write_to(writer)
+    }
+}
+
+/// A list of code lengths, run-length encoded with the code length alphabet
+/// (RFC 1951, section 3.2.7).
+///
+/// Read back with [`read_code_lengths`](crate::compressor::inflate::read_code_lengths).
+pub(crate) struct CodeLengthTable {
+    /// The code length symbols, each with the value of its extra bits.
+    symbols: Vec<(u8, u8)>,
+    code_length_lengths: Vec<u8>,
+    code_length_count: usize,
+}
+
+impl CodeLengthTable {
+    pub(crate) fn new(lengths: &[u8]) -> CodeLengthTable {
+        let symbols = run_length_encode(lengths);
+
+        let mut frequencies = [0u32; 19];
+        for &(symbol, _) in &symbols {
+            frequencies[symbol as usize] += 1;
+        }
+        let code_length_lengths = huffman::code_lengths(&frequencies, MAX_CODE_LENGTH_CODE);
+        let code_length_count = CODE_LENGTH_ORDER
+            .iter()
+            .rposition(|&symbol| code_length_lengths[symbol] > 0)
+            .map_or(0, |index| index + 1)
+            .max(4);
+
+        CodeLengthTable {
+            symbols,
+            code_length_lengths,
+            code_length_count,
+        }
+    }
+
+    /// Returns the size of the table in bits.
+    pub(crate) fn cost(&self) -> u64 {
+        let symbols: u64 = self
+            .symbols
+            .iter()
+            .map(|&(symbol, _)| {
+                self.code_length_lengths[symbol as usize] as u64 + extra_bits(symbol) as u64
+            })
+            .sum();
+        4 + 3 * self.code_length_count as u64 + symbols
+    }
+
+    pub(crate) fn write_to<W: Write>(&self, writer: &mut BitWriter<W>) -> io::Result<()> {
+        writer.write((self.code_length_count - 4) as u16, 4)?;
+        for &symbol in &CODE_LENGTH_ORDER[..self.code_length_count] {
+            writer.write(self.code_length_lengths[symbol] as u16, 3)?;
+        }
+
+        let codes = reversed_codes(&self.code_length_lengths);
+        for &(symbol, extra) in &self.symbols {
+            writer.write(
+                codes[symbol as usize],
+                self.code_length_lengths[symbol as usize],
+            )?;
+            writer.write(extra as u16, extra_bits(symbol))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the number of extra bits following a code length symbol.
+fn extra_bits(symbol: u8) -> u8 {
+    match symbol {
+        16 => 2,
+        17 => 3,
+        18 => 7,
+        _ => 0,
+    }
+}
+
+/// Replaces runs in `lengths` by the repeat symbols 16 (previous length 3 to
+/// 6 times), 17 (3 to 10 zeros) and 18 (11 to 138 zeros).
+fn run_length_encode(lengths: &[u8]) -> Vec<(u8, u8)> {
+    let mut symbols = Vec::new();
+    let mut start = 0;
+    while start < lengths.len() {
+        let value = lengths[start];
+        let run = lengths[start..]
+            .iter()
+            .take_while(|&&length| length == value)
+            .count();
+        start += run;
+
+        let mut left = run;
+        if value == 0 {
+            while left >= 11 {
+                let count = left.min(138);
+                symbols.push((18, (count - 11) as u8));
+                left -= count;
+            }
+            if left >= 3 {
+                symbols.push((17, (left - 3) as u8));
+                left = 0;
+            }
+        } else {
+            symbols.push((value, 0));
+            left -= 1;
+            while left >= 3 {
+                let count = left.min(6);
+                symbols.push((16, (count - 3) as u8));
+                left -= count;
+            }
+        }
+        symbols.extend((0..left).map(|_| (value, 0)));
+    }
+    symbols
+}
+
+/// Streaming DEFLATE (RFC 1951) encoder that writes a raw DEFLATE stream to an
+/// underlying writer.
+///
+/// Repeated strings are found with hash chains over a 32 KiB window, using
+/// lazy matching. Every block is written as a stored, fixed Huffman or dynamic
+/// Huffman block, whichever is smallest. The output can be read by any
+/// DEFLATE decoder, such as zlib's `inflate` with a negative window size.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::DeflateEncoder;
+/// use std::io::Write;
+///
+/// let mut encoder = DeflateEncoder::new(Vec::new());
+/// encoder.write_all(b"TOBEORNOTTOBEORTOBEORNOT").unwrap();
+/// let compressed = encoder.finish().unwrap();
+/// # assert!(!compressed.is_empty());
+/// ```
+pub struct DeflateEncoder<W>
+where
+    W: Write,
+{
+    writer: BitWriter<W>,
+    /// The input still needed: the window before `position` and the lookahead.
+    window: Vec<u8>,
+    /// Index in `window` of the next byte to encode.
+    position: usize,
+    /// Index in `window` of the first byte of the current block.
+    block_start: usize,
+    /// Number of input bytes covered by the symbols of the current block.
+    block_length: usize,
+    /// The latest position of each hash of three bytes.
+    head: Vec<usize>,
+    /// The previous position with the same hash, indexed by position modulo [`WINDOW_SIZE`].
+    prev: Vec<usize>,
+    /// A match starting at `position - 1`, held back in case `position` starts a longer one.
+    pending_match: Option<Match>,
+    symbols: Vec<Symbol>,
+    literal_frequencies: [u32; LITERAL_SYMBOLS],
+    distance_frequencies: [u32; DISTANCE_SYMBOLS],
+}
+
+impl<W> DeflateEncoder<W>
+where
+    W: Write,
+{
+    /// Creates an encoder that writes the compressed stream to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: BitWriter::new(writer, BitOrder::LsbFirst),
+            window: Vec::with_capacity(3 * WINDOW_SIZE),
+            position: 0,
+            block_start: 0,
+            block_length: 0,
+            head: vec![NIL; 1 << HASH_BITS],
+            prev: vec![NIL; WINDOW_SIZE],
+            pending_match: None,
+            symbols: Vec::with_capacity(BLOCK_SYMBOLS),
+            literal_frequencies: [0; LITERAL_SYMBOLS],
+            distance_frequencies: [0; DISTANCE_SYMBOLS],
+        }
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.writer.get_ref()
+    }
+
+    /// Encodes the remaining input, writes the final block and returns the
+    /// underlying writer.
+    ///
+    /// An encoder that received no data writes an empty final block.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.compress(true)?;
+        self.write_block(true)?;
+        self.writer.into_inner()
+    }
+
+    /// Turns the input into symbols. Unless `finishing`, positions without a
+    /// full lookahead are left for later, so matches are not cut short.
+    fn compress(&mut self, finishing: bool) -> io::Result<()> {
+        loop {
+            let available = self.window.len() - self.position;
+            if available == 0 || (!finishing && available <= MAX_MATCH) {
+                break;
+            }
+
+            let current = self.find_match(self.position);
+            match self.pending_match.take() {
+                Some(previous) if current.length <= previous.length => {
+                    let start = self.position - 1;
+                    self.push_match(previous)?;
+                    for position in self.position + 1..start + previous.length {
+                        self.insert(position);
+                    }
+                    self.position = start + previous.length;
+                }
+                Some(_) => {
+                    // The match at this position is longer
+                    self.push_literal(self.window[self.position - 1])?;
+                    self.pending_match = Some(current);
+                    self.position += 1;
+                }
+                None if current.length >= LAZY_MATCH => {
+                    self.push_match(current)?;
+                    for position in self.position + 1..self.position + current.length {
+                        self.insert(position);
+                    }
+                    self.position += current.length;
+                }
+                None if current.length >= MIN_MATCH => {
+                    self.pending_match = Some(current);
+                    self.position += 1;
+                }
+                None => {
+                    self.push_literal(self.window[self.position])?;
+                    self.position += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let bytes = &self.window[position..position + MIN_MATCH];
+        let key = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+        (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    /// Adds `position` to its hash chain.
+    fn insert(&mut self, position: usize) {
+        if position + MIN_MATCH <= self.window.len() {
+            let hash = self.hash(position);
+            self.prev[position % WINDOW_SIZE] = self.head[hash];
+            self.head[hash] = position;
+        }
+    }
+
+    /// Returns the longest earlier match of the input at `position` and adds
+    /// `position` to its hash chain.
+    fn find_match(&mut self, position: usize) -> Match {
+        let available = (self.window.len() - position).min(MAX_MATCH);
+        if available < MIN_MATCH {
+            return Match::NONE;
+        }
+        let hash = self.hash(position);
+        let mut candidate = self.head[hash];
+        self.prev[position % WINDOW_SIZE] = candidate;
+        self.head[hash] = position;
+
+        let target = &self.window[position..position + available];
+        let mut best = Match::NONE;
+        for _ in 0..MAX_CHAIN {
+            if candidate == NIL || position - candidate > WINDOW_SIZE {
+                break;
+            }
+            let length = self.window[candidate..]
+                .iter()
+                .zip(target)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best.length {
+                best = Match {
+                    length,
+                    distance: position - candidate,
+                };
+                if length >= NICE_MATCH || length == available {
+                    break;
+                }
+            }
+
+            // A slot that was reused by a newer position ends the chain
+            let next = self.prev[candidate % WINDOW_SIZE];
+            if next == NIL || next >= candidate {
+                break;
+            }
+            candidate = next;
+        }
+
+        if best.length < MIN_MATCH || (best.length == MIN_MATCH && best.distance > TOO_FAR) {
+            Match::NONE
+        } else {
+            best
+        }
+    }
+
+    fn push_literal(&mut self, byte: u8) -> io::Result<()> {
+        self.make_room(1)?;
+        self.literal_frequencies[byte as usize] += 1;
+        self.symbols.push(Symbol::Literal(byte));
+        self.block_length += 1;
+        Ok(())
+    }
+
+    fn push_match(&mut self, found: Match) -> io::Result<()> {
+        self.make_room(found.length)?;
+        let (length, distance) = (found.length as u16, found.distance as u16);
+        self.literal_frequencies[257 + length_symbol(length)] += 1;
+        self.distance_frequencies[distance_symbol(distance)] += 1;
+        self.symbols.push(Symbol::Match { length, distance });
+        self.block_length += found.length;
+        Ok(())
+    }
+
+    /// Writes the current block if it cannot take a symbol covering `length` more bytes.
+    fn make_room(&mut self, length: usize) -> io::Result<()> {
+        if self.symbols.len() == BLOCK_SYMBOLS || self.block_length + length > MAX_BLOCK_LENGTH {
+            self.write_block(false)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the symbols collected so far as one block, using the smallest block type.
+    fn write_block(&mut self, last: bool) -> io::Result<()> {
+        self.literal_frequencies[END_OF_BLOCK as usize] += 1;
+
+        let literal_lengths = huffman::code_lengths(&self.literal_frequencies, MAX_CODE_LENGTH);
+        let mut distance_lengths =
+            huffman::code_lengths(&self.distance_frequencies, MAX_CODE_LENGTH);
+        if distance_lengths.iter().all(|&length| length == 0) {
+            // A block without matches still describes one distance code
+            distance_lengths[0] = 1;
+        }
+        let header = DynamicHeader::new(&literal_lengths, &distance_lengths);
+
+        let dynamic_bits = header.cost() + self.symbols_cost(&literal_lengths, &distance_lengths);
+        let fixed_bits = self.symbols_cost(&fixed_literal_lengths(), &fixed_distance_lengths());
+        let padding = (8 - (self.writer.bits_written() + 3) % 8) % 8;
+        let stored_bits = padding + 32 + 8 * self.block_length as u64;
+
+        self.writer.write(last as u16, 1)?;
+        if stored_bits < fixed_bits.min(dynamic_bits) {
+            self.writer.write(BLOCK_STORED, 2)?;
+            self.writer.align_to_byte()?;
+            self.writer.write(self.block_length as u16, 16)?;
+            self.writer.write(!(self.block_length as u16), 16)?;
+            let end = self.block_start + self.block_length;
+            for &byte in &self.window[self.block_start..end] {
+                self.writer.write(byte as u16, 8)?;
+            }
+        } else if dynamic_bits < fixed_bits {
+            self.writer.write(BLOCK_DYNAMIC, 2)?;
+            header.write_to(&mut self.writer)?;
+            self.write_symbols(&literal_lengths, &distance_lengths)?;
+        } else {
+            self.writer.write(BLOCK_FIXED, 2)?;
+            self.write_symbols(&fixed_literal_lengths(), &fixed_distance_lengths())?;
+        }
+
+        self.symbols.clear();
+        self.literal_frequencies = [0; LITERAL_SYMBOLS];
+        self.distance_frequencies = [0; DISTANCE_SYMBOLS];
+        self.block_start += self.block_length;
+        self.block_length = 0;
+        Ok(())
+    }
+
+    /// Returns the size in bits of the symbols of the current block with the given codes.
+    fn symbols_cost(&self, literal_lengths: &[u8], distance_lengths: &[u8]) -> u64 {
+        let literals: u64 = self
+            .literal_frequencies
+            .iter()
+            .enumerate()
+            .map(|(symbol, &frequency)| {
+                let extra = symbol
+                    .checked_sub(257)
+                    .map_or(0, |index| LENGTH_EXTRA_BITS[index]);
+                frequency as u64 * (literal_lengths[symbol] + extra) as u64
+            })
+            .sum();
+        let distances: u64 = self
+            .distance_frequencies
+            .iter()
+            .zip(DISTANCE_EXTRA_BITS)
+            .enumerate()
+            .map(|(symbol, (&frequency, extra))| {
+                frequency as u64 * (distance_lengths[symbol] + extra) as u64
+            })
+            .sum();
+        literals + distances
+    }
+
+    fn write_symbols(&mut self, literal_lengths: &[u8], distance_lengths: &[u8]) -> io::Result<()> {
+        let literal_codes = reversed_codes(literal_lengths);
+        let distance_codes = reversed_codes(distance_lengths);
+
+        for &symbol in &self.symbols {
+            match symbol {
+                Symbol::Literal(byte) => {
+                    let byte = byte as usize;
+                    self.writer
+                        .write(literal_codes[byte], literal_lengths[byte])?;
+                }
+                Symbol::Match { length, distance } => {
+                    let index = length_symbol(length);
+                    let symbol = 257 + index;
+                    self.writer
+                        .write(literal_codes[symbol], literal_lengths[symbol])?;
+                    self.writer
+                        .write(length - LENGTH_BASES[index], LENGTH_EXTRA_BITS[index])?;
+
+                    let index = distance_symbol(distance);
+                    self.writer
+                        .write(distance_codes[index], distance_lengths[index])?;
+                    self.writer
+                        .write(distance - DISTANCE_BASES[index], DISTANCE_EXTRA_BITS[index])?;
+                }
+            }
+        }
+
+        let end = END_OF_BLOCK as usize;
+        self.writer.write(literal_codes[end], literal_lengths[end])
+    }
+
+    /// Drops input that neither matches nor the current block can refer to any more.
+    fn slide_window(&mut self) {
+        let unused = self
+            .block_start
+            .min(self.position.saturating_sub(WINDOW_SIZE));
+        // The hash chains are indexed by position modulo the window size
+        let amount = unused - unused % WINDOW_SIZE;
+        if amount == 0 {
+            return;
+        }
+
+        self.window.drain(..amount);
+        self.position -= amount;
+        self.block_start -= amount;
+        for slot in self.head.iter_mut().chain(self.prev.iter_mut()) {
+            *slot = match *slot {
+                NIL => NIL,
+                slot if slot < amount => NIL,
+                slot => slot - amount,
+            };
+        }
+    }
+}
+
+impl<W> Write for DeflateEncoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for chunk in buf.chunks(WINDOW_SIZE) {
+            self.window.extend_from_slice(chunk);
+            self.compress(false)?;
+            self.slide_window();
+        }
+        Ok(buf.len())
+    }
+
+    /// Writes all complete bytes of the finished blocks to the underlying writer.
+    ///
+    /// The current block stays buffered until it is full or until
+    /// [`DeflateEncoder::finish`].
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.write_pending()?;
+        self.writer.get_mut().flush()
+    }
+}
+
+/// Compresses `data` into a raw DEFLATE stream.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new());
+    encoder
+        .write_all(data)
+        .expect("Writing to a Vec cannot fail");
+    encoder.finish().expect("Writing to a Vec cannot fail")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_tables() {
+        assert_eq!(length_symbol(3), 0);
+        assert_eq!(length_symbol(10), 7);
+        assert_eq!(length_symbol(11), 8);
+        assert_eq!(length_symbol(12), 8);
+        assert_eq!(length_symbol(257), 27);
+        assert_eq!(length_symbol(258), 28);
+        assert_eq!(distance_symbol(1), 0);
+        assert_eq!(distance_symbol(6), 4);
+        assert_eq!(distance_symbol(32768), 29);
+    }
+
+    #[test]
+    fn test_run_length_encode() {
+        // Arrange
+        let mut lengths = vec![8; 10];
+        lengths.extend([0; 150]);
+        lengths.extend([5, 0, 0, 0, 0]);
+
+        // Act
+        let symbols = run_length_encode(&lengths);
+
+        // Assert
+        assert_eq!(
+            symbols,
+            [
+                (8, 0),
+                (16, 3),
+                (16, 0),
+                (18, 127),
+                (18, 1),
+                (5, 0),
+                (17, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_input_is_an_empty_fixed_block() {
+        // Act
+        let compressed = deflate(b"");
+
+        // Assert
+        assert_eq!(compressed, [0x03, 0x00]);
+    }
+
+    #[test]
+    fn test_block_type_follows_the_data() {
+        // Arrange
+        let mut noise = 0x1234_5678u32;
+        let random: Vec<u8> = (0..4000)
+            .map(|_| {
+                noise ^= noise << 13;
+                noise ^= noise >> 17;
+                noise ^= noise << 5;
+                noise as u8
+            })
+            .collect();
+        let text = b"the quick brown fox jumps over the lazy dog; ".repeat(200);
+
+        // Act
+        let block_type = |data: &[u8]| (deflate(data)[0] >> 1) & 0b11;
+
+        // Assert
+        assert_eq!(block_type(&random), BLOCK_STORED as u8);
+        assert_eq!(block_type(b"abc"), BLOCK_FIXED as u8);
+        assert_eq!(block_type(&text), BLOCK_DYNAMIC as u8);
+    }
+
+    #[test]
+    fn test_long_runs_use_long_matches() {
+        // Act
+        let compressed = deflate(&[b'a'; 100_000]);
+
+        // Assert
+        assert!(compressed.len() < 200, "got {} bytes", compressed.len());
+    }
+}
@@ -0,0 +1,374 @@
+//! Huffman coding of the LZW code stream, see [`EntropyCoding`].
+//!
+//! Each code is split into a symbol and extra bits, like the distances of
+//! DEFLATE: the literals, the clear code and the end code are symbols of their
+//! own, and the codes of dictionary words are grouped into up to 256 symbols
+//! of consecutive codes, the position in the group following as extra bits.
+//! The groups grow with the current code width, so a word code costs as many
+//! extra bits as the width exceeds the literals.
+
+use std::io::{self, Read, Write};
+
+use crate::compressor::compress::BitWriter;
+use crate::compressor::decompress::BitReader;
+use crate::compressor::deflate::{reversed_codes, CodeLengthTable};
+use crate::compressor::huffman::{self, HuffmanDecoder, MAX_CODE_LENGTH};
+use crate::compressor::inflate::read_code_lengths;
+use crate::compressor::{EntropyCoding, INITIAL_CODE_WIDTH};
+use crate::Error;
+
+/// The first code assigned to a dictionary word in the press_rs dialect.
+const FIRST_WORD: u16 = 258;
+
+/// Number of symbols: one per code below [`FIRST_WORD`] and 256 groups of words.
+const SYMBOLS: usize = FIRST_WORD as usize + 256;
+
+/// Maximum number of codes in a block of [`EntropyCoding::StaticHuffman`].
+const BLOCK_CODES: usize = 16 * 1024;
+
+/// Bit width of the code count in front of each static block.
+const BLOCK_COUNT_WIDTH: u8 = 15;
+
+/// Number of codes before the adaptive code is first rebuilt. The interval
+/// doubles after each rebuild, up to [`MAX_REBUILD_INTERVAL`].
+const FIRST_REBUILD_INTERVAL: u32 = 64;
+
+/// The longest interval between rebuilds of the adaptive code.
+const MAX_REBUILD_INTERVAL: u32 = 1024;
+
+/// Once the adaptive counts add up to this, they are halved so the code
+/// follows changes in the data.
+const MAX_TOTAL_FREQUENCY: u32 = 8 * 1024;
+
+/// Returns the number of extra bits following `symbol` when codes are `width` bits wide.
+fn extra_bits(symbol: usize, width: u8) -> u8 {
+    if symbol < FIRST_WORD as usize {
+        0
+    } else {
+        width - INITIAL_CODE_WIDTH
+    }
+}
+
+/// Returns the symbol of a `width` bit `code` and the value of its extra bits.
+fn split(code: u16, width: u8) -> (usize, u16) {
+    if code < FIRST_WORD {
+        return (code as usize, 0);
+    }
+    let offset = code - FIRST_WORD;
+    let extra_bits = width - INITIAL_CODE_WIDTH;
+    (
+        FIRST_WORD as usize + (offset >> extra_bits) as usize,
+        offset & ((1 << extra_bits) - 1),
+    )
+}
+
+/// Reverses [`split`].
+fn join(symbol: u16, extra: u16, width: u8) -> Result<u16, Error> {
+    if symbol < FIRST_WORD {
+        return Ok(symbol);
+    }
+    let group = (symbol - FIRST_WORD) as u32;
+    let code = FIRST_WORD as u32 + (group << (width - INITIAL_CODE_WIDTH)) + extra as u32;
+    if code >= 1 << width {
+        return Err(Error::CorruptCodeStream(format!(
+            "code {} does not fit in {} bits",
+            code, width
+        )));
+    }
+    Ok(code as u16)
+}
+
+/// Symbol frequencies of [`EntropyCoding::AdaptiveHuffman`], updated the same
+/// way by the encoder and the decoder.
+pub(crate) struct AdaptiveModel {
+    frequencies: Vec<u32>,
+    total: u32,
+    /// Codes left until the code is rebuilt.
+    countdown: u32,
+    interval: u32,
+}
+
+impl AdaptiveModel {
+    /// Creates a model in which every symbol has been seen once.
+    fn new() -> AdaptiveModel {
+        AdaptiveModel {
+            frequencies: vec![1; SYMBOLS],
+            total: SYMBOLS as u32,
+            countdown: FIRST_REBUILD_INTERVAL,
+            interval: FIRST_REBUILD_INTERVAL,
+        }
+    }
+
+    /// Returns the code lengths for the current frequencies.
+    ///
+    /// Every symbol keeps a count of at least 1, so every symbol has a code.
+    fn lengths(&self) -> Vec<u8> {
+        huffman::code_lengths(&self.frequencies, MAX_CODE_LENGTH)
+    }
+
+    /// Counts `symbol` and returns `true` when the code should be rebuilt.
+    fn update(&mut self, symbol: usize) -> bool {
+        self.frequencies[symbol] += 1;
+        self.total += 1;
+        self.countdown -= 1;
+        if self.countdown > 0 {
+            return false;
+        }
+
+        if self.total >= MAX_TOTAL_FREQUENCY {
+            self.total = 0;
+            for frequency in &mut self.frequencies {
+                *frequency = frequency.div_ceil(2);
+                self.total += *frequency;
+            }
+        }
+        self.interval = (self.interval * 2).min(MAX_REBUILD_INTERVAL);
+        self.countdown = self.interval;
+        true
+    }
+}
+
+/// Writes LZW codes through the Huffman stage selected by an [`EntropyCoding`].
+pub(crate) enum EntropyEncoder {
+    /// The symbols of the current block with their extra bits and the number
+    /// of extra bits, written once the block is complete.
+    Static { block: Vec<(u16, u16, u8)> },
+    Adaptive {
+        model: AdaptiveModel,
+        lengths: Vec<u8>,
+        codes: Vec<u16>,
+    },
+}
+
+impl EntropyEncoder {
+    /// Creates the encoder for `coding`, or `None` for [`EntropyCoding::None`].
+    pub(crate) fn new(coding: EntropyCoding) -> Option<EntropyEncoder> {
+        match coding {
+            EntropyCoding::None => None,
+            EntropyCoding::StaticHuffman => Some(EntropyEncoder::Static {
+                block: Vec::with_capacity(BLOCK_CODES),
+            }),
+            EntropyCoding::AdaptiveHuffman => {
+                let model = AdaptiveModel::new();
+                let lengths = model.lengths();
+                Some(EntropyEncoder::Adaptive {
+                    codes: reversed_codes(&lengths),
+                    model,
+                    lengths,
+                })
+            }
+        }
+    }
+
+    /// Writes `code`, which the plain code stream would write with `width` bits.
+    pub(crate) fn write_code<W: Write>(
+        &mut self,
+        writer: &mut BitWriter<W>,
+        code: u16,
+        width: u8,
+    ) -> io::Result<()> {
+        let (symbol, extra) = split(code, width);
+        match self {
+            EntropyEncoder::Static { block } => {
+                block.push((symbol as u16, extra, extra_bits(symbol, width)));
+                if block.len() == BLOCK_CODES {
+                    self.write_block(writer)?;
+                }
+            }
+            EntropyEncoder::Adaptive {
+                model,
+                lengths,
+                codes,
+            } => {
+                writer.write(codes[symbol], lengths[symbol])?;
+                writer.write(extra, extra_bits(symbol, width))?;
+                if model.update(symbol) {
+                    *lengths = model.lengths();
+                    *codes = reversed_codes(lengths);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the codes that are still buffered. Called before the stream ends.
+    pub(crate) fn finish<W: Write>(&mut self, writer: &mut BitWriter<W>) -> io::Result<()> {
+        match self {
+            EntropyEncoder::Static { block } if !block.is_empty() => self.write_block(writer),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the buffered codes as a static block: the number of codes, the
+    /// code lengths of the block and the coded symbols.
+    fn write_block<W: Write>(&mut self, writer: &mut BitWriter<W>) -> io::Result<()> {
+        let EntropyEncoder::Static { block } = self else {
+            unreachable!("called for static blocks only");
+        };
+        let mut frequencies = [0u32; SYMBOLS];
+        for &(symbol, _, _) in block.iter() {
+            frequencies[symbol as usize] += 1;
+        }
+        let lengths = huffman::code_lengths(&frequencies, MAX_CODE_LENGTH);
+        let codes = reversed_codes(&lengths);
+
+        writer.write(block.len() as u16, BLOCK_COUNT_WIDTH)?;
+        CodeLengthTable::new(&lengths).write_to(writer)?;
+        for &(symbol, extra, extra_bits) in block.iter() {
+            writer.write(codes[symbol as usize], lengths[symbol as usize])?;
+            writer.write(extra, extra_bits)?;
+        }
+        block.clear();
+        Ok(())
+    }
+}
+
+/// Reads LZW codes written by an [`EntropyEncoder`].
+pub(crate) enum EntropyDecoder {
+    Static {
+        /// The code of the current block, `None` before the first block.
+        decoder: Option<HuffmanDecoder>,
+        /// Codes left in the current block.
+        remaining: u16,
+    },
+    Adaptive {
+        model: AdaptiveModel,
+        decoder: HuffmanDecoder,
+    },
+}
+
+impl EntropyDecoder {
+    /// Creates the decoder for `coding`, or `None` for [`EntropyCoding::None`].
+    pub(crate) fn new(coding: EntropyCoding) -> Option<EntropyDecoder> {
+        match coding {
+            EntropyCoding::None => None,
+            EntropyCoding::StaticHuffman => Some(EntropyDecoder::Static {
+                decoder: None,
+                remaining: 0,
+            }),
+            EntropyCoding::AdaptiveHuffman => {
+                let model = AdaptiveModel::new();
+                Some(EntropyDecoder::Adaptive {
+                    decoder: HuffmanDecoder::new(&model.lengths())
+                        .expect("lengths from frequencies form a valid code"),
+                    model,
+                })
+            }
+        }
+    }
+
+    /// Reads the next LZW code, which the plain code stream would hold in `width` bits.
+    ///
+    /// Running out of input is reported as an [`io::Error`] of kind
+    /// [`UnexpectedEof`](io::ErrorKind::UnexpectedEof), like reading a plain code.
+    pub(crate) fn read_code<R: Read>(
+        &mut self,
+        reader: &mut BitReader<R>,
+        width: u8,
+    ) -> io::Result<u16> {
+        let symbol = match self {
+            EntropyDecoder::Static { decoder, remaining } => {
+                if *remaining == 0 {
+                    let count = reader.read_one(BLOCK_COUNT_WIDTH)?;
+                    if count == 0 {
+                        return Err(
+                            Error::CorruptCodeStream("empty Huffman block".to_string()).into()
+                        );
+                    }
+                    let lengths = read_code_lengths(reader, SYMBOLS)?;
+                    *decoder = Some(HuffmanDecoder::new(&lengths)?);
+                    *remaining = count;
+                }
+                *remaining -= 1;
+                let decoder = decoder.as_ref().expect("a block was started above");
+                decoder.decode(|| reader.read_one(1))?
+            }
+            EntropyDecoder::Adaptive { model, decoder } => {
+                let symbol = decoder.decode(|| reader.read_one(1))?;
+                if model.update(symbol as usize) {
+                    *decoder = HuffmanDecoder::new(&model.lengths())?;
+                }
+                symbol
+            }
+        };
+        let extra = reader.read_one(extra_bits(symbol as usize, width))?;
+        Ok(join(symbol, extra, width)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::BitOrder;
+
+    fn round_trip(coding: EntropyCoding, codes: &[(u16, u8)]) -> Vec<(u16, u8)> {
+        let mut encoder = EntropyEncoder::new(coding).unwrap();
+        let mut writer = BitWriter::new(Vec::new(), BitOrder::LsbFirst);
+        for &(code, width) in codes {
+            encoder.write_code(&mut writer, code, width).unwrap();
+        }
+        encoder.finish(&mut writer).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let mut decoder = EntropyDecoder::new(coding).unwrap();
+        let mut reader = BitReader::new(bytes.as_slice(), BitOrder::LsbFirst);
+        codes
+            .iter()
+            .map(|&(_, width)| (decoder.read_code(&mut reader, width).unwrap(), width))
+            .collect()
+    }
+
+    #[test]
+    fn test_split_and_join() {
+        // Act & Assert
+        assert_eq!(split(65, 12), (65, 0));
+        assert_eq!(split(257, 12), (257, 0));
+        assert_eq!(split(258 + 0x2A, 12), (258 + 2, 0xA));
+        assert_eq!(split(511, 9), (258 + 126, 1));
+        for width in 9..=16 {
+            for code in [0, 255, 256, 257, 258, 300, ((1u32 << width) - 1) as u16] {
+                let (symbol, extra) = split(code, width);
+                assert!(symbol < SYMBOLS);
+                assert_eq!(join(symbol as u16, extra, width).unwrap(), code);
+            }
+        }
+    }
+
+    #[test]
+    fn test_codes_of_every_width_round_trip() {
+        // Arrange
+        let codes: Vec<(u16, u8)> = (9..=16)
+            .flat_map(|width| {
+                (0..1u32 << width)
+                    .step_by(37)
+                    .map(move |code| (code as u16, width))
+            })
+            .collect();
+
+        // Act & Assert
+        for coding in [EntropyCoding::StaticHuffman, EntropyCoding::AdaptiveHuffman] {
+            assert_eq!(round_trip(coding, &codes), codes);
+        }
+    }
+
+    #[test]
+    fn test_many_blocks_round_trip() {
+        // Arrange
+        let codes: Vec<(u16, u8)> = (0..3 * BLOCK_CODES as u32 + 5)
+            .map(|i| (((i * i) % 600) as u16, 12))
+            .collect();
+
+        // Act & Assert
+        for coding in [EntropyCoding::StaticHuffman, EntropyCoding::AdaptiveHuffman] {
+            assert_eq!(round_trip(coding, &codes), codes);
+        }
+    }
+
+    #[test]
+    fn test_join_rejects_codes_wider_than_the_width() {
+        assert!(matches!(
+            join(258 + 255, 0xFF, 16),
+            Err(Error::CorruptCodeStream(_))
+        ));
+    }
+}
@@ -0,0 +1,432 @@
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+
+use crate::compressor::{CompressOptions, Dialect, LzwDecoder, LzwEncoder, ResetPolicy};
+use crate::Error;
+
+/// The LZW minimum code sizes allowed in GIF image data.
+pub const GIF_MIN_CODE_SIZE_RANGE: RangeInclusive<u8> = 2..=8;
+
+/// The maximum bit width of LZW codes in GIF image data.
+const GIF_MAX_CODE_WIDTH: u8 = 12;
+
+/// The maximum length of a data sub-block.
+const MAX_SUB_BLOCK_SIZE: usize = 255;
+
+/// Splits the written data into sub-blocks of at most 255 bytes, each
+/// preceded by its length.
+struct SubBlockWriter<W>
+where
+    W: Write,
+{
+    output: W,
+    block: Vec<u8>,
+}
+
+impl<W> SubBlockWriter<W>
+where
+    W: Write,
+{
+    fn new(output: W) -> Self {
+        Self {
+            output,
+            block: Vec::with_capacity(MAX_SUB_BLOCK_SIZE),
+        }
+    }
+
+    fn write_block(&mut self) -> io::Result<()> {
+        if !self.block.is_empty() {
+            self.output.write_all(&[self.block.len() as u8])?;
+            self.output.write_all(&self.block)?;
+            self.block.clear();
+        }
+        Ok(())
+    }
+
+    /// Writes the last sub-block and the block terminator.
+    fn finish(mut self) -> io::Result<W> {
+        self.write_block()?;
+        self.output.write_all(&[0])?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+}
+
+impl<W> Write for SubBlockWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = buf.len().min(MAX_SUB_BLOCK_SIZE - self.block.len());
+        self.block.extend_from_slice(&buf[..count]);
+        if self.block.len() == MAX_SUB_BLOCK_SIZE {
+            self.write_block()?;
+        }
+        Ok(count)
+    }
+
+    /// Writes the buffered data as a (possibly short) sub-block.
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_block()?;
+        self.output.flush()
+    }
+}
+
+/// Joins the data of consecutive sub-blocks, ending at the block terminator.
+struct SubBlockReader<R>
+where
+    R: Read,
+{
+    input: R,
+    remaining: usize,
+    finished: bool,
+}
+
+impl<R> SubBlockReader<R>
+where
+    R: Read,
+{
+    fn new(input: R) -> Self {
+        Self {
+            input,
+            remaining: 0,
+            finished: false,
+        }
+    }
+
+    /// Skips the remaining sub-blocks up to and including the block terminator.
+    fn skip_to_end(&mut self) -> io::Result<()> {
+        io::copy(self, &mut io::sink())?;
+        Ok(())
+    }
+}
+
+impl<R> Read for SubBlockReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.finished || buf.is_empty() {
+            return Ok(0);
+        }
+        if self.remaining == 0 {
+            let mut length = [0u8; 1];
+            self.input.read_exact(&mut length)?;
+            if length[0] == 0 {
+                self.finished = true;
+                return Ok(0);
+            }
+            self.remaining = length[0] as usize;
+        }
+
+        let count = buf.len().min(self.remaining);
+        let read = self.input.read(&mut buf[..count])?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= read;
+        Ok(read)
+    }
+}
+
+/// Streaming encoder for the LZW compressed image data of a GIF file.
+///
+/// The bytes written are color indices, each of which must be smaller than
+/// `2^min_code_size`. The output starts with the minimum code size, followed by
+/// the code stream in data sub-blocks of at most 255 bytes and the block
+/// terminator, as it appears after a GIF image descriptor.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{GifDecoder, GifEncoder};
+/// use std::io::{Read, Write};
+///
+/// let pixels = [0, 1, 1, 2, 3, 3, 3, 3, 0, 1];
+/// let mut encoder = GifEncoder::new(Vec::new(), 2).unwrap();
+/// encoder.write_all(&pixels).unwrap();
+/// let image_data = encoder.finish().unwrap();
+///
+/// let mut decoded = Vec::new();
+/// GifDecoder::new(image_data.as_slice()).unwrap().read_to_end(&mut decoded).unwrap();
+/// assert_eq!(decoded, pixels);
+/// ```
+pub struct GifEncoder<W>
+where
+    W: Write,
+{
+    encoder: LzwEncoder<SubBlockWriter<W>>,
+}
+
+impl<W> GifEncoder<W>
+where
+    W: Write,
+{
+    /// Writes the minimum code size (2 to 8) to `writer` and returns an encoder
+    /// that clears the dictionary as soon as it is full.
+    pub fn new(writer: W, min_code_size: u8) -> Result<Self, Error> {
+        Self::with_reset_policy(writer, min_code_size, ResetPolicy::WhenFull)
+    }
+
+    /// Like [`new`](Self::new), but with the given [`ResetPolicy`].
+    ///
+    /// With [`ResetPolicy::OnRatioDrop`] the encoder keeps using the full
+    /// dictionary and defers the clear code, which GIF decoders must support.
+    pub fn with_reset_policy(
+        mut writer: W,
+        min_code_size: u8,
+        reset_policy: ResetPolicy,
+    ) -> Result<Self, Error> {
+        if !GIF_MIN_CODE_SIZE_RANGE.contains(&min_code_size) {
+            return Err(Error::InvalidOptions(format!(
+                "minimum code size must be between {} and {}, got {}",
+                GIF_MIN_CODE_SIZE_RANGE.start(),
+                GIF_MIN_CODE_SIZE_RANGE.end(),
+                min_code_size
+            )));
+        }
+        writer.write_all(&[min_code_size])?;
+
+        let options = CompressOptions {
+            max_code_width: GIF_MAX_CODE_WIDTH,
+            reset_policy,
+            ..Default::default()
+        };
+        let blocks = SubBlockWriter::new(writer);
+        Ok(Self {
+            encoder: LzwEncoder::with_dialect(blocks, &options, Dialect::gif(min_code_size))?,
+        })
+    }
+
+    /// Writes the end-of-information code and the block terminator, then
+    /// returns the underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        Ok(self.encoder.finish()?.finish()?)
+    }
+}
+
+impl<W> Write for GifEncoder<W>
+where
+    W: Write,
+{
+    /// Encodes color indices. Fails with [`io::ErrorKind::InvalidInput`] if an
+    /// index does not fit the minimum code size.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Streaming decoder for the LZW compressed image data of a GIF file.
+///
+/// Reads the minimum code size and the data sub-blocks written by
+/// [`GifEncoder`] or any other GIF encoder, including streams that keep using
+/// a full dictionary before a deferred clear code. Once the end-of-information
+/// code is read, the remaining sub-blocks are skipped, so [`into_inner`](Self::into_inner)
+/// returns the reader positioned after the block terminator.
+pub struct GifDecoder<R>
+where
+    R: Read,
+{
+    decoder: LzwDecoder<SubBlockReader<R>>,
+    min_code_size: u8,
+    finished: bool,
+}
+
+impl<R> GifDecoder<R>
+where
+    R: Read,
+{
+    /// Reads the minimum code size from `reader`.
+    ///
+    /// Fails with [`Error::CorruptCodeStream`] if it is missing or out of range.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut min_code_size = [0u8; 1];
+        if reader.read(&mut min_code_size)? == 0 {
+            return Err(Error::CorruptCodeStream(
+                "minimum code size is missing".to_string(),
+            ));
+        }
+        let min_code_size = min_code_size[0];
+        if !GIF_MIN_CODE_SIZE_RANGE.contains(&min_code_size) {
+            return Err(Error::CorruptCodeStream(format!(
+                "invalid minimum code size {}",
+                min_code_size
+            )));
+        }
+
+        let blocks = SubBlockReader::new(reader);
+        Ok(Self {
+            decoder: LzwDecoder::with_dialect(
+                blocks,
+                GIF_MAX_CODE_WIDTH,
+                Dialect::gif(min_code_size),
+            )?,
+            min_code_size,
+            finished: false,
+        })
+    }
+
+    /// Returns the minimum code size of the stream.
+    pub fn min_code_size(&self) -> u8 {
+        self.min_code_size
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner().input
+    }
+}
+
+impl<R> Read for GifDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.decoder.read(buf)?;
+        if read == 0 && !buf.is_empty() && !self.finished {
+            self.finished = true;
+            self.decoder.get_mut().skip_to_end()?;
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rng, Rng};
+
+    /// The 10x10 sample image from "What's in a GIF" and its image data.
+    const SAMPLE_PIXELS: [u8; 100] = [
+        1, 1, 1, 1, 1, 2, 2, 2, 2, 2, //
+        1, 1, 1, 1, 1, 2, 2, 2, 2, 2, //
+        1, 1, 1, 1, 1, 2, 2, 2, 2, 2, //
+        1, 1, 1, 0, 0, 0, 0, 2, 2, 2, //
+        1, 1, 1, 0, 0, 0, 0, 2, 2, 2, //
+        2, 2, 2, 0, 0, 0, 0, 1, 1, 1, //
+        2, 2, 2, 0, 0, 0, 0, 1, 1, 1, //
+        2, 2, 2, 2, 2, 1, 1, 1, 1, 1, //
+        2, 2, 2, 2, 2, 1, 1, 1, 1, 1, //
+        2, 2, 2, 2, 2, 1, 1, 1, 1, 1, //
+    ];
+    const SAMPLE_IMAGE_DATA: [u8; 25] = [
+        0x02, 0x16, 0x8C, 0x2D, 0x99, 0x87, 0x2A, 0x1C, 0xDC, 0x33, 0xA0, 0x02, 0x75, 0xEC, 0x95,
+        0xFA, 0xA8, 0xDE, 0x60, 0x8C, 0x04, 0x91, 0x4C, 0x01, 0x00,
+    ];
+
+    fn encode(pixels: &[u8], min_code_size: u8, reset_policy: ResetPolicy) -> Vec<u8> {
+        let mut encoder =
+            GifEncoder::with_reset_policy(Vec::new(), min_code_size, reset_policy).unwrap();
+        encoder.write_all(pixels).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decode(data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        GifDecoder::new(data)?.read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn random_pixels(min_code_size: u8, len: usize) -> Vec<u8> {
+        let mut rng = rng();
+        (0..len)
+            .map(|_| rng.random_range(0..1u16 << min_code_size) as u8)
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_image() {
+        // Act
+        let encoded = encode(&SAMPLE_PIXELS, 2, ResetPolicy::WhenFull);
+        let decoded = decode(&SAMPLE_IMAGE_DATA).unwrap();
+
+        // Assert
+        assert_eq!(encoded, SAMPLE_IMAGE_DATA);
+        assert_eq!(decoded, SAMPLE_PIXELS);
+    }
+
+    #[test]
+    fn test_round_trip_for_every_min_code_size() {
+        for min_code_size in GIF_MIN_CODE_SIZE_RANGE {
+            for policy in [ResetPolicy::WhenFull, ResetPolicy::OnRatioDrop] {
+                // Arrange
+                // Enough pixels to fill the 12-bit dictionary several times
+                let pixels = random_pixels(min_code_size, 60_000);
+
+                // Act
+                let encoded = encode(&pixels, min_code_size, policy);
+
+                // Assert
+                assert_eq!(encoded[0], min_code_size);
+                assert!(
+                    decode(&encoded).unwrap() == pixels,
+                    "round trip failed for minimum code size {} with {:?}",
+                    min_code_size,
+                    policy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sub_block_framing() {
+        // Arrange
+        let pixels = random_pixels(8, 5_000);
+
+        // Act
+        let encoded = encode(&pixels, 8, ResetPolicy::WhenFull);
+
+        // Assert
+        let mut position = 1;
+        let mut lengths = Vec::new();
+        while encoded[position] != 0 {
+            lengths.push(encoded[position] as usize);
+            position += encoded[position] as usize + 1;
+        }
+        assert_eq!(position, encoded.len() - 1);
+        let (last, full) = lengths.split_last().unwrap();
+        assert!(full.iter().all(|&length| length == MAX_SUB_BLOCK_SIZE));
+        assert!((1..=MAX_SUB_BLOCK_SIZE).contains(last));
+    }
+
+    #[test]
+    fn test_decoder_skips_data_after_end_code() {
+        // Arrange
+        let mut data = SAMPLE_IMAGE_DATA[..SAMPLE_IMAGE_DATA.len() - 1].to_vec();
+        data.extend_from_slice(&[3, 0xAA, 0xBB, 0xCC, 0, b';']);
+        let mut decoder = GifDecoder::new(data.as_slice()).unwrap();
+
+        // Act
+        let mut pixels = Vec::new();
+        decoder.read_to_end(&mut pixels).unwrap();
+        let rest = decoder.into_inner();
+
+        // Assert
+        assert_eq!(pixels, SAMPLE_PIXELS);
+        assert_eq!(rest, b";");
+    }
+
+    #[test]
+    fn test_invalid_input_is_rejected() {
+        // Indices must fit the minimum code size
+        let mut encoder = GifEncoder::new(Vec::new(), 2).unwrap();
+        let error = encoder.write_all(&[0, 1, 4]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        for min_code_size in [0, 1, 9, 12] {
+            assert!(matches!(
+                GifEncoder::new(Vec::new(), min_code_size),
+                Err(Error::InvalidOptions(_))
+            ));
+            assert!(matches!(
+                decode(&[min_code_size, 0]),
+                Err(Error::CorruptCodeStream(_))
+            ));
+        }
+        assert!(matches!(decode(&[]), Err(Error::CorruptCodeStream(_))));
+    }
+}
@@ -0,0 +1,596 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::checksum::Crc32;
+use crate::compressor::{DeflateDecoder, DeflateEncoder};
+use crate::Error;
+
+/// The bytes every gzip member starts with.
+pub const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// The file extension of gzip files.
+pub const GZIP_EXTENSION: &str = "gz";
+
+/// Compression method id of DEFLATE, the only method gzip defines.
+const METHOD_DEFLATE: u8 = 8;
+
+/// Header flag: the data is probably text.
+const FLAG_TEXT: u8 = 0x01;
+
+/// Header flag: a CRC-16 of the header precedes the compressed data.
+const FLAG_HEADER_CRC: u8 = 0x02;
+
+/// Header flag: the header carries an extra field.
+const FLAG_EXTRA: u8 = 0x04;
+
+/// Header flag: the header carries the original file name.
+const FLAG_NAME: u8 = 0x08;
+
+/// Header flag: the header carries a comment.
+const FLAG_COMMENT: u8 = 0x10;
+
+/// Flag bits that must be zero.
+const RESERVED_FLAGS: u8 = 0xE0;
+
+/// Operating system id for an unknown system.
+const OS_UNKNOWN: u8 = 255;
+
+/// The header of a gzip member (RFC 1952, section 2.3).
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{GzipCompressor, GzipDecompressor, GzipHeader};
+/// use std::io::Write;
+///
+/// let header = GzipHeader {
+///     file_name: Some(b"server.log".to_vec()),
+///     ..Default::default()
+/// };
+/// let mut compressor = GzipCompressor::with_header(Vec::new(), &header).unwrap();
+/// compressor.write_all(b"GET /index.html 200").unwrap();
+/// let compressed = compressor.finish().unwrap();
+///
+/// let decompressor = GzipDecompressor::new(compressed.as_slice()).unwrap();
+/// assert_eq!(decompressor.header().file_name.as_deref(), Some(&b"server.log"[..]));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GzipHeader {
+    /// The name of the original file, in ISO 8859-1 without the terminating zero byte.
+    pub file_name: Option<Vec<u8>>,
+
+    /// A comment, in ISO 8859-1 without the terminating zero byte.
+    pub comment: Option<Vec<u8>>,
+
+    /// The extra field, stored as is (up to 65 535 bytes).
+    pub extra: Option<Vec<u8>>,
+
+    /// The modification time of the original file in seconds since the Unix
+    /// epoch, or 0 if unknown.
+    pub mtime: u32,
+
+    /// The id of the system the file was compressed on (3 is Unix), or 255 if unknown.
+    pub operating_system: u8,
+
+    /// Whether the data is probably text.
+    pub is_text: bool,
+}
+
+impl Default for GzipHeader {
+    fn default() -> Self {
+        Self {
+            file_name: None,
+            comment: None,
+            extra: None,
+            mtime: 0,
+            operating_system: OS_UNKNOWN,
+            is_text: false,
+        }
+    }
+}
+
+impl GzipHeader {
+    /// Writes the header to `writer`.
+    ///
+    /// Fails with [`Error::InvalidOptions`] if the file name or comment
+    /// contains a zero byte or the extra field is too long.
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<(), Error> {
+        for text in [&self.file_name, &self.comment].into_iter().flatten() {
+            if text.contains(&0) {
+                return Err(Error::InvalidOptions(
+                    "gzip file names and comments cannot contain zero bytes".to_string(),
+                ));
+            }
+        }
+        let extra_length = match &self.extra {
+            Some(extra) => Some(u16::try_from(extra.len()).map_err(|_| {
+                Error::InvalidOptions("the gzip extra field is longer than 65535 bytes".to_string())
+            })?),
+            None => None,
+        };
+
+        let mut flags = 0;
+        if self.is_text {
+            flags |= FLAG_TEXT;
+        }
+        if self.extra.is_some() {
+            flags |= FLAG_EXTRA;
+        }
+        if self.file_name.is_some() {
+            flags |= FLAG_NAME;
+        }
+        if self.comment.is_some() {
+            flags |= FLAG_COMMENT;
+        }
+
+        let mut bytes = Vec::with_capacity(10);
+        bytes.extend_from_slice(&GZIP_MAGIC);
+        bytes.extend_from_slice(&[METHOD_DEFLATE, flags]);
+        bytes.extend_from_slice(&self.mtime.to_le_bytes());
+        bytes.extend_from_slice(&[0, self.operating_system]);
+        if let (Some(extra), Some(length)) = (&self.extra, extra_length) {
+            bytes.extend_from_slice(&length.to_le_bytes());
+            bytes.extend_from_slice(extra);
+        }
+        for text in [&self.file_name, &self.comment].into_iter().flatten() {
+            bytes.extend_from_slice(text);
+            bytes.push(0);
+        }
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Reads and validates a header from `reader`, leaving it at the start of
+    /// the compressed data.
+    ///
+    /// Fails with [`Error::NotAnArchive`] if the data does not start with
+    /// [`GZIP_MAGIC`], with [`Error::UnsupportedCodec`] for compression methods
+    /// other than DEFLATE and with [`Error::ChecksumMismatch`] if the optional
+    /// header CRC does not match.
+    pub fn read_from(reader: &mut impl Read) -> Result<GzipHeader, Error> {
+        let mut reader = HeaderReader {
+            reader,
+            crc: Crc32::new(),
+        };
+
+        let mut fixed = Vec::with_capacity(10);
+        (&mut reader).take(10).read_to_end(&mut fixed)?;
+        if fixed.len() < GZIP_MAGIC.len() || fixed[..GZIP_MAGIC.len()] != GZIP_MAGIC {
+            return Err(Error::NotAnArchive);
+        }
+        if fixed.len() < 10 {
+            return Err(Error::CorruptCodeStream(
+                "stream header is truncated".to_string(),
+            ));
+        }
+        if fixed[2] != METHOD_DEFLATE {
+            return Err(Error::UnsupportedCodec(fixed[2]));
+        }
+        let flags = fixed[3];
+        if flags & RESERVED_FLAGS != 0 {
+            return Err(Error::CorruptCodeStream(format!(
+                "unknown gzip header flags {:#04x}",
+                flags
+            )));
+        }
+
+        let extra = if flags & FLAG_EXTRA != 0 {
+            let mut length = [0u8; 2];
+            reader.read_header_bytes(&mut length)?;
+            let mut extra = vec![0u8; u16::from_le_bytes(length) as usize];
+            reader.read_header_bytes(&mut extra)?;
+            Some(extra)
+        } else {
+            None
+        };
+        let file_name = if flags & FLAG_NAME != 0 {
+            Some(reader.read_zero_terminated()?)
+        } else {
+            None
+        };
+        let comment = if flags & FLAG_COMMENT != 0 {
+            Some(reader.read_zero_terminated()?)
+        } else {
+            None
+        };
+        if flags & FLAG_HEADER_CRC != 0 {
+            let expected = reader.crc.finalize() as u16;
+            let mut crc = [0u8; 2];
+            reader.read_header_bytes(&mut crc)?;
+            if u16::from_le_bytes(crc) != expected {
+                return Err(Error::ChecksumMismatch { entry: None });
+            }
+        }
+
+        Ok(GzipHeader {
+            file_name,
+            comment,
+            extra,
+            mtime: u32::from_le_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]),
+            operating_system: fixed[9],
+            is_text: flags & FLAG_TEXT != 0,
+        })
+    }
+}
+
+/// Reader computing the CRC of the header bytes, for the optional header CRC.
+struct HeaderReader<'a, R>
+where
+    R: Read,
+{
+    reader: &'a mut R,
+    crc: Crc32,
+}
+
+impl<R> HeaderReader<'_, R>
+where
+    R: Read,
+{
+    fn read_header_bytes(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.read_exact(buf).map_err(|e| {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                Error::CorruptCodeStream("stream header is truncated".to_string())
+            } else {
+                Error::Io(e)
+            }
+        })
+    }
+
+    fn read_zero_terminated(&mut self) -> Result<Vec<u8>, Error> {
+        let mut text = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            self.read_header_bytes(&mut byte)?;
+            if byte[0] == 0 {
+                return Ok(text);
+            }
+            text.push(byte[0]);
+        }
+    }
+}
+
+impl<R> Read for HeaderReader<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.crc.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Streaming compressor writing gzip (`.gz`) files (RFC 1952), readable by
+/// `gzip -d` and any other gzip implementation.
+///
+/// The output is a single member: the [`GzipHeader`], the DEFLATE compressed
+/// data (see [`DeflateEncoder`]) and a trailer holding the CRC-32 and the
+/// length of the uncompressed data.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{decompress_gzip, GzipCompressor};
+/// use std::io::Write;
+///
+/// let mut compressor = GzipCompressor::new(Vec::new()).unwrap();
+/// compressor.write_all(b"hello hello hello").unwrap();
+/// let compressed = compressor.finish().unwrap();
+///
+/// assert_eq!(&compressed[..2], &[0x1F, 0x8B]);
+/// assert_eq!(decompress_gzip(&compressed).unwrap(), b"hello hello hello");
+/// ```
+pub struct GzipCompressor<W>
+where
+    W: Write,
+{
+    encoder: DeflateEncoder<W>,
+    crc: Crc32,
+    /// Length of the uncompressed data modulo 2^32, as stored in the trailer.
+    size: u32,
+}
+
+impl<W> GzipCompressor<W>
+where
+    W: Write,
+{
+    /// Writes a default header (no file name, unknown modification time and
+    /// system) to `writer` and returns a compressor for the data.
+    pub fn new(writer: W) -> Result<Self, Error> {
+        Self::with_header(writer, &GzipHeader::default())
+    }
+
+    /// Writes `header` to `writer` and returns a compressor for the data.
+    pub fn with_header(mut writer: W, header: &GzipHeader) -> Result<Self, Error> {
+        header.write_to(&mut writer)?;
+        Ok(Self {
+            encoder: DeflateEncoder::new(writer),
+            crc: Crc32::new(),
+            size: 0,
+        })
+    }
+
+    /// Finishes the compressed data, writes the trailer and returns the underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        let mut writer = self.encoder.finish()?;
+        writer.write_all(&self.crc.finalize().to_le_bytes())?;
+        writer.write_all(&self.size.to_le_bytes())?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W> Write for GzipCompressor<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.encoder.write(buf)?;
+        self.crc.update(&buf[..written]);
+        self.size = self.size.wrapping_add(written as u32);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Streaming decompressor reading gzip (`.gz`) files.
+///
+/// Files made of several members (e.g. written by `cat a.gz b.gz` or by log
+/// rotation appending to a file) are read as the concatenation of all members.
+/// The CRC-32 and length in the trailer of each member are verified once the
+/// member has been read; a mismatch is reported as an [`io::Error`] wrapping
+/// [`Error::ChecksumMismatch`].
+pub struct GzipDecompressor<R>
+where
+    R: Read,
+{
+    decoder: DeflateDecoder<R>,
+    header: GzipHeader,
+    crc: Crc32,
+    size: u32,
+    finished: bool,
+}
+
+impl<R> GzipDecompressor<R>
+where
+    R: Read,
+{
+    /// Reads and validates the header of the first member from `reader`.
+    ///
+    /// Fails with [`Error::NotAnArchive`] if the data does not start with
+    /// [`GZIP_MAGIC`].
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let header = GzipHeader::read_from(&mut reader)?;
+        Ok(Self {
+            decoder: DeflateDecoder::new(reader),
+            header,
+            crc: Crc32::new(),
+            size: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns the header of the first member.
+    pub fn header(&self) -> &GzipHeader {
+        &self.header
+    }
+
+    /// Verifies the trailer of the current member and starts the next member,
+    /// if any.
+    fn finish_member(&mut self) -> Result<(), Error> {
+        let reader = self.decoder.get_mut();
+        let mut trailer = [0u8; 8];
+        reader.read_exact(&mut trailer).map_err(|e| {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                Error::CorruptCodeStream("gzip trailer is missing".to_string())
+            } else {
+                Error::Io(e)
+            }
+        })?;
+        if u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]])
+            != self.crc.finalize()
+        {
+            return Err(Error::ChecksumMismatch { entry: None });
+        }
+        if u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]) != self.size {
+            return Err(Error::CorruptCodeStream(
+                "gzip member length does not match its trailer".to_string(),
+            ));
+        }
+
+        let mut first = [0u8; 1];
+        if reader.read(&mut first)? == 0 {
+            self.finished = true;
+            return Ok(());
+        }
+        match GzipHeader::read_from(&mut first.as_slice().chain(reader)) {
+            Ok(_) => {}
+            Err(Error::NotAnArchive) => {
+                return Err(Error::CorruptCodeStream(
+                    "unexpected data after the last gzip member".to_string(),
+                ))
+            }
+            Err(e) => return Err(e),
+        }
+        self.decoder.reset();
+        self.crc = Crc32::new();
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl<R> Read for GzipDecompressor<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while !self.finished && !buf.is_empty() {
+            let read = self.decoder.read(buf)?;
+            if read > 0 {
+                self.crc.update(&buf[..read]);
+                self.size = self.size.wrapping_add(read as u32);
+                return Ok(read);
+            }
+            self.finish_member()?;
+        }
+        Ok(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        GzipDecompressor::new(data)?.read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn compress(data: &[u8], header: &GzipHeader) -> Vec<u8> {
+        let mut compressor = GzipCompressor::with_header(Vec::new(), header).unwrap();
+        compressor.write_all(data).unwrap();
+        compressor.finish().unwrap()
+    }
+
+    mod vectors {
+        use super::*;
+
+        // Written by `gzip -9`, `gzip -n` and `cat` of two `gzip` files
+        const NAMED: &[u8] = include_bytes!("../../tests/vectors/named.txt.gz");
+        const ANONYMOUS: &[u8] = include_bytes!("../../tests/vectors/text.gz");
+        const TWO_MEMBERS: &[u8] = include_bytes!("../../tests/vectors/two-members.gz");
+
+        #[test]
+        fn test_vectors_decompress() {
+            // Act
+            let named = GzipDecompressor::new(NAMED).unwrap();
+
+            // Assert
+            assert_eq!(named.header().file_name.as_deref(), Some(&b"named.txt"[..]));
+            assert_eq!(named.header().mtime, 1_704_067_200);
+            assert_eq!(named.header().operating_system, 3);
+            assert_eq!(decompress(NAMED).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT\n");
+            assert_eq!(decompress(ANONYMOUS).unwrap(), vector_text());
+            assert_eq!(
+                decompress(TWO_MEMBERS).unwrap(),
+                b"first member\nsecond member\n"
+            );
+        }
+    }
+
+    /// The text of the `text.gz` vector.
+    fn vector_text() -> Vec<u8> {
+        let words = ["press", "archive", "gzip", "member", "trailer", "logs"];
+        (0..3000)
+            .map(|i| format!("{} {} {}\n", i, words[i % 6], words[(i * 7) % 6]))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_header_round_trip() {
+        // Arrange
+        let header = GzipHeader {
+            file_name: Some(b"access.log".to_vec()),
+            comment: Some(b"rotated".to_vec()),
+            extra: Some(vec![b'P', b'R', 2, 0, 1, 2]),
+            mtime: 1_700_000_000,
+            operating_system: 3,
+            is_text: true,
+        };
+
+        // Act
+        let compressed = compress(b"log line\n", &header);
+        let decompressor = GzipDecompressor::new(compressed.as_slice()).unwrap();
+
+        // Assert
+        assert_eq!(decompressor.header(), &header);
+        assert_eq!(decompress(&compressed).unwrap(), b"log line\n");
+    }
+
+    #[test]
+    fn test_members_are_concatenated() {
+        // Arrange
+        let mut data = compress(b"", &GzipHeader::default());
+        data.extend(compress(&vector_text(), &GzipHeader::default()));
+        data.extend(compress(b"tail\n", &GzipHeader::default()));
+
+        // Act
+        let output = decompress(&data).unwrap();
+
+        // Assert
+        let mut expected = vector_text();
+        expected.extend_from_slice(b"tail\n");
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_header_crc_is_verified() {
+        // Arrange
+        // Fixed header with FHCRC set, followed by the low 16 bits of its CRC-32
+        let mut data = vec![0x1F, 0x8B, 8, FLAG_HEADER_CRC, 0, 0, 0, 0, 0, 255];
+        let crc = crate::checksum::crc32(&data) as u16;
+        data.extend_from_slice(&crc.to_le_bytes());
+        data.extend_from_slice(&[0x03, 0x00]);
+        data.extend_from_slice(&[0; 8]);
+        let mut damaged = data.clone();
+        damaged[10] ^= 0x01;
+
+        // Act & Assert
+        assert_eq!(decompress(&data).unwrap(), b"");
+        assert!(matches!(
+            decompress(&damaged),
+            Err(Error::ChecksumMismatch { entry: None })
+        ));
+    }
+
+    #[test]
+    fn test_damaged_trailer_is_rejected() {
+        // Arrange
+        let compressed = compress(b"some log lines", &GzipHeader::default());
+        let mut bad_crc = compressed.clone();
+        bad_crc[compressed.len() - 8] ^= 0x01;
+        let mut bad_size = compressed.clone();
+        bad_size[compressed.len() - 4] ^= 0x01;
+        let missing = &compressed[..compressed.len() - 8];
+        let mut trailing = compressed.clone();
+        trailing.extend_from_slice(b"garbage");
+
+        // Act & Assert
+        assert!(matches!(
+            decompress(&bad_crc),
+            Err(Error::ChecksumMismatch { entry: None })
+        ));
+        for data in [&bad_size[..], missing, &trailing] {
+            assert!(matches!(decompress(data), Err(Error::CorruptCodeStream(_))));
+        }
+    }
+
+    #[test]
+    fn test_invalid_headers_are_rejected() {
+        assert!(matches!(decompress(b"PRES"), Err(Error::NotAnArchive)));
+        assert!(matches!(
+            decompress(&[0x1F, 0x8B, 9, 0, 0, 0, 0, 0, 0, 255]),
+            Err(Error::UnsupportedCodec(9))
+        ));
+        assert!(matches!(
+            decompress(&[0x1F, 0x8B, 8, 0x20, 0, 0, 0, 0, 0, 255]),
+            Err(Error::CorruptCodeStream(_))
+        ));
+        assert!(matches!(
+            decompress(&[0x1F, 0x8B, 8, FLAG_NAME, 0, 0, 0, 0, 0, 255, b'a']),
+            Err(Error::CorruptCodeStream(_))
+        ));
+        let header = GzipHeader {
+            file_name: Some(b"a\0b".to_vec()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            GzipCompressor::with_header(Vec::new(), &header),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+}
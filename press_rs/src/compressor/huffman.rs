@@ -0,0 +1,316 @@
+//! Canonical Huffman codes shared by the entropy coding stages.
+
+use std::io;
+
+use crate::Error;
+
+/// The longest code length any stage of this crate uses.
+pub(crate) const MAX_CODE_LENGTH: u8 = 15;
+
+/// Computes Huffman code lengths for symbols with the given frequencies, no
+/// longer than `max_length` bits.
+///
+/// Unused symbols get length 0. A single used symbol gets length 1, so it can
+/// still be written and read.
+pub(crate) fn code_lengths(frequencies: &[u32], max_length: u8) -> Vec<u8> {
+    let mut lengths = vec![0u8; frequencies.len()];
+    let mut used: Vec<usize> = (0..frequencies.len())
+        .filter(|&symbol| frequencies[symbol] > 0)
+        .collect();
+    match used.len() {
+        0 => return lengths,
+        1 => {
+            lengths[used[0]] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+    used.sort_by_key(|&symbol| (frequencies[symbol], symbol));
+
+    // Build the tree with two queues: the sorted leaves and the merged nodes,
+    // which are created in increasing weight order
+    let leaf_count = used.len();
+    let mut weights: Vec<u64> = used.iter().map(|&s| frequencies[s] as u64).collect();
+    let mut parents = vec![0usize; 2 * leaf_count - 1];
+    let (mut next_leaf, mut next_node) = (0, leaf_count);
+    for node in leaf_count..2 * leaf_count - 1 {
+        let mut children = [0; 2];
+        for child in children.iter_mut() {
+            let take_leaf = next_leaf < leaf_count
+                && (next_node >= node || weights[next_leaf] <= weights[next_node]);
+            if take_leaf {
+                *child = next_leaf;
+                next_leaf += 1;
+            } else {
+                *child = next_node;
+                next_node += 1;
+            }
+        }
+        weights.push(weights[children[0]] + weights[children[1]]);
+        parents[children[0]] = node;
+        parents[children[1]] = node;
+    }
+
+    // Depths follow from the parents, walking down from the root
+    let root = 2 * leaf_count - 2;
+    let mut depths = vec![0usize; 2 * leaf_count - 1];
+    for node in (0..root).rev() {
+        depths[node] = depths[parents[node]] + 1;
+    }
+
+    let max_length = max_length as usize;
+    let mut length_counts = vec![0u32; max_length + 1];
+    for &depth in &depths[..leaf_count] {
+        length_counts[depth.min(max_length)] += 1;
+    }
+    limit_lengths(&mut length_counts, max_length);
+
+    // The least frequent symbols get the longest codes
+    let mut length = max_length;
+    for &symbol in &used {
+        while length_counts[length] == 0 {
+            length -= 1;
+        }
+        length_counts[length] -= 1;
+        lengths[symbol] = length as u8;
+    }
+    lengths
+}
+
+/// Moves codes that were clamped to `max_length` so the code is complete again
+/// (the Kraft sum is exactly 1).
+fn limit_lengths(length_counts: &mut [u32], max_length: usize) {
+    let kraft_sum = |counts: &[u32]| -> u64 {
+        (1..=max_length)
+            .map(|length| (counts[length] as u64) << (max_length - length))
+            .sum()
+    };
+
+    let mut total = kraft_sum(length_counts);
+    while total > 1 << max_length {
+        // Replace a leaf at the maximum depth by splitting a shorter leaf
+        length_counts[max_length] -= 1;
+        let length = (1..max_length)
+            .rev()
+            .find(|&length| length_counts[length] > 0)
+            .expect("a shorter code exists while the code is over-subscribed");
+        length_counts[length] -= 1;
+        length_counts[length + 1] += 2;
+        total -= 1;
+    }
+}
+
+/// Returns the canonical code of every symbol, most significant bit first
+/// (RFC 1951, section 3.2.2). Symbols with length 0 get code 0.
+pub(crate) fn canonical_codes(lengths: &[u8]) -> Vec<u16> {
+    let mut length_counts = [0u16; MAX_CODE_LENGTH as usize + 1];
+    for &length in lengths {
+        length_counts[length as usize] += 1;
+    }
+    length_counts[0] = 0;
+
+    let mut next_code = [0u16; MAX_CODE_LENGTH as usize + 2];
+    let mut code = 0u16;
+    for length in 1..=MAX_CODE_LENGTH as usize {
+        code = (code + length_counts[length - 1]) << 1;
+        next_code[length] = code;
+    }
+
+    lengths
+        .iter()
+        .map(|&length| {
+            if length == 0 {
+                return 0;
+            }
+            let code = next_code[length as usize];
+            next_code[length as usize] += 1;
+            code
+        })
+        .collect()
+}
+
+/// Reverses the lowest `length` bits of `code`, turning a canonical code into
+/// the order in which LSB-first bit writers emit it.
+pub(crate) fn reverse_bits(code: u16, length: u8) -> u16 {
+    if length == 0 {
+        return 0;
+    }
+    code.reverse_bits() >> (16 - length)
+}
+
+/// Decoder for a canonical Huffman code, reading one bit at a time.
+///
+/// Reading bit by bit never consumes input past the last code, so the data
+/// following a code stream stays in the underlying reader.
+pub(crate) struct HuffmanDecoder {
+    /// Number of codes of each length.
+    length_counts: [u16; MAX_CODE_LENGTH as usize + 1],
+    /// The symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl HuffmanDecoder {
+    /// Creates a decoder for the code with the given lengths.
+    ///
+    /// Fails with [`Error::CorruptCodeStream`] if the lengths describe more
+    /// codes than fit (an over-subscribed code). Incomplete codes are accepted;
+    /// reading one of the missing codes fails instead.
+    pub(crate) fn new(lengths: &[u8]) -> Result<HuffmanDecoder, Error> {
+        let mut length_counts = [0u16; MAX_CODE_LENGTH as usize + 1];
+        for &length in lengths {
+            if length > MAX_CODE_LENGTH {
+                return Err(Error::CorruptCodeStream(format!(
+                    "invalid Huffman code length {}",
+                    length
+                )));
+            }
+            length_counts[length as usize] += 1;
+        }
+        length_counts[0] = 0;
+
+        let mut left = 1i32;
+        for &count in &length_counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(Error::CorruptCodeStream(
+                    "over-subscribed Huffman code".to_string(),
+                ));
+            }
+        }
+
+        let mut offsets = [0u16; MAX_CODE_LENGTH as usize + 1];
+        for length in 1..MAX_CODE_LENGTH as usize {
+            offsets[length + 1] = offsets[length] + length_counts[length];
+        }
+        let mut symbols = vec![0u16; lengths.iter().filter(|&&length| length > 0).count()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length > 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+
+        Ok(HuffmanDecoder {
+            length_counts,
+            symbols,
+        })
+    }
+
+    /// Decodes one symbol, pulling the bits of its code from `next_bit`.
+    pub(crate) fn decode(
+        &self,
+        mut next_bit: impl FnMut() -> io::Result<u16>,
+    ) -> Result<u16, Error> {
+        // Canonical codes of one length are consecutive, so the code is
+        // compared against the first code of each length in turn
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.length_counts[1..] {
+            code |= next_bit()? as i32;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(Error::CorruptCodeStream("invalid Huffman code".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kraft_sum(lengths: &[u8]) -> f64 {
+        lengths
+            .iter()
+            .filter(|&&length| length > 0)
+            .map(|&length| 0.5f64.powi(length as i32))
+            .sum()
+    }
+
+    #[test]
+    fn test_code_lengths_follow_frequencies() {
+        // Arrange
+        let frequencies = [10, 0, 5, 1, 1, 20];
+
+        // Act
+        let lengths = code_lengths(&frequencies, MAX_CODE_LENGTH);
+
+        // Assert
+        assert_eq!(lengths, [2, 0, 3, 4, 4, 1]);
+    }
+
+    #[test]
+    fn test_code_lengths_are_limited() {
+        // Arrange
+        // Fibonacci frequencies build the deepest possible tree
+        let mut frequencies = vec![1u32, 1];
+        while frequencies.len() < 30 {
+            let n = frequencies.len();
+            frequencies.push(frequencies[n - 1] + frequencies[n - 2]);
+        }
+
+        // Act
+        let lengths = code_lengths(&frequencies, 7);
+
+        // Assert
+        assert!(lengths.iter().all(|&length| (1..=7).contains(&length)));
+        assert_eq!(kraft_sum(&lengths), 1.0);
+    }
+
+    #[test]
+    fn test_single_and_no_symbols() {
+        assert_eq!(code_lengths(&[0, 0, 7], 15), [0, 0, 1]);
+        assert_eq!(code_lengths(&[0, 0, 0], 15), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_canonical_codes_match_rfc_example() {
+        // Arrange
+        // RFC 1951, section 3.2.2: lengths (3, 3, 3, 3, 3, 2, 4, 4) for A to H
+        let lengths = [3, 3, 3, 3, 3, 2, 4, 4];
+
+        // Act
+        let codes = canonical_codes(&lengths);
+
+        // Assert
+        assert_eq!(codes, [2, 3, 4, 5, 6, 0, 14, 15]);
+    }
+
+    #[test]
+    fn test_decoder_round_trip() {
+        // Arrange
+        let frequencies = [3, 9, 0, 1, 4, 4, 12];
+        let lengths = code_lengths(&frequencies, MAX_CODE_LENGTH);
+        let codes = canonical_codes(&lengths);
+        let message = [6u16, 1, 0, 3, 5, 4, 6, 6, 1];
+        let mut bits = Vec::new();
+        for &symbol in &message {
+            let length = lengths[symbol as usize];
+            for bit in (0..length).rev() {
+                bits.push((codes[symbol as usize] >> bit) & 1);
+            }
+        }
+
+        // Act
+        let decoder = HuffmanDecoder::new(&lengths).unwrap();
+        let mut bits = bits.into_iter();
+        let decoded: Vec<u16> = (0..message.len())
+            .map(|_| decoder.decode(|| Ok(bits.next().unwrap())).unwrap())
+            .collect();
+
+        // Assert
+        assert_eq!(decoded, message);
+        assert_eq!(bits.next(), None);
+    }
+
+    #[test]
+    fn test_over_subscribed_code_is_rejected() {
+        assert!(matches!(
+            HuffmanDecoder::new(&[1, 1, 1]),
+            Err(Error::CorruptCodeStream(_))
+        ));
+    }
+}
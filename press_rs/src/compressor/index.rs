@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::checksum::crc32;
+use crate::compressor::container::decode_block;
+use crate::compressor::parallel::{read_block, BlockPosition};
+use crate::compressor::{StreamHeader, HEADER_SIZE};
+use crate::packager::{EntryType, IndexEntry};
+use crate::Error;
+
+/// The bytes the trailing index starts and the archive ends with.
+pub const INDEX_MAGIC: [u8; 4] = *b"PRIX";
+
+/// The size of the footer closing an indexed archive: the offset of the index
+/// as 8 little-endian bytes followed by [`INDEX_MAGIC`].
+const FOOTER_SIZE: usize = 12;
+
+/// Writes the index of `blocks` and `entries`, followed by its CRC-32 and the
+/// footer. `index_offset` is the position of the index in the archive.
+///
+/// The index holds the number of blocks, then for each block the offset of its
+/// frame in the archive, its offset in the uncompressed stream, its size and
+/// its CRC-32; then the number of entries and for each entry its type, the
+/// length of its name, the name, the offset of its first record, the offset
+/// of its data and the size of its data. Numbers are little-endian.
+pub(crate) fn write_index(
+    writer: &mut impl Write,
+    index_offset: u64,
+    blocks: &[BlockPosition],
+    entries: &[IndexEntry],
+) -> Result<(), Error> {
+    let mut index = INDEX_MAGIC.to_vec();
+    index.extend_from_slice(&(blocks.len() as u32).to_le_bytes());
+    for block in blocks {
+        // Block offsets are recorded from the start of the archive
+        index.extend_from_slice(&(HEADER_SIZE as u64 + block.offset).to_le_bytes());
+        index.extend_from_slice(&block.uncompressed_offset.to_le_bytes());
+        index.extend_from_slice(&block.size.to_le_bytes());
+        index.extend_from_slice(&block.crc32.to_le_bytes());
+    }
+
+    index.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for entry in entries {
+        index.push(entry.entry_type.as_byte());
+        index.extend_from_slice(&(entry.name.len() as u32).to_le_bytes());
+        index.extend_from_slice(entry.name.as_bytes());
+        index.extend_from_slice(&entry.offset.to_le_bytes());
+        index.extend_from_slice(&entry.data_offset.to_le_bytes());
+        index.extend_from_slice(&entry.size.to_le_bytes());
+    }
+
+    let crc = crc32(&index);
+    index.extend_from_slice(&crc.to_le_bytes());
+    index.extend_from_slice(&index_offset.to_le_bytes());
+    index.extend_from_slice(&INDEX_MAGIC);
+    writer.write_all(&index)?;
+    Ok(())
+}
+
+/// Reads the fields of a serialized index.
+struct IndexParser<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> IndexParser<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(corrupt_index("index is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, Error> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+fn corrupt_index(msg: &str) -> Error {
+    Error::CorruptCodeStream(msg.to_string())
+}
+
+/// Random access to an archive written with
+/// [`CompressOptions::index`](crate::compressor::CompressOptions::index).
+///
+/// The trailing index maps offsets in the packed stream and the names of the
+/// packed entries to the independently compressed blocks holding them, so
+/// [`open_entry`](IndexedArchive::open_entry) seeks to the first block of an
+/// entry and decompresses only the blocks the entry spans. Every block is
+/// checked against the CRC-32 recorded in the index.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{CompressOptions, Compressor, IndexedArchive};
+/// use press_rs::packager::ArchiveWriter;
+/// use std::io::{Cursor, Read};
+///
+/// let options = CompressOptions {
+///     parallel_block_size: Some(64 * 1024),
+///     index: true,
+///     ..Default::default()
+/// };
+/// let mut archive = ArchiveWriter::new(Compressor::with_options(Vec::new(), &options).unwrap());
+/// archive.append_reader("notes.txt", 5, &b"hello"[..]).unwrap();
+/// let (compressor, entries) = archive.finish_with_index().unwrap();
+/// let bytes = compressor.finish_with_entries(&entries).unwrap();
+///
+/// let mut archive = IndexedArchive::new(Cursor::new(bytes)).unwrap();
+/// let mut content = String::new();
+/// archive.open_entry("notes.txt").unwrap().read_to_string(&mut content).unwrap();
+/// assert_eq!(content, "hello");
+/// ```
+pub struct IndexedArchive<R>
+where
+    R: Read + Seek,
+{
+    reader: R,
+    header: StreamHeader,
+    blocks: Vec<BlockPosition>,
+    entries: Vec<IndexEntry>,
+    /// The position of the entries by name; later entries replace earlier ones.
+    names: HashMap<String, usize>,
+}
+
+impl<R> IndexedArchive<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the stream header and the trailing index of the archive.
+    ///
+    /// Fails with [`Error::MissingIndex`] if the archive was written without an
+    /// index and with [`Error::CorruptCodeStream`] if the index is damaged.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        reader.seek(SeekFrom::Start(0))?;
+        let header = StreamHeader::read_from(&mut reader)?;
+        if !header.has_index() {
+            return Err(Error::MissingIndex);
+        }
+
+        let end = reader.seek(SeekFrom::End(0))?;
+        if end < (HEADER_SIZE + FOOTER_SIZE) as u64 {
+            return Err(corrupt_index("index footer is missing"));
+        }
+        let footer_offset = end - FOOTER_SIZE as u64;
+        reader.seek(SeekFrom::Start(footer_offset))?;
+        let mut footer = [0u8; FOOTER_SIZE];
+        reader.read_exact(&mut footer)?;
+        let index_offset = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        if footer[8..] != INDEX_MAGIC
+            || index_offset < HEADER_SIZE as u64
+            || index_offset > footer_offset
+        {
+            return Err(corrupt_index("index footer is missing"));
+        }
+
+        reader.seek(SeekFrom::Start(index_offset))?;
+        let mut index = Vec::new();
+        (&mut reader)
+            .take(footer_offset - index_offset)
+            .read_to_end(&mut index)?;
+        let (blocks, entries) = parse_index(&index, index_offset)?;
+        let names = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.name.clone(), i))
+            .collect();
+        Ok(Self {
+            reader,
+            header,
+            blocks,
+            entries,
+            names,
+        })
+    }
+
+    /// Returns the header of the archive.
+    pub fn header(&self) -> &StreamHeader {
+        &self.header
+    }
+
+    /// Returns the entries recorded in the index, in archive order.
+    pub fn entries(&self) -> &[IndexEntry] {
+        &self.entries
+    }
+
+    /// Returns the entry named `name`, the last one if the name occurs more
+    /// than once.
+    pub fn entry(&self, name: &str) -> Option<&IndexEntry> {
+        self.names.get(name).map(|&i| &self.entries[i])
+    }
+
+    /// Returns the size of the packed (uncompressed) stream.
+    pub fn packed_size(&self) -> u64 {
+        self.blocks
+            .last()
+            .map_or(0, |last| last.uncompressed_offset + last.size as u64)
+    }
+
+    /// Returns a reader over the data of the entry named `name`: the contents
+    /// of a file or the target of a link.
+    ///
+    /// Fails with [`Error::EntryNotFound`] if the index has no such entry. A
+    /// damaged block fails a read with [`Error::ChecksumMismatch`] naming the
+    /// entry.
+    pub fn open_entry(&mut self, name: &str) -> Result<IndexedReader<'_, R>, Error> {
+        let entry = self
+            .entry(name)
+            .ok_or_else(|| Error::EntryNotFound(name.to_string()))?;
+        let (offset, size) = (entry.data_offset, entry.size);
+        let mut reader = self.open_at(offset)?;
+        reader.remaining = size;
+        reader.entry = Some(name.to_string());
+        Ok(reader)
+    }
+
+    /// Returns a reader over the packed stream from `offset` to its end,
+    /// decompressing only the blocks from the one holding `offset` on.
+    pub fn open_at(&mut self, offset: u64) -> Result<IndexedReader<'_, R>, Error> {
+        let packed_size = self.packed_size();
+        if offset > packed_size {
+            return Err(Error::InvalidOptions(format!(
+                "offset {} is past the end of the {} packed bytes",
+                offset, packed_size
+            )));
+        }
+        let block = self
+            .blocks
+            .partition_point(|block| block.uncompressed_offset + block.size as u64 <= offset);
+        let skip = self
+            .blocks
+            .get(block)
+            .map_or(0, |block| (offset - block.uncompressed_offset) as usize);
+        Ok(IndexedReader {
+            archive: self,
+            next_block: block,
+            skip,
+            buffer: Vec::new(),
+            read_position: 0,
+            remaining: packed_size - offset,
+            entry: None,
+        })
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Seeks to block `i`, decompresses it and checks it against the index.
+    fn decode(&mut self, i: usize, entry: &Option<String>) -> Result<Vec<u8>, Error> {
+        let position = self.blocks[i];
+        self.reader.seek(SeekFrom::Start(position.offset))?;
+        let (size, compressed) = read_block(&mut self.reader, i as u32)?
+            .ok_or_else(|| corrupt_index("indexed block is missing"))?;
+        let data = decode_block(&compressed, &self.header)?;
+        if size != position.size as usize || data.len() != size {
+            return Err(Error::CorruptCodeStream(format!(
+                "block {} does not match its size in the index",
+                i
+            )));
+        }
+        if crc32(&data) != position.crc32 {
+            return Err(Error::ChecksumMismatch {
+                entry: entry.clone(),
+            });
+        }
+        Ok(data)
+    }
+}
+
+/// Parses and validates the index found at `index_offset`.
+fn parse_index(
+    index: &[u8],
+    index_offset: u64,
+) -> Result<(Vec<BlockPosition>, Vec<IndexEntry>), Error> {
+    if index.len() < INDEX_MAGIC.len() + 4 || index[..INDEX_MAGIC.len()] != INDEX_MAGIC {
+        return Err(corrupt_index("index is missing"));
+    }
+    let (index, crc) = index.split_at(index.len() - 4);
+    if crc32(index) != u32::from_le_bytes(crc.try_into().unwrap()) {
+        return Err(Error::ChecksumMismatch { entry: None });
+    }
+    let mut parser = IndexParser {
+        bytes: &index[INDEX_MAGIC.len()..],
+    };
+
+    let block_count = parser.u32()? as usize;
+    let mut blocks: Vec<BlockPosition> = Vec::with_capacity(block_count.min(index.len() / 24));
+    for _ in 0..block_count {
+        let block = BlockPosition {
+            offset: parser.u64()?,
+            uncompressed_offset: parser.u64()?,
+            size: parser.u32()?,
+            crc32: parser.u32()?,
+        };
+        let (min_offset, uncompressed_offset) = match blocks.last() {
+            Some(last) => (last.offset + 1, last.uncompressed_offset + last.size as u64),
+            None => (HEADER_SIZE as u64, 0),
+        };
+        if block.offset < min_offset
+            || block.offset >= index_offset
+            || block.uncompressed_offset != uncompressed_offset
+        {
+            return Err(corrupt_index("index blocks are out of order"));
+        }
+        blocks.push(block);
+    }
+    let packed_size = blocks
+        .last()
+        .map_or(0, |last| last.uncompressed_offset + last.size as u64);
+
+    let entry_count = parser.u32()? as usize;
+    let mut entries = Vec::with_capacity(entry_count.min(index.len() / 29));
+    for _ in 0..entry_count {
+        let entry_type = EntryType::new(parser.take(1)?[0])?;
+        let name_len = parser.u32()? as usize;
+        let name = String::from_utf8_lossy(parser.take(name_len)?).into_owned();
+        let entry = IndexEntry {
+            name,
+            entry_type,
+            offset: parser.u64()?,
+            data_offset: parser.u64()?,
+            size: parser.u64()?,
+        };
+        if entry.offset > entry.data_offset
+            || entry
+                .data_offset
+                .checked_add(entry.size)
+                .is_none_or(|end| end > packed_size)
+        {
+            return Err(Error::CorruptCodeStream(format!(
+                "entry '{}' lies outside the archive",
+                entry.name
+            )));
+        }
+        entries.push(entry);
+    }
+    if !parser.bytes.is_empty() {
+        return Err(corrupt_index("unexpected data after the index"));
+    }
+    Ok((blocks, entries))
+}
+
+/// Reads a range of the packed stream of an [`IndexedArchive`], decompressing
+/// one block at a time. Returned by [`IndexedArchive::open_entry`] and
+/// [`IndexedArchive::open_at`].
+pub struct IndexedReader<'a, R>
+where
+    R: Read + Seek,
+{
+    archive: &'a mut IndexedArchive<R>,
+    next_block: usize,
+    /// Bytes to skip at the start of the next block.
+    skip: usize,
+    buffer: Vec<u8>,
+    read_position: usize,
+    /// Bytes left to read.
+    remaining: u64,
+    /// The entry being read, named in checksum errors.
+    entry: Option<String>,
+}
+
+impl<R> Read for IndexedReader<'_, R>
+where
+    R: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        while self.read_position == self.buffer.len() {
+            if self.next_block == self.archive.blocks.len() {
+                return Err(Error::TruncatedArchive.into());
+            }
+            self.buffer = self.archive.decode(self.next_block, &self.entry)?;
+            self.read_position = self.skip.min(self.buffer.len());
+            self.skip = 0;
+            self.next_block += 1;
+        }
+
+        let available = (self.buffer.len() - self.read_position).min(buf.len());
+        let read = available.min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        buf[..read].copy_from_slice(&self.buffer[self.read_position..self.read_position + read]);
+        self.read_position += read;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::{decompress_raw, CompressOptions, Compressor, Decompressor};
+    use crate::packager::ArchiveWriter;
+    use std::io::Cursor;
+
+    fn options() -> CompressOptions {
+        CompressOptions {
+            parallel_block_size: Some(4096),
+            index: true,
+            ..Default::default()
+        }
+    }
+
+    /// Packs files whose contents tell them apart, returning the compressed archive.
+    fn indexed_archive(files: &[(&str, usize)]) -> Vec<u8> {
+        let compressor = Compressor::with_options(Vec::new(), &options()).unwrap();
+        let mut archive = ArchiveWriter::new(compressor);
+        archive.append_dir("data").unwrap();
+        for &(name, size) in files {
+            let data = file_data(name, size);
+            archive
+                .append_reader(name, data.len() as u64, data.as_slice())
+                .unwrap();
+        }
+        let (compressor, entries) = archive.finish_with_index().unwrap();
+        compressor.finish_with_entries(&entries).unwrap()
+    }
+
+    fn file_data(name: &str, size: usize) -> Vec<u8> {
+        format!("{} ", name).bytes().cycle().take(size).collect()
+    }
+
+    #[test]
+    fn test_open_entry_reads_one_entry() {
+        // Arrange
+        let files = [
+            ("data/a.txt", 10_000),
+            ("data/b.txt", 100),
+            ("data/c.txt", 20_000),
+        ];
+        let bytes = indexed_archive(&files);
+        let mut archive = IndexedArchive::new(Cursor::new(bytes)).unwrap();
+
+        // Act
+        let mut contents = Vec::new();
+        for (name, _) in files.iter().rev() {
+            let mut content = Vec::new();
+            archive
+                .open_entry(name)
+                .unwrap()
+                .read_to_end(&mut content)
+                .unwrap();
+            contents.push(content);
+        }
+
+        // Assert
+        for ((name, size), content) in files.iter().rev().zip(contents) {
+            assert_eq!(content, file_data(name, *size), "{}", name);
+        }
+        assert_eq!(archive.entries().len(), 4);
+        assert_eq!(
+            archive.entry("data").unwrap().entry_type,
+            EntryType::Directory
+        );
+    }
+
+    #[test]
+    fn test_open_entry_decodes_only_its_blocks() {
+        // Arrange
+        let bytes = indexed_archive(&[("data/a.txt", 40_000), ("data/b.txt", 10)]);
+        let archive = IndexedArchive::new(Cursor::new(bytes.clone())).unwrap();
+        let first_block = archive.blocks[0];
+        // Damage the first block; the last entry does not need it
+        let mut damaged = bytes;
+        damaged[first_block.offset as usize + 40] ^= 0xFF;
+        let mut damaged_archive = IndexedArchive::new(Cursor::new(damaged)).unwrap();
+
+        // Act
+        let mut content = Vec::new();
+        damaged_archive
+            .open_entry("data/b.txt")
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap();
+        let result = damaged_archive
+            .open_entry("data/a.txt")
+            .unwrap()
+            .read_to_end(&mut Vec::new());
+
+        // Assert
+        assert_eq!(content, file_data("data/b.txt", 10));
+        assert!(result.is_err());
+        assert!(archive.blocks.len() > 5);
+    }
+
+    #[test]
+    fn test_open_at_reads_the_packed_stream() {
+        // Arrange
+        let bytes = indexed_archive(&[("data/a.txt", 9_000)]);
+        let packed = {
+            let mut packed = Vec::new();
+            Decompressor::new(bytes.as_slice())
+                .unwrap()
+                .read_to_end(&mut packed)
+                .unwrap();
+            packed
+        };
+        let mut archive = IndexedArchive::new(Cursor::new(bytes)).unwrap();
+
+        // Act
+        let mut tail = Vec::new();
+        archive
+            .open_at(5000)
+            .unwrap()
+            .read_to_end(&mut tail)
+            .unwrap();
+
+        // Assert
+        assert_eq!(archive.packed_size(), packed.len() as u64);
+        assert_eq!(tail, packed[5000..]);
+    }
+
+    #[test]
+    fn test_indexed_archive_still_streams() {
+        // Arrange
+        let bytes = indexed_archive(&[("data/a.txt", 9_000)]);
+
+        // Act
+        let packed = decompress_raw(&bytes).unwrap();
+
+        // Assert
+        let entries = crate::packager::unpack_to_entries(packed).unwrap();
+        assert_eq!(entries[1].data, file_data("data/a.txt", 9_000));
+    }
+
+    #[test]
+    fn test_missing_entry_and_index_are_reported() {
+        // Arrange
+        let bytes = indexed_archive(&[]);
+        let plain = crate::compressor::compress_raw(b"no index").unwrap();
+
+        // Act
+        let mut archive = IndexedArchive::new(Cursor::new(bytes)).unwrap();
+        let missing_entry = archive.open_entry("nothing.txt").map(|_| ());
+        let missing_index = IndexedArchive::new(Cursor::new(plain)).map(|_| ());
+
+        // Assert
+        assert!(matches!(missing_entry, Err(Error::EntryNotFound(name)) if name == "nothing.txt"));
+        assert!(matches!(missing_index, Err(Error::MissingIndex)));
+    }
+
+    #[test]
+    fn test_damaged_index_is_rejected() {
+        // Arrange
+        let mut bytes = indexed_archive(&[("data/a.txt", 100)]);
+        let footer_offset = bytes.len() - FOOTER_SIZE;
+        let index_offset =
+            u64::from_le_bytes(bytes[footer_offset..footer_offset + 8].try_into().unwrap());
+        let mut truncated = bytes.clone();
+        truncated.truncate(footer_offset);
+        bytes[index_offset as usize + 10] ^= 0xFF;
+
+        // Act
+        let damaged = IndexedArchive::new(Cursor::new(bytes)).map(|_| ());
+        let without_footer = IndexedArchive::new(Cursor::new(truncated)).map(|_| ());
+
+        // Assert
+        assert!(matches!(
+            damaged,
+            Err(Error::ChecksumMismatch { entry: None })
+        ));
+        assert!(matches!(without_footer, Err(Error::CorruptCodeStream(_))));
+    }
+
+    #[test]
+    fn test_compress_from_path_records_entries() {
+        // Arrange
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("config")).unwrap();
+        std::fs::write(dir.path().join("config/app.toml"), "debug = true").unwrap();
+        std::fs::write(dir.path().join("big.log"), "log line\n".repeat(10_000)).unwrap();
+
+        // Act
+        let bytes =
+            crate::compressor::compress_from_path_with_options(dir.path(), &options()).unwrap();
+        let mut archive = IndexedArchive::new(Cursor::new(bytes)).unwrap();
+        let mut content = String::new();
+        archive
+            .open_entry("config/app.toml")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        // Assert
+        assert_eq!(content, "debug = true");
+        assert_eq!(archive.entry("big.log").unwrap().size, 90_000);
+    }
+
+    #[test]
+    fn test_index_needs_blocks() {
+        // Arrange
+        let options = CompressOptions {
+            index: true,
+            ..Default::default()
+        };
+
+        // Act
+        let result = Compressor::with_options(Vec::new(), &options).map(|_| ());
+
+        // Assert
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+    }
+}
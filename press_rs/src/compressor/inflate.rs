@@ -0,0 +1,485 @@
+use std::io::{ErrorKind, Read};
+
+use crate::compressor::decompress::BitReader;
+use crate::compressor::deflate::{
+    fixed_distance_lengths, fixed_literal_lengths, BLOCK_DYNAMIC, BLOCK_FIXED, BLOCK_STORED,
+    CODE_LENGTH_ORDER, DISTANCE_BASES, DISTANCE_EXTRA_BITS, DISTANCE_SYMBOLS, END_OF_BLOCK,
+    LENGTH_BASES, LENGTH_EXTRA_BITS, LITERAL_SYMBOLS, WINDOW_SIZE,
+};
+use crate::compressor::huffman::HuffmanDecoder;
+use crate::compressor::BitOrder;
+use crate::Error;
+
+/// Number of bytes decoded ahead of the caller.
+const OUTPUT_CHUNK_SIZE: usize = 32 * 1024;
+
+enum BlockState {
+    /// The next bits are a block header, or the stream ended after the last block.
+    Header,
+    Stored {
+        remaining: usize,
+    },
+    Compressed {
+        literals: HuffmanDecoder,
+        distances: HuffmanDecoder,
+    },
+    Done,
+}
+
+/// Streaming DEFLATE (RFC 1951) decoder that reads a raw DEFLATE stream from
+/// an underlying reader.
+///
+/// Stored, fixed Huffman and dynamic Huffman blocks are supported, so streams
+/// written by zlib, gzip or any other DEFLATE encoder can be read. Decoding
+/// stops after the last block.
+///
+/// The decoder pulls the input one byte at a time and never reads past the
+/// end of the stream, so wrap unbuffered sources (files, sockets) in a
+/// [`BufReader`](std::io::BufReader).
+///
+/// Malformed input is reported as an [`std::io::Error`] of kind
+/// [`InvalidData`](ErrorKind::InvalidData) wrapping [`Error::CorruptCodeStream`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{DeflateDecoder, DeflateEncoder};
+/// use std::io::{Read, Write};
+///
+/// let mut encoder = DeflateEncoder::new(Vec::new());
+/// encoder.write_all(b"TOBEORNOTTOBEORTOBEORNOT").unwrap();
+/// let compressed = encoder.finish().unwrap();
+///
+/// let mut decoder = DeflateDecoder::new(compressed.as_slice());
+/// let mut decompressed = String::new();
+/// decoder.read_to_string(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, "TOBEORNOTTOBEORTOBEORNOT");
+/// ```
+pub struct DeflateDecoder<R>
+where
+    R: Read,
+{
+    reader: BitReader<R>,
+    /// The last [`WINDOW_SIZE`] bytes returned to the caller, followed by the
+    /// decoded bytes not returned yet.
+    output: Vec<u8>,
+    /// Index in `output` of the next byte to return.
+    read_position: usize,
+    state: BlockState,
+    last_block: bool,
+}
+
+impl<R> DeflateDecoder<R>
+where
+    R: Read,
+{
+    /// Creates a decoder that reads the compressed stream from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BitReader::new(reader, BitOrder::LsbFirst),
+            output: Vec::with_capacity(WINDOW_SIZE + OUTPUT_CHUNK_SIZE),
+            read_position: 0,
+            state: BlockState::Header,
+            last_block: false,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying reader.
+    ///
+    /// Once the end of the stream was reached, the reader is positioned right
+    /// after the last byte of the stream.
+    pub fn get_mut(&mut self) -> &mut R {
+        self.reader.get_mut()
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader.into_inner()
+    }
+
+    /// Prepares the decoder for another stream that follows the current one in
+    /// the same reader, as the members of a gzip file do.
+    pub(crate) fn reset(&mut self) {
+        self.reader.align_to_byte();
+        self.output.clear();
+        self.read_position = 0;
+        self.state = BlockState::Header;
+        self.last_block = false;
+    }
+
+    fn read_bits(&mut self, width: u8) -> Result<u16, Error> {
+        self.reader
+            .read_one(width)
+            .map_err(|e| end_of_input(e.into()))
+    }
+
+    /// Decodes until [`OUTPUT_CHUNK_SIZE`] bytes are ready or the stream ends.
+    fn decode_chunk(&mut self) -> Result<(), Error> {
+        if self.read_position > 2 * WINDOW_SIZE {
+            let unused = self.read_position - WINDOW_SIZE;
+            self.output.drain(..unused);
+            self.read_position -= unused;
+        }
+        while self.output.len() - self.read_position < OUTPUT_CHUNK_SIZE {
+            match &self.state {
+                BlockState::Header => self.read_block_header()?,
+                BlockState::Stored { .. } => self.copy_stored()?,
+                BlockState::Compressed { .. } => self.decode_symbol()?,
+                BlockState::Done => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> Result<(), Error> {
+        if self.last_block {
+            self.state = BlockState::Done;
+            return Ok(());
+        }
+        self.last_block = self.read_bits(1)? == 1;
+
+        self.state = match self.read_bits(2)? {
+            BLOCK_STORED => {
+                self.reader.align_to_byte();
+                let mut lengths = [0u8; 4];
+                self.reader
+                    .read_bytes(&mut lengths)
+                    .map_err(|e| end_of_input(e.into()))?;
+                let length = u16::from_le_bytes([lengths[0], lengths[1]]);
+                let inverted = u16::from_le_bytes([lengths[2], lengths[3]]);
+                if length != !inverted {
+                    return Err(Error::CorruptCodeStream(
+                        "stored block length does not match its complement".to_string(),
+                    ));
+                }
+                BlockState::Stored {
+                    remaining: length as usize,
+                }
+            }
+            BLOCK_FIXED => BlockState::Compressed {
+                literals: HuffmanDecoder::new(&fixed_literal_lengths())?,
+                distances: HuffmanDecoder::new(&fixed_distance_lengths())?,
+            },
+            BLOCK_DYNAMIC => self.read_dynamic_header()?,
+            _ => return Err(Error::CorruptCodeStream("invalid block type 3".to_string())),
+        };
+        Ok(())
+    }
+
+    /// Reads the code lengths of a dynamic block (RFC 1951, section 3.2.7).
+    fn read_dynamic_header(&mut self) -> Result<BlockState, Error> {
+        let literal_count = self.read_bits(5)? as usize + 257;
+        let distance_count = self.read_bits(5)? as usize + 1;
+        if literal_count > LITERAL_SYMBOLS || distance_count > DISTANCE_SYMBOLS {
+            return Err(Error::CorruptCodeStream(format!(
+                "too many codes ({} literal/length, {} distance)",
+                literal_count, distance_count
+            )));
+        }
+
+        let total = literal_count + distance_count;
+        let lengths = read_code_lengths(&mut self.reader, total).map_err(end_of_input)?;
+        if lengths[END_OF_BLOCK as usize] == 0 {
+            return Err(Error::CorruptCodeStream(
+                "block has no end-of-block code".to_string(),
+            ));
+        }
+
+        Ok(BlockState::Compressed {
+            literals: HuffmanDecoder::new(&lengths[..literal_count])?,
+            distances: HuffmanDecoder::new(&lengths[literal_count..])?,
+        })
+    }
+
+    fn copy_stored(&mut self) -> Result<(), Error> {
+        let BlockState::Stored { remaining } = &mut self.state else {
+            unreachable!("called for stored blocks only");
+        };
+        if *remaining == 0 {
+            self.state = BlockState::Header;
+            return Ok(());
+        }
+
+        let count = (*remaining).min(OUTPUT_CHUNK_SIZE);
+        *remaining -= count;
+        let start = self.output.len();
+        self.output.resize(start + count, 0);
+        self.reader
+            .read_bytes(&mut self.output[start..])
+            .map_err(|e| end_of_input(e.into()))
+    }
+
+    fn decode_symbol(&mut self) -> Result<(), Error> {
+        let BlockState::Compressed {
+            literals,
+            distances,
+        } = &self.state
+        else {
+            unreachable!("called for compressed blocks only");
+        };
+        let reader = &mut self.reader;
+
+        let symbol = literals
+            .decode(|| reader.read_one(1))
+            .map_err(end_of_input)?;
+        if symbol < END_OF_BLOCK {
+            self.output.push(symbol as u8);
+            return Ok(());
+        }
+        if symbol == END_OF_BLOCK {
+            self.state = BlockState::Header;
+            return Ok(());
+        }
+
+        let index = symbol as usize - 257;
+        if index >= LENGTH_BASES.len() {
+            return Err(Error::CorruptCodeStream(format!(
+                "invalid length symbol {}",
+                symbol
+            )));
+        }
+        let extra = reader
+            .read_one(LENGTH_EXTRA_BITS[index])
+            .map_err(|e| end_of_input(e.into()))?;
+        let length = (LENGTH_BASES[index] + extra) as usize;
+
+        let index = distances
+            .decode(|| reader.read_one(1))
+            .map_err(end_of_input)? as usize;
+        if index >= DISTANCE_BASES.len() {
+            return Err(Error::CorruptCodeStream(format!(
+                "invalid distance symbol {}",
+                index
+            )));
+        }
+        let extra = reader
+            .read_one(DISTANCE_EXTRA_BITS[index])
+            .map_err(|e| end_of_input(e.into()))?;
+        let distance = (DISTANCE_BASES[index] + extra) as usize;
+
+        if distance > self.output.len() {
+            return Err(Error::CorruptCodeStream(format!(
+                "distance {} points before the start of the stream",
+                distance
+            )));
+        }
+        // The source may overlap the bytes being written, so copy byte by byte
+        let start = self.output.len() - distance;
+        for offset in 0..length {
+            let byte = self.output[start + offset];
+            self.output.push(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Reads `count` code lengths written by
+/// [`CodeLengthTable`](crate::compressor::deflate::CodeLengthTable).
+///
+/// Running out of input is reported as [`Error::Io`], so callers decide how a
+/// truncated table is described.
+pub(crate) fn read_code_lengths<R: Read>(
+    reader: &mut BitReader<R>,
+    count: usize,
+) -> Result<Vec<u8>, Error> {
+    let code_length_count = reader.read_one(4)? as usize + 4;
+    let mut code_length_lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_length_lengths[symbol] = reader.read_one(3)? as u8;
+    }
+    let code_lengths = HuffmanDecoder::new(&code_length_lengths)?;
+
+    let mut lengths = Vec::with_capacity(count);
+    while lengths.len() < count {
+        let symbol = code_lengths.decode(|| reader.read_one(1))?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let Some(&previous) = lengths.last() else {
+                    return Err(Error::CorruptCodeStream(
+                        "code length repeat without a previous length".to_string(),
+                    ));
+                };
+                (previous, 3 + reader.read_one(2)? as usize)
+            }
+            17 => (0, 3 + reader.read_one(3)? as usize),
+            _ => (0, 11 + reader.read_one(7)? as usize),
+        };
+        if lengths.len() + repeat > count {
+            return Err(Error::CorruptCodeStream(
+                "code lengths exceed the number of codes".to_string(),
+            ));
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+    Ok(lengths)
+}
+
+/// Reports running out of input as a corrupt stream.
+fn end_of_input(e: Error) -> Error {
+    match e {
+        Error::Io(e) if e.kind() == ErrorKind::UnexpectedEof => {
+            Error::CorruptCodeStream("stream ended before the last block".to_string())
+        }
+        e => e,
+    }
+}
+
+impl<R> Read for DeflateDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.read_position == self.output.len() {
+            if matches!(self.state, BlockState::Done) || buf.is_empty() {
+                return Ok(0);
+            }
+            self.decode_chunk()?;
+        }
+
+        let count = buf.len().min(self.output.len() - self.read_position);
+        buf[..count].copy_from_slice(&self.output[self.read_position..self.read_position + count]);
+        self.read_position += count;
+        Ok(count)
+    }
+}
+
+/// Decompresses a raw DEFLATE stream.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    DeflateDecoder::new(data).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::deflate::deflate;
+
+    mod vectors {
+        use super::*;
+
+        // Raw DEFLATE streams written by zlib
+        const FIXED: &[u8] = &[
+            0x0B, 0xF1, 0x77, 0x72, 0xF5, 0x0F, 0xF2, 0xF3, 0x0F, 0x09, 0x01, 0x33, 0x42, 0x60,
+            0x5C, 0x2E, 0x00,
+        ];
+        const STORED: &[u8] = &[
+            0x01, 0x0B, 0x00, 0xF4, 0xFF, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x20, 0x77, 0x6F, 0x72,
+            0x6C, 0x64,
+        ];
+        const DYNAMIC: &[u8] = include_bytes!("../../tests/vectors/text.deflate");
+
+        #[test]
+        fn test_fixed_block() {
+            assert_eq!(inflate(FIXED).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT\n");
+        }
+
+        #[test]
+        fn test_stored_block() {
+            assert_eq!(inflate(STORED).unwrap(), b"hello world");
+        }
+
+        #[test]
+        fn test_dynamic_blocks() {
+            assert_eq!(inflate(DYNAMIC).unwrap(), vector_text());
+        }
+    }
+
+    /// The text of the `text.deflate` vector.
+    fn vector_text() -> Vec<u8> {
+        let words = ["press", "archive", "deflate", "huffman", "window", "codes"];
+        (0..3000)
+            .map(|i| format!("{} {} {}\n", i, words[i % 6], words[(i * 7) % 6]))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Arrange
+        let mut input = vector_text();
+        input.extend((0..200_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+        input.extend([0u8; 70_000]);
+        input.extend(vector_text());
+
+        // Act
+        let compressed = deflate(&input);
+        let decompressed = inflate(&compressed).unwrap();
+
+        // Assert
+        assert!(decompressed == input);
+    }
+
+    #[test]
+    fn test_compresses_better_than_lzw() {
+        // Arrange
+        let text = vector_text();
+
+        // Act
+        let deflated = deflate(&text);
+        let lzw = crate::compressor::compress_bare(&text);
+
+        // Assert
+        assert!(deflated.len() < lzw.len());
+        assert_eq!(inflate(&deflated).unwrap(), text);
+    }
+
+    #[test]
+    fn test_small_reads() {
+        // Arrange
+        let text = vector_text();
+        let compressed = deflate(&text);
+        let mut decoder = DeflateDecoder::new(compressed.as_slice());
+        let mut output = Vec::new();
+        let mut buffer = [0u8; 7];
+
+        // Act
+        loop {
+            let read = decoder.read(&mut buffer).unwrap();
+            if read == 0 {
+                break;
+            }
+            output.extend_from_slice(&buffer[..read]);
+        }
+
+        // Assert
+        assert_eq!(output, text);
+    }
+
+    #[test]
+    fn test_stops_after_the_last_block() {
+        // Arrange
+        let mut data = deflate(b"payload");
+        data.extend_from_slice(b"TRAILER");
+        let mut decoder = DeflateDecoder::new(data.as_slice());
+        let mut output = Vec::new();
+
+        // Act
+        decoder.read_to_end(&mut output).unwrap();
+        let mut rest = Vec::new();
+        decoder.into_inner().read_to_end(&mut rest).unwrap();
+
+        // Assert
+        assert_eq!(output, b"payload");
+        assert_eq!(rest, b"TRAILER");
+    }
+
+    #[test]
+    fn test_corrupt_streams_are_rejected() {
+        // Arrange
+        let compressed = deflate(&vector_text());
+        let truncated = &compressed[..compressed.len() / 2];
+        // Final block of type 3
+        let invalid_type = [0x07];
+        // Stored block whose length does not match its complement
+        let bad_length = [0x01, 0x05, 0x00, 0x00, 0x00];
+        // Fixed block with a match before the start of the stream
+        let far_match = [0x03, 0x02];
+
+        // Act & Assert
+        for data in [truncated, &invalid_type, &bad_length, &far_match, &[]] {
+            assert!(matches!(inflate(data), Err(Error::CorruptCodeStream(_))));
+        }
+    }
+}
@@ -1,38 +1,320 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
 use std::time::Instant;
-use std::{fmt::Error, path::Path};
 
-use crate::packager::pack;
-use crate::packager::unpack;
+use crate::packager::{list, unpack_with_options, ListEntry, UnpackOptions};
+use crate::packager::{pack_with_index, pack_with_options, PackOptions};
+use crate::Error;
 
+mod bwt;
 mod compress;
+mod container;
 mod decompress;
+mod deflate;
+mod entropy;
+mod gif;
+mod gzip;
+mod huffman;
+mod index;
+mod inflate;
+mod parallel;
+mod z;
+mod zlib;
 
-/// The maximum number of entries in the LZW dictionary (4096 + 1).
-/// Corresponds to a 12-bit code width (2^12 = 4096).
-const MAX_ENTRY_COUNT: usize = 4097;
+pub use bwt::{BwtDecoder, BwtEncoder, BWT_BLOCK_SIZE_RANGE, DEFAULT_BWT_BLOCK_SIZE};
+pub use compress::LzwEncoder;
+pub use container::{
+    Codec, Compressor, Decompressor, StreamHeader, FLAG_ADAPTIVE_HUFFMAN, FLAG_BLOCKS, FLAG_CRC32,
+    FLAG_EARLY_CHANGE, FLAG_HUFFMAN, FLAG_INDEX, FLAG_MSB_FIRST, FORMAT_VERSION, HEADER_SIZE,
+    MAGIC,
+};
+pub use decompress::LzwDecoder;
+pub use deflate::DeflateEncoder;
+pub use gif::{GifDecoder, GifEncoder, GIF_MIN_CODE_SIZE_RANGE};
+pub use gzip::{GzipCompressor, GzipDecompressor, GzipHeader, GZIP_EXTENSION, GZIP_MAGIC};
+pub use index::{IndexedArchive, IndexedReader, INDEX_MAGIC};
+pub use inflate::DeflateDecoder;
+pub use parallel::{DEFAULT_PARALLEL_BLOCK_SIZE, PARALLEL_BLOCK_SIZE_RANGE};
+pub use z::{ZCompressor, ZDecompressor, Z_EXTENSION, Z_MAGIC};
+pub use zlib::{ZlibCompressor, ZlibDecompressor};
 
 /// The initial bit width for LZW codes.
 const INITIAL_CODE_WIDTH: u8 = 8;
 
-/// The maximum bit width for LZW codes.
-const MAX_CODE_WIDTH: u8 = 12;
+/// Code stream conventions that differ between LZW based formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Dialect {
+    /// Bit width of the literal codes; the first code width is one bit wider.
+    literal_width: u8,
+    /// The code after the literals resets the dictionary. Old `.Z` files without
+    /// block mode have no clear code.
+    has_clear_code: bool,
+    /// The stream starts with a clear code.
+    leading_clear: bool,
+    /// The code after the clear code marks the end of the stream, otherwise the
+    /// stream ends with the input.
+    has_end_code: bool,
+    /// Codes are written in groups of eight codes of the same width, and a group
+    /// is padded with zero codes when the width changes or after a clear code (`.Z`).
+    padded_groups: bool,
+    /// With a maximum width of 9 bits, the codes still grow to 10 bits once the
+    /// dictionary is full. A quirk of `compress(1)` that its decoders rely on.
+    widening_full_table: bool,
+}
+
+impl Dialect {
+    /// The code stream of `.pressrs` archives.
+    const PRESS_RS: Dialect = Dialect {
+        literal_width: INITIAL_CODE_WIDTH,
+        has_clear_code: true,
+        leading_clear: true,
+        has_end_code: true,
+        padded_groups: false,
+        widening_full_table: false,
+    };
+
+    /// The code stream of Unix `compress` (`.Z`) files in block mode.
+    const UNIX: Dialect = Dialect {
+        literal_width: INITIAL_CODE_WIDTH,
+        has_clear_code: true,
+        leading_clear: false,
+        has_end_code: false,
+        padded_groups: true,
+        widening_full_table: true,
+    };
+
+    /// The code stream of GIF image data with the given minimum code size.
+    fn gif(min_code_size: u8) -> Dialect {
+        Dialect {
+            literal_width: min_code_size,
+            ..Dialect::PRESS_RS
+        }
+    }
+
+    /// Returns the clear code, the first code after the literals.
+    fn clear_code(self) -> u16 {
+        1 << self.literal_width
+    }
+
+    /// Returns the end-of-information code.
+    fn end_code(self) -> u16 {
+        self.clear_code() + 1
+    }
+
+    /// Returns the first code that is assigned to a dictionary word.
+    fn first_free_code(self) -> u16 {
+        self.clear_code() + self.has_clear_code as u16 + self.has_end_code as u16
+    }
+
+    /// Returns the widest code in a stream whose dictionary has `2^max_code_width` entries.
+    fn widest_code(self, max_code_width: u8) -> u8 {
+        if self.widening_full_table && max_code_width == self.literal_width + 1 {
+            max_code_width + 1
+        } else {
+            max_code_width
+        }
+    }
+}
+
+/// The default maximum bit width for LZW codes, giving a dictionary of 4096 entries.
+pub const DEFAULT_MAX_CODE_WIDTH: u8 = 12;
+
+/// The supported maximum bit widths for LZW codes.
+pub const CODE_WIDTH_RANGE: RangeInclusive<u8> = 9..=16;
 
 /// The default file extension for archives created by this crate.
 pub const EXTENSION: &str = "pressrs";
 
+/// Options controlling how data is compressed.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{compress_raw_with_options, decompress_raw, CompressOptions};
+///
+/// let options = CompressOptions {
+///     max_code_width: 16,
+///     ..Default::default()
+/// };
+/// let text = "a large text corpus ".repeat(1000);
+/// let compressed = compress_raw_with_options(text.as_bytes(), &options).unwrap();
+/// assert_eq!(decompress_raw(&compressed).unwrap(), text.as_bytes());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressOptions {
+    /// The maximum bit width of LZW codes, between 9 and 16 (see [`CODE_WIDTH_RANGE`]).
+    ///
+    /// The dictionary holds up to `2^max_code_width` entries, so
+    /// wider codes usually compress large inputs better (like `compress -b16`)
+    /// at the cost of more memory. The width is recorded in the [`StreamHeader`].
+    pub max_code_width: u8,
+
+    /// When to discard the dictionary once it is full.
+    pub reset_policy: ResetPolicy,
+
+    /// The order in which code bits are packed into bytes.
+    pub bit_order: BitOrder,
+
+    /// Increase the code width one code early, as TIFF and PDF (`EarlyChange 1`) do.
+    pub early_change: bool,
+
+    /// An optional Huffman stage applied to the LZW codes, recorded in the
+    /// [`StreamHeader`]. Only supported for LSB-first press_rs streams.
+    pub entropy: EntropyCoding,
+
+    /// The block size of [`Codec::Bwt`] in units of 100 000 bytes, between 1
+    /// and 9 (see [`BWT_BLOCK_SIZE_RANGE`]), recorded in the [`StreamHeader`].
+    pub bwt_block_size: u8,
+
+    /// The codec used by [`Compressor`] and the functions built on it, recorded
+    /// in the [`StreamHeader`]. The LZW options only apply to [`Codec::Lzw`].
+    pub codec: Codec,
+
+    /// Split the payload of [`Compressor`] into independently compressed
+    /// blocks of this many bytes (see [`PARALLEL_BLOCK_SIZE_RANGE`]), so that
+    /// several blocks are compressed and decompressed at the same time. Each
+    /// block is compressed with [`codec`](Self::codec); `None` writes a single
+    /// stream.
+    pub parallel_block_size: Option<usize>,
+
+    /// The number of threads compressing blocks when
+    /// [`parallel_block_size`](Self::parallel_block_size) is set, or 0 for
+    /// [`std::thread::available_parallelism`]. The output does not depend on it.
+    pub threads: usize,
+
+    /// Append an index of the blocks and entries to the archive, so single
+    /// entries can be read without decompressing everything before them (see
+    /// [`IndexedArchive`]). Needs [`parallel_block_size`](Self::parallel_block_size).
+    pub index: bool,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        Self {
+            max_code_width: DEFAULT_MAX_CODE_WIDTH,
+            reset_policy: ResetPolicy::default(),
+            bit_order: BitOrder::default(),
+            early_change: false,
+            entropy: EntropyCoding::None,
+            bwt_block_size: DEFAULT_BWT_BLOCK_SIZE,
+            codec: Codec::Lzw,
+            parallel_block_size: None,
+            threads: 0,
+            index: false,
+        }
+    }
+}
+
+impl CompressOptions {
+    /// Options for the LZW variant of TIFF and of the PDF `LZWDecode` filter:
+    /// MSB-first codes of up to 12 bits with early change.
+    ///
+    /// For PDF streams with `/EarlyChange 0`, set [`early_change`](Self::early_change)
+    /// to `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use press_rs::compressor::{decompress_bare_with_options, CompressOptions};
+    ///
+    /// // The LZWDecode example from the PDF reference
+    /// let stream = [0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01];
+    /// let decoded = decompress_bare_with_options(&stream, &CompressOptions::tiff()).unwrap();
+    /// assert_eq!(decoded, b"-----A---B");
+    /// ```
+    pub fn tiff() -> CompressOptions {
+        CompressOptions {
+            max_code_width: 12,
+            bit_order: BitOrder::MsbFirst,
+            early_change: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// The order in which the bits of LZW codes are packed into bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Codes fill each byte from the least significant bit (press_rs, GIF, `.Z`).
+    #[default]
+    LsbFirst,
+    /// Codes fill each byte from the most significant bit (TIFF, PDF).
+    MsbFirst,
+}
+
+/// When the LZW encoder discards its dictionary and emits a clear code.
+///
+/// The decoder follows either policy, as it only reacts to the clear codes in
+/// the stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResetPolicy {
+    /// Reset as soon as the dictionary is full.
+    #[default]
+    WhenFull,
+    /// Keep using the full dictionary and reset only when the compression ratio
+    /// stops improving, checked every 10 000 input bytes (like `compress(1)`).
+    /// Compresses uniform data better and still adapts when the data changes.
+    OnRatioDrop,
+}
+
+/// A Huffman stage after LZW, coding frequent codes in fewer bits.
+///
+/// LZW writes every code with the full current width, although some codes
+/// (often the literals of text) are much more common than others. The stage
+/// codes the literals, the clear code and the end code as symbols of their
+/// own, and the codes of dictionary words as 256 groups of consecutive codes
+/// followed by the position in the group.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{
+///     compress_raw_with_options, decompress_raw, CompressOptions, EntropyCoding,
+/// };
+///
+/// let options = CompressOptions {
+///     entropy: EntropyCoding::StaticHuffman,
+///     ..Default::default()
+/// };
+/// let text = "the quick brown fox jumps over the lazy dog ".repeat(100);
+/// let compressed = compress_raw_with_options(text.as_bytes(), &options).unwrap();
+/// assert_eq!(decompress_raw(&compressed).unwrap(), text.as_bytes());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntropyCoding {
+    /// Write the LZW codes as they are.
+    #[default]
+    None,
+    /// Build a Huffman code for each block of 16 384 codes and store it in
+    /// front of the block. Compresses best, but each block is only written
+    /// once it is complete.
+    StaticHuffman,
+    /// Rebuild the Huffman code from the counts of the codes seen so far, the
+    /// same way in the encoder and the decoder, so no code is stored. Codes are
+    /// written as soon as they are produced.
+    AdaptiveHuffman,
+}
+
 /// Compresses a file or directory path into a packed archive.
 ///
 /// This function performs two steps:
 /// 1. **Packing:** Traverses the directory (or single file) and serializes it into a binary format.
 /// 2. **Compression:** Applies LZW compression to the packed data.
 ///
+/// The result starts with a [`StreamHeader`] identifying the archive and ends
+/// with a CRC-32 of the packed data, see [`Compressor`]. Both steps are
+/// streamed, so only the compressed output is held in memory.
+///
 /// # Arguments
 ///
 /// * `path` - A path to the file or directory to compress.
 ///
 /// # Returns
 ///
-/// Returns a `Result` containing the compressed bytes (`Vec<u8>`) or an error.
+/// Returns a `Result` containing the compressed bytes (`Vec<u8>`) or an [`Error`]
+/// if the path cannot be read or stored in the archive.
 ///
 /// # Side Effects
 ///
@@ -47,33 +329,119 @@ pub const EXTENSION: &str = "pressrs";
 /// // Now you can save `compressed_data` to a file, e.g., "archive.pressrs"
 /// ```
 pub fn compress_from_path(path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
+    compress_from_path_with_options(path, &CompressOptions::default())
+}
+
+/// Compresses a file or directory path into a packed archive using the given
+/// [`CompressOptions`].
+///
+/// With [`CompressOptions::index`], the packed entries are recorded in the
+/// trailing index, so they can be read one by one with [`IndexedArchive`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::compressor::{compress_from_path_with_options, CompressOptions};
+///
+/// let options = CompressOptions {
+///     max_code_width: 16,
+///     ..Default::default()
+/// };
+/// let compressed_data = compress_from_path_with_options("./my_folder", &options).unwrap();
+/// ```
+pub fn compress_from_path_with_options(
+    path: impl AsRef<Path>,
+    options: &CompressOptions,
+) -> Result<Vec<u8>, Error> {
+    compress_from_path_with_pack_options(path, options, &PackOptions::default())
+}
+
+/// Compresses a file or directory path into a packed archive, choosing the
+/// packed files with [`PackOptions`] and the compression with [`CompressOptions`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::compressor::{compress_from_path_with_pack_options, CompressOptions};
+/// use press_rs::packager::{PackOptions, PathFilter};
+///
+/// let pack_options = PackOptions {
+///     filter: PathFilter::new().exclude("node_modules"),
+///     ignore_files: true,
+///     ..Default::default()
+/// };
+/// let compressed_data =
+///     compress_from_path_with_pack_options("./my_project", &CompressOptions::default(), &pack_options)
+///         .unwrap();
+/// ```
+pub fn compress_from_path_with_pack_options(
+    path: impl AsRef<Path>,
+    options: &CompressOptions,
+    pack_options: &PackOptions,
+) -> Result<Vec<u8>, Error> {
     let now = Instant::now();
-    let result = Ok(compress::lzw_compress(&pack(path)));
+    let compressor = Compressor::with_options(Vec::new(), options)?;
+    let result = if options.index {
+        let (compressor, entries) = pack_with_index(path, compressor, pack_options)?;
+        compressor.finish_with_entries(&entries)?
+    } else {
+        pack_with_options(path, compressor, pack_options)?.finish()?
+    };
 
     println!("Compression took {} ms", now.elapsed().as_millis());
-    return result;
+    Ok(result)
 }
 
 /// Compresses raw byte data using LZW algorithm without packing.
 ///
 /// Use this function if you already have binary data (e.g., a serialized struct or text)
 /// and just want to compress it without creating a file archive structure
-/// or you already have packed data.
+/// or you already have packed data. The output starts with a [`StreamHeader`]
+/// and ends with a CRC-32 trailer; use [`compress_bare`] for the bare LZW bit stream.
 ///
 /// # Arguments
 ///
 /// * `data` - A slice of bytes to compress.
 ///
+/// # Returns
+///
+/// Returns a `Result` containing the compressed bytes.
+///
 /// # Examples
 ///
 /// ``` no_run
 /// use press_rs::compressor::compress_raw;
 /// use press_rs::packager::pack;
 ///
-/// let data = pack("./my_folder");
-/// let compressed = compress_raw(&data);
+/// let data = pack("./my_folder").unwrap();
+/// let compressed = compress_raw(&data).unwrap();
 /// ```
-pub fn compress_raw(data: &[u8]) -> Vec<u8> {
+pub fn compress_raw(data: &[u8]) -> Result<Vec<u8>, Error> {
+    compress_raw_with_options(data, &CompressOptions::default())
+}
+
+/// Compresses raw byte data without packing, using the given [`CompressOptions`].
+///
+/// Fails with [`Error::InvalidOptions`] if the options are out of range.
+pub fn compress_raw_with_options(data: &[u8], options: &CompressOptions) -> Result<Vec<u8>, Error> {
+    let mut compressor = Compressor::with_options(Vec::new(), options)?;
+    compressor.write_all(data)?;
+    compressor.finish()
+}
+
+/// Compresses raw byte data into a bare LZW bit stream, without the [`StreamHeader`].
+///
+/// The output starts directly with the clear code; restore it with [`decompress_bare`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{compress_bare, decompress_bare};
+///
+/// let compressed = compress_bare(b"TOBEORNOTTOBEORTOBEORNOT");
+/// assert_eq!(decompress_bare(&compressed).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT");
+/// ```
+pub fn compress_bare(data: &[u8]) -> Vec<u8> {
     compress::lzw_compress(data)
 }
 
@@ -83,11 +451,18 @@ pub fn compress_raw(data: &[u8]) -> Vec<u8> {
 /// 1. **Decompression:** Reads the file at `path` and decodes LZW.
 /// 2. **Unpacking:** Recreates the original file/directory structure at `output`.
 ///
+/// Both steps are streamed, so memory usage does not grow with the archive size.
+///
 /// # Arguments
 ///
 /// * `path` - Path to the `.pressrs` archive file.
 /// * `output` - Path to the directory where files should be extracted.
 ///
+/// # Errors
+///
+/// Returns an [`Error`] if the archive cannot be read, is corrupt, or cannot be
+/// written to `output`.
+///
 /// # Side Effects
 ///
 /// * Creates files and directories on the disk.
@@ -99,18 +474,107 @@ pub fn compress_raw(data: &[u8]) -> Vec<u8> {
 /// use press_rs::compressor::decompress_from_path_to_path;
 ///
 /// // Extracts contents of "backup.pressrs" into the "restored" folder
-/// decompress_from_path_to_path("backup.pressrs", "./restored");
+/// decompress_from_path_to_path("backup.pressrs", "./restored").unwrap();
+/// ```
+pub fn decompress_from_path_to_path(
+    path: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<(), Error> {
+    decompress_from_path_to_path_with_options(path, output, &UnpackOptions::default())
+}
+
+/// Decompresses an archive from a file and unpacks it to a destination using
+/// the given [`UnpackOptions`].
+///
+/// With [`UnpackOptions::filter`], only the selected entries are written; the
+/// rest of the archive is still decompressed to verify its checksum, but
+/// nothing else reaches the disk.
+///
+/// # Arguments
+///
+/// * `path` - Path to the `.pressrs` archive file.
+/// * `output` - Path to the directory where files should be extracted.
+/// * `options` - Options controlling the extraction.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if the archive cannot be read, is corrupt, or cannot be
+/// written to `output`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::compressor::decompress_from_path_to_path_with_options;
+/// use press_rs::packager::{PathFilter, UnpackOptions};
+///
+/// // Restores a single file from a nightly backup
+/// let options = UnpackOptions {
+///     filter: PathFilter::new().include("etc/app.toml"),
+///     ..Default::default()
+/// };
+/// decompress_from_path_to_path_with_options("nightly.pressrs", "./restored", &options).unwrap();
 /// ```
-pub fn decompress_from_path_to_path(path: impl AsRef<Path>, output: impl AsRef<Path>) {
+pub fn decompress_from_path_to_path_with_options(
+    path: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    options: &UnpackOptions,
+) -> Result<(), Error> {
     let now = Instant::now();
-    unpack(decompress::lzw_decompress(path), output);
+    read_archive_file(path, |decompressor| {
+        unpack_with_options(decompressor, output, options)
+    })?;
 
     println!("Decompression took {} ms", now.elapsed().as_millis());
+    Ok(())
 }
 
-/// Decompresses raw LZW-encoded bytes.
+/// Lists the entries of a compressed archive file without unpacking it.
 ///
-/// Use this to restore data compressed with [`compress_raw`].
+/// The archive is decompressed as a stream and entry data is discarded, so
+/// nothing is written to disk. The checksum of the whole payload is verified
+/// once the archive has been read.
+///
+/// # Arguments
+///
+/// * `path` - The path to the compressed archive file.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the [`ListEntry`] of every entry, or an
+/// [`Error`] if the archive cannot be read or is corrupt.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::compressor::list_from_path;
+///
+/// for entry in list_from_path("backup.pressrs").unwrap() {
+///     println!("{} ({} bytes)", entry.name, entry.size);
+/// }
+/// ```
+pub fn list_from_path(path: impl AsRef<Path>) -> Result<Vec<ListEntry>, Error> {
+    read_archive_file(path, |decompressor| list(decompressor))
+}
+
+/// Opens the compressed archive at `path`, passes the decompressed stream to
+/// `read` and then verifies the checksum of the whole payload.
+fn read_archive_file<T>(
+    path: impl AsRef<Path>,
+    read: impl FnOnce(&mut Decompressor<BufReader<File>>) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let file = BufReader::new(File::open(path)?);
+    let mut decompressor = Decompressor::new(file)?;
+    let result = read(&mut decompressor)?;
+    // The archive ends before the payload does; read the rest to verify the checksum
+    io::copy(&mut decompressor, &mut io::sink())?;
+    Ok(result)
+}
+
+/// Decompresses data starting with a [`StreamHeader`], without unpacking it.
+///
+/// Use this to restore data compressed with [`compress_raw`] or
+/// [`compress_raw_with_options`]. The header selects the [`Codec`] (LZW, DEFLATE
+/// or BWT) and its settings, so any `.pressrs` payload can be read.
 ///
 /// # Arguments
 ///
@@ -118,7 +582,14 @@ pub fn decompress_from_path_to_path(path: impl AsRef<Path>, output: impl AsRef<P
 ///
 /// # Returns
 ///
-/// Returns a `Vec<u8>` containing the original uncompressed data.
+/// Returns a `Result` containing the original uncompressed data.
+///
+/// # Errors
+///
+/// * [`Error::NotAnArchive`] if the data does not start with the magic bytes.
+/// * [`Error::UnsupportedVersion`] if the header has a newer format version.
+/// * [`Error::CorruptCodeStream`] if the header or the payload is invalid for its codec.
+/// * [`Error::ChecksumMismatch`] if the data does not match its CRC-32.
 ///
 /// # Examples
 ///
@@ -126,10 +597,138 @@ pub fn decompress_from_path_to_path(path: impl AsRef<Path>, output: impl AsRef<P
 /// use press_rs::compressor::{decompress_raw, compress_raw};
 /// use press_rs::packager::pack;
 ///
-/// let data = pack("./my_folder");
-/// let compressed = compress_raw(&data);
-/// let decompressed = decompress_raw(compressed.as_slice());
+/// let data = pack("./my_folder").unwrap();
+/// let compressed = compress_raw(&data).unwrap();
+/// let decompressed = decompress_raw(compressed.as_slice()).unwrap();
 /// ```
-pub fn decompress_raw(data: &[u8]) -> Vec<u8> {
+pub fn decompress_raw(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    Decompressor::new(data)?.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Compresses raw byte data into a bare LZW bit stream using the given
+/// [`CompressOptions`], e.g. [`CompressOptions::tiff`] for a TIFF strip or a
+/// PDF `LZWDecode` stream.
+pub fn compress_bare_with_options(
+    data: &[u8],
+    options: &CompressOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut encoder = LzwEncoder::with_options(Vec::new(), options)?;
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Decompresses a bare LZW bit stream produced by [`compress_bare`] or [`LzwEncoder`].
+pub fn decompress_bare(data: &[u8]) -> Result<Vec<u8>, Error> {
     decompress::lzw_decompress_bytes(data)
 }
+
+/// Decompresses a bare LZW bit stream written with the given [`CompressOptions`],
+/// e.g. [`CompressOptions::tiff`] for a TIFF strip or a PDF `LZWDecode` stream.
+pub fn decompress_bare_with_options(
+    data: &[u8],
+    options: &CompressOptions,
+) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    LzwDecoder::with_options(data, options)?.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Compresses raw byte data into the Unix `compress` (`.Z`) format, using the
+/// defaults of `compress(1)`. See [`ZCompressor`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{compress_z, decompress_z};
+///
+/// let compressed = compress_z(b"TOBEORNOTTOBEORTOBEORNOT").unwrap();
+/// assert_eq!(decompress_z(&compressed).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT");
+/// ```
+pub fn compress_z(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut compressor = ZCompressor::new(Vec::new());
+    compressor.write_all(data)?;
+    compressor.finish()
+}
+
+/// Compresses raw byte data into the Unix `compress` (`.Z`) format using the
+/// given [`CompressOptions`].
+pub fn compress_z_with_options(data: &[u8], options: &CompressOptions) -> Result<Vec<u8>, Error> {
+    let mut compressor = ZCompressor::with_options(Vec::new(), options)?;
+    compressor.write_all(data)?;
+    compressor.finish()
+}
+
+/// Decompresses a Unix `compress` (`.Z`) file, e.g. one written by `compress(1)`.
+///
+/// Fails with [`Error::NotAnArchive`] if the data does not start with [`Z_MAGIC`]
+/// and with [`Error::CorruptCodeStream`] if the code stream is invalid.
+pub fn decompress_z(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    ZDecompressor::new(data)?.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Compresses raw byte data into a raw DEFLATE (RFC 1951) stream, without a
+/// header or checksum. See [`DeflateEncoder`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{compress_deflate, decompress_deflate};
+///
+/// let compressed = compress_deflate(b"TOBEORNOTTOBEORTOBEORNOT");
+/// assert_eq!(decompress_deflate(&compressed).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT");
+/// ```
+pub fn compress_deflate(data: &[u8]) -> Vec<u8> {
+    deflate::deflate(data)
+}
+
+/// Decompresses a raw DEFLATE (RFC 1951) stream, e.g. one written by zlib with
+/// a negative window size.
+pub fn decompress_deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    inflate::inflate(data)
+}
+
+/// Compresses raw byte data into a gzip (`.gz`) file with a default header.
+/// See [`GzipCompressor`].
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{compress_gzip, decompress_gzip};
+///
+/// let compressed = compress_gzip(b"TOBEORNOTTOBEORTOBEORNOT").unwrap();
+/// assert_eq!(decompress_gzip(&compressed).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT");
+/// ```
+pub fn compress_gzip(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut compressor = GzipCompressor::new(Vec::new())?;
+    compressor.write_all(data)?;
+    compressor.finish()
+}
+
+/// Decompresses a gzip (`.gz`) file, concatenating the data of all its members.
+///
+/// Fails with [`Error::NotAnArchive`] if the data does not start with
+/// [`GZIP_MAGIC`] and with [`Error::ChecksumMismatch`] if a member does not
+/// match its CRC-32.
+pub fn decompress_gzip(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    GzipDecompressor::new(data)?.read_to_end(&mut output)?;
+    Ok(output)
+}
+
+/// Compresses raw byte data into a zlib stream (RFC 1950). See [`ZlibCompressor`].
+pub fn compress_zlib(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut compressor = ZlibCompressor::new(Vec::new())?;
+    compressor.write_all(data)?;
+    compressor.finish()
+}
+
+/// Decompresses a zlib stream (RFC 1950), verifying its Adler-32 checksum.
+pub fn decompress_zlib(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output = Vec::new();
+    ZlibDecompressor::new(data)?.read_to_end(&mut output)?;
+    Ok(output)
+}
@@ -0,0 +1,518 @@
+use std::io::{self, Read, Write};
+use std::ops::RangeInclusive;
+use std::thread;
+
+use crate::checksum::crc32;
+use crate::compressor::container::{decode_block, encode_block};
+use crate::compressor::{CompressOptions, StreamHeader};
+use crate::Error;
+
+/// The default size of the independently compressed blocks, see
+/// [`CompressOptions::parallel_block_size`].
+pub const DEFAULT_PARALLEL_BLOCK_SIZE: usize = 1 << 20;
+
+/// The supported sizes of independently compressed blocks, from 1 KiB to 64 MiB.
+pub const PARALLEL_BLOCK_SIZE_RANGE: RangeInclusive<usize> = 1 << 10..=1 << 26;
+
+/// The size of the frame in front of each block: the block index, the
+/// uncompressed size and the compressed size as 4 little-endian bytes each.
+pub(crate) const FRAME_SIZE: usize = 12;
+
+/// Fails with [`Error::InvalidOptions`] unless `block_size` is in
+/// [`PARALLEL_BLOCK_SIZE_RANGE`].
+pub(crate) fn check_parallel_block_size(block_size: usize) -> Result<(), Error> {
+    if PARALLEL_BLOCK_SIZE_RANGE.contains(&block_size) {
+        Ok(())
+    } else {
+        Err(Error::InvalidOptions(format!(
+            "parallel block size must be between {} and {} bytes, got {}",
+            PARALLEL_BLOCK_SIZE_RANGE.start(),
+            PARALLEL_BLOCK_SIZE_RANGE.end(),
+            block_size
+        )))
+    }
+}
+
+/// Returns the number of worker threads to use, `threads` or the available
+/// parallelism if it is 0.
+pub(crate) fn thread_count(threads: usize) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+}
+
+/// Runs `job` on each item, on a thread of its own if there is more than one,
+/// and returns the results in order.
+fn run_parallel<T, U, F>(items: &[T], job: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync,
+{
+    if items.len() < 2 {
+        return items.iter().map(job).collect();
+    }
+    thread::scope(|scope| {
+        let job = &job;
+        let handles: Vec<_> = items
+            .iter()
+            .map(|item| scope.spawn(move || job(item)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
+
+/// Writes the frame of a block.
+fn write_frame(
+    writer: &mut impl Write,
+    index: u32,
+    size: u32,
+    compressed_size: u32,
+) -> io::Result<()> {
+    let mut frame = [0u8; FRAME_SIZE];
+    frame[..4].copy_from_slice(&index.to_le_bytes());
+    frame[4..8].copy_from_slice(&size.to_le_bytes());
+    frame[8..].copy_from_slice(&compressed_size.to_le_bytes());
+    writer.write_all(&frame)
+}
+
+/// The position of a compressed block, recorded for the trailing index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlockPosition {
+    /// The offset of the block frame from the start of the payload.
+    pub(crate) offset: u64,
+    /// The offset of the block data in the uncompressed stream.
+    pub(crate) uncompressed_offset: u64,
+    /// The uncompressed size of the block.
+    pub(crate) size: u32,
+    /// The CRC-32 of the uncompressed block.
+    pub(crate) crc32: u32,
+}
+
+/// Splits the input into blocks of a fixed size and compresses them
+/// independently, several blocks at a time on threads of their own.
+///
+/// Each block is a complete payload of the selected codec (an LZW block starts
+/// with a clear code) behind a frame holding the block index, the uncompressed
+/// size and the compressed size. The blocks are written in order and end with a
+/// frame of two zero sizes. Up to one block per thread is buffered.
+pub(crate) struct BlockEncoder<W>
+where
+    W: Write,
+{
+    writer: W,
+    options: CompressOptions,
+    block_size: usize,
+    threads: usize,
+    buffer: Vec<u8>,
+    /// The number of bytes written to `writer` so far.
+    position: u64,
+    /// The positions of the blocks written so far.
+    blocks: Vec<BlockPosition>,
+}
+
+impl<W> BlockEncoder<W>
+where
+    W: Write,
+{
+    /// Creates an encoder compressing blocks of `options.parallel_block_size`
+    /// bytes on `options.threads` threads.
+    pub(crate) fn new(writer: W, options: &CompressOptions) -> Result<Self, Error> {
+        let block_size = options
+            .parallel_block_size
+            .unwrap_or(DEFAULT_PARALLEL_BLOCK_SIZE);
+        check_parallel_block_size(block_size)?;
+        let threads = thread_count(options.threads);
+        Ok(Self {
+            writer,
+            options: CompressOptions {
+                parallel_block_size: None,
+                ..options.clone()
+            },
+            block_size,
+            threads,
+            buffer: Vec::new(),
+            position: 0,
+            blocks: Vec::new(),
+        })
+    }
+
+    /// Compresses the buffered blocks and writes them in order.
+    fn write_blocks(&mut self) -> Result<(), Error> {
+        let blocks: Vec<&[u8]> = self.buffer.chunks(self.block_size).collect();
+        let options = &self.options;
+        let compressed = run_parallel(&blocks, |block| {
+            encode_block(block, options).map(|compressed| (compressed, crc32(block)))
+        });
+        for (block, compressed) in blocks.iter().zip(compressed) {
+            let (compressed, crc32) = compressed?;
+            let compressed_size = u32::try_from(compressed.len()).map_err(|_| {
+                Error::InvalidOptions("compressed block is larger than 4 GiB".to_string())
+            })?;
+            let uncompressed_offset = self
+                .blocks
+                .last()
+                .map_or(0, |last| last.uncompressed_offset + last.size as u64);
+            self.blocks.push(BlockPosition {
+                offset: self.position,
+                uncompressed_offset,
+                size: block.len() as u32,
+                crc32,
+            });
+            write_frame(
+                &mut self.writer,
+                self.blocks.len() as u32 - 1,
+                block.len() as u32,
+                compressed_size,
+            )?;
+            self.writer.write_all(&compressed)?;
+            self.position += (FRAME_SIZE + compressed.len()) as u64;
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+
+    /// Writes the remaining blocks and the end frame and returns the underlying writer.
+    pub(crate) fn finish(self) -> Result<W, Error> {
+        Ok(self.finish_with_blocks()?.0)
+    }
+
+    /// Like [`finish`](Self::finish), but also returns the size of the payload
+    /// and the positions of the blocks.
+    pub(crate) fn finish_with_blocks(mut self) -> Result<(W, u64, Vec<BlockPosition>), Error> {
+        self.write_blocks()?;
+        write_frame(&mut self.writer, self.blocks.len() as u32, 0, 0)?;
+        self.position += FRAME_SIZE as u64;
+        Ok((self.writer, self.position, self.blocks))
+    }
+}
+
+impl<W> Write for BlockEncoder<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let capacity = self.block_size * self.threads;
+        let written = buf.len().min(capacity - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..written]);
+        if self.buffer.len() == capacity {
+            self.write_blocks()?;
+        }
+        Ok(written)
+    }
+
+    /// Flushes the blocks written so far; the buffered input stays buffered
+    /// until a block is complete.
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads the blocks written by [`BlockEncoder`], decompressing several blocks
+/// at a time on threads of their own.
+pub(crate) struct BlockDecoder<R>
+where
+    R: Read,
+{
+    reader: R,
+    header: StreamHeader,
+    threads: usize,
+    buffer: Vec<u8>,
+    read_position: usize,
+    next_index: u32,
+    finished: bool,
+}
+
+impl<R> BlockDecoder<R>
+where
+    R: Read,
+{
+    /// Creates a decoder for the blocks following `header`, using `threads`
+    /// threads (the available parallelism if 0).
+    pub(crate) fn new(reader: R, header: StreamHeader, threads: usize) -> Self {
+        Self {
+            reader,
+            header,
+            threads: thread_count(threads),
+            buffer: Vec::new(),
+            read_position: 0,
+            next_index: 0,
+            finished: false,
+        }
+    }
+
+    /// Returns a mutable reference to the underlying reader, positioned after
+    /// the end frame once all blocks have been read.
+    pub(crate) fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Reads and decompresses the next blocks, one per thread.
+    fn fill_buffer(&mut self) -> Result<(), Error> {
+        let mut blocks = Vec::with_capacity(self.threads);
+        while blocks.len() < self.threads {
+            match read_block(&mut self.reader, self.next_index)? {
+                Some(block) => {
+                    blocks.push(block);
+                    self.next_index += 1;
+                }
+                None => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+
+        let first_index = self.next_index as usize - blocks.len();
+        let header = &self.header;
+        let decompressed =
+            run_parallel(&blocks, |(_, compressed)| decode_block(compressed, header));
+        self.buffer.clear();
+        self.read_position = 0;
+        for (i, ((size, _), data)) in blocks.iter().zip(decompressed).enumerate() {
+            let data = data?;
+            if data.len() != *size {
+                return Err(Error::CorruptCodeStream(format!(
+                    "block {} decompressed to {} bytes instead of {}",
+                    first_index + i,
+                    data.len(),
+                    size
+                )));
+            }
+            self.buffer.extend_from_slice(&data);
+        }
+        Ok(())
+    }
+}
+
+/// Reads the frame and data of the block with the given index, or `None` if
+/// it is the end frame. Returns the uncompressed size and the compressed data.
+pub(crate) fn read_block(
+    reader: &mut impl Read,
+    expected_index: u32,
+) -> Result<Option<(usize, Vec<u8>)>, Error> {
+    let mut frame = [0u8; FRAME_SIZE];
+    reader.read_exact(&mut frame).map_err(end_of_input)?;
+    let index = u32::from_le_bytes(frame[..4].try_into().unwrap());
+    let size = u32::from_le_bytes(frame[4..8].try_into().unwrap()) as usize;
+    let compressed_size = u32::from_le_bytes(frame[8..].try_into().unwrap()) as u64;
+    if index != expected_index {
+        return Err(Error::CorruptCodeStream(format!(
+            "expected block {}, found block {}",
+            expected_index, index
+        )));
+    }
+    if size == 0 && compressed_size == 0 {
+        return Ok(None);
+    }
+    if size > *PARALLEL_BLOCK_SIZE_RANGE.end() {
+        return Err(Error::CorruptCodeStream(format!(
+            "block {} is too large ({} bytes)",
+            index, size
+        )));
+    }
+
+    let mut compressed = Vec::new();
+    reader.take(compressed_size).read_to_end(&mut compressed)?;
+    if compressed.len() as u64 != compressed_size {
+        return Err(end_of_input(io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(Some((size, compressed)))
+}
+
+/// Reports a stream that ends inside a block as corrupt.
+fn end_of_input(e: io::Error) -> Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        Error::CorruptCodeStream("stream ended before the last block".to_string())
+    } else {
+        Error::Io(e)
+    }
+}
+
+impl<R> Read for BlockDecoder<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.read_position == self.buffer.len() {
+            if self.finished || buf.is_empty() {
+                return Ok(0);
+            }
+            self.fill_buffer()?;
+        }
+        let read = buf.len().min(self.buffer.len() - self.read_position);
+        buf[..read].copy_from_slice(&self.buffer[self.read_position..self.read_position + read]);
+        self.read_position += read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::Codec;
+
+    fn text(size: usize) -> Vec<u8> {
+        let words = ["block", "parallel", "thread", "frame", "index", "lzw"];
+        let mut state = 7u32;
+        let mut text = Vec::with_capacity(size + 16);
+        while text.len() < size {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            text.extend_from_slice(words[(state >> 16) as usize % words.len()].as_bytes());
+            text.push(b' ');
+        }
+        text.truncate(size);
+        text
+    }
+
+    fn encode(data: &[u8], options: &CompressOptions) -> Vec<u8> {
+        let mut encoder = BlockEncoder::new(Vec::new(), options).unwrap();
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decode(data: &[u8], codec: Codec, threads: usize) -> Result<Vec<u8>, io::Error> {
+        let header = StreamHeader::new(codec);
+        let mut output = Vec::new();
+        BlockDecoder::new(data, header, threads).read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn options(threads: usize) -> CompressOptions {
+        CompressOptions {
+            parallel_block_size: Some(4096),
+            threads,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip_with_threads() {
+        // Arrange
+        let data = text(50_000);
+
+        // Act
+        let encoded = encode(&data, &options(4));
+
+        // Assert
+        assert_eq!(decode(&encoded, Codec::Lzw, 1).unwrap(), data);
+        assert_eq!(decode(&encoded, Codec::Lzw, 3).unwrap(), data);
+    }
+
+    #[test]
+    fn test_output_does_not_depend_on_threads() {
+        // Arrange
+        let data = text(30_000);
+
+        // Act
+        let single = encode(&data, &options(1));
+        let parallel = encode(&data, &options(8));
+
+        // Assert
+        assert_eq!(single, parallel);
+    }
+
+    #[test]
+    fn test_blocks_are_framed_in_order() {
+        // Arrange
+        let data = text(10_000);
+
+        // Act
+        let encoded = encode(&data, &options(2));
+
+        // Assert
+        let mut offset = 0;
+        let mut sizes = Vec::new();
+        for index in 0..3u32 {
+            let frame = &encoded[offset..offset + FRAME_SIZE];
+            assert_eq!(frame[..4], index.to_le_bytes());
+            sizes.push(u32::from_le_bytes(frame[4..8].try_into().unwrap()));
+            let compressed_size = u32::from_le_bytes(frame[8..].try_into().unwrap());
+            // Every LZW block starts with a clear code
+            assert_eq!(encoded[offset + FRAME_SIZE], 0x00);
+            offset += FRAME_SIZE + compressed_size as usize;
+        }
+        assert_eq!(sizes, [4096, 4096, 1808]);
+        let mut end_frame = [0u8; FRAME_SIZE];
+        end_frame[0] = 3;
+        assert_eq!(encoded[offset..], end_frame);
+    }
+
+    #[test]
+    fn test_empty_input_has_only_the_end_frame() {
+        // Act
+        let encoded = encode(b"", &options(2));
+
+        // Assert
+        assert_eq!(encoded, [0u8; FRAME_SIZE]);
+        assert!(decode(&encoded, Codec::Lzw, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_every_codec_can_be_split() {
+        for codec in [Codec::Lzw, Codec::Deflate, Codec::Bwt] {
+            // Arrange
+            let data = text(20_000);
+            let options = CompressOptions {
+                codec,
+                ..options(3)
+            };
+
+            // Act
+            let encoded = encode(&data, &options);
+
+            // Assert
+            assert_eq!(decode(&encoded, codec, 2).unwrap(), data, "{:?}", codec);
+        }
+    }
+
+    #[test]
+    fn test_reordered_blocks_are_rejected() {
+        // Arrange
+        let mut encoded = encode(&text(10_000), &options(2));
+        encoded[0] = 1;
+
+        // Act
+        let result = decode(&encoded, Codec::Lzw, 2);
+
+        // Assert
+        let error = Error::from(result.unwrap_err());
+        assert!(matches!(error, Error::CorruptCodeStream(_)));
+    }
+
+    #[test]
+    fn test_truncated_stream_is_rejected() {
+        // Arrange
+        let encoded = encode(&text(10_000), &options(2));
+
+        // Act
+        let result = decode(&encoded[..encoded.len() - FRAME_SIZE - 10], Codec::Lzw, 2);
+
+        // Assert
+        let error = Error::from(result.unwrap_err());
+        assert!(matches!(error, Error::CorruptCodeStream(_)));
+    }
+
+    #[test]
+    fn test_invalid_block_size_is_rejected() {
+        // Arrange
+        let options = CompressOptions {
+            parallel_block_size: Some(100),
+            ..Default::default()
+        };
+
+        // Act
+        let result = BlockEncoder::new(Vec::new(), &options);
+
+        // Assert
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+    }
+}
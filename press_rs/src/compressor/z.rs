@@ -0,0 +1,330 @@
+use std::io::{self, Read, Write};
+
+use crate::compressor::compress::{check_code_width, check_entropy};
+use crate::compressor::{BitOrder, CompressOptions, Dialect, LzwDecoder, LzwEncoder, ResetPolicy};
+use crate::Error;
+
+/// The bytes every Unix `compress` (`.Z`) file starts with.
+pub const Z_MAGIC: [u8; 2] = [0x1F, 0x9D];
+
+/// The file extension of Unix `compress` files.
+pub const Z_EXTENSION: &str = "Z";
+
+/// Flag bit of the third header byte: code 256 clears the dictionary.
+const BLOCK_MODE: u8 = 0x80;
+
+/// Bits of the third header byte holding the maximum code width.
+const CODE_WIDTH_MASK: u8 = 0x1F;
+
+/// Streaming compressor writing Unix `compress` (`.Z`) files, readable by
+/// `uncompress` and `gzip -d`.
+///
+/// The output starts with the [`Z_MAGIC`] bytes and a byte holding the block
+/// mode flag and the maximum code width. The codes follow without an
+/// end-of-information code, in groups of eight codes that are padded whenever
+/// the code width changes or the dictionary is cleared.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{decompress_z, ZCompressor};
+/// use std::io::Write;
+///
+/// let mut compressor = ZCompressor::new(Vec::new());
+/// compressor.write_all(b"hello hello hello").unwrap();
+/// let compressed = compressor.finish().unwrap();
+///
+/// assert_eq!(&compressed[..2], &[0x1F, 0x9D]);
+/// assert_eq!(decompress_z(&compressed).unwrap(), b"hello hello hello");
+/// ```
+pub struct ZCompressor<W>
+where
+    W: Write,
+{
+    encoder: LzwEncoder<W>,
+}
+
+impl<W> ZCompressor<W>
+where
+    W: Write,
+{
+    /// Creates a compressor with the defaults of `compress(1)`: codes of up to
+    /// 16 bits and [`ResetPolicy::OnRatioDrop`].
+    pub fn new(writer: W) -> Self {
+        let options = CompressOptions {
+            max_code_width: 16,
+            reset_policy: ResetPolicy::OnRatioDrop,
+            ..Default::default()
+        };
+        Self::with_options(writer, &options).expect("the default options are valid")
+    }
+
+    /// Writes the header to `writer` and returns a compressor using `options`
+    /// (like `compress -b`).
+    ///
+    /// Fails with [`Error::InvalidOptions`] unless the options use
+    /// [`BitOrder::LsbFirst`] without early change or a Huffman stage, as the
+    /// format requires.
+    pub fn with_options(mut writer: W, options: &CompressOptions) -> Result<Self, Error> {
+        // Validate the options before anything is written
+        check_code_width(options.max_code_width)?;
+        if options.bit_order != BitOrder::LsbFirst || options.early_change {
+            return Err(Error::InvalidOptions(
+                ".Z files use LSB-first codes without early change".to_string(),
+            ));
+        }
+        check_entropy(options, Dialect::UNIX)?;
+        writer.write_all(&Z_MAGIC)?;
+        writer.write_all(&[BLOCK_MODE | options.max_code_width])?;
+        Ok(Self {
+            encoder: LzwEncoder::with_dialect(writer, options, Dialect::UNIX)?,
+        })
+    }
+
+    /// Writes the remaining codes and returns the underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        Ok(self.encoder.finish()?)
+    }
+}
+
+impl<W> Write for ZCompressor<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Streaming decompressor reading Unix `compress` (`.Z`) files.
+///
+/// Files written without block mode by very old versions of `compress` are
+/// supported as well. The format has no checksum, so damaged data is only
+/// detected if it contains an invalid code.
+pub struct ZDecompressor<R>
+where
+    R: Read,
+{
+    decoder: LzwDecoder<R>,
+    max_code_width: u8,
+    block_mode: bool,
+}
+
+impl<R> ZDecompressor<R>
+where
+    R: Read,
+{
+    /// Reads and validates the header from `reader`.
+    ///
+    /// Fails with [`Error::NotAnArchive`] if the data does not start with
+    /// [`Z_MAGIC`] and with [`Error::CorruptCodeStream`] if the maximum code
+    /// width is out of range.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut header = Vec::with_capacity(3);
+        (&mut reader).take(3).read_to_end(&mut header)?;
+
+        if header.len() < Z_MAGIC.len() || header[..Z_MAGIC.len()] != Z_MAGIC {
+            return Err(Error::NotAnArchive);
+        }
+        let Some(&flags) = header.get(2) else {
+            return Err(Error::CorruptCodeStream(
+                "stream header is truncated".to_string(),
+            ));
+        };
+
+        let max_code_width = flags & CODE_WIDTH_MASK;
+        if check_code_width(max_code_width).is_err() {
+            return Err(Error::CorruptCodeStream(format!(
+                "invalid maximum code width {}",
+                max_code_width
+            )));
+        }
+        let block_mode = flags & BLOCK_MODE != 0;
+        let dialect = Dialect {
+            has_clear_code: block_mode,
+            ..Dialect::UNIX
+        };
+
+        Ok(Self {
+            decoder: LzwDecoder::with_dialect(reader, max_code_width, dialect)?,
+            max_code_width,
+            block_mode,
+        })
+    }
+
+    /// Returns the maximum code width stored in the header.
+    pub fn max_code_width(&self) -> u8 {
+        self.max_code_width
+    }
+
+    /// Returns `true` if the file was written in block mode, where code 256
+    /// clears the dictionary.
+    pub fn block_mode(&self) -> bool {
+        self.block_mode
+    }
+}
+
+impl<R> Read for ZDecompressor<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.decoder.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::EntropyCoding;
+
+    /// Deterministic text that fills small dictionaries several times.
+    fn vector_text() -> Vec<u8> {
+        let words = ["press", "archive", "legacy", "mainframe", "export", "codes"];
+        (0..2000)
+            .map(|i| format!("{} {} {}\n", i, words[i % 6], words[(i * 7) % 6]))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    fn compress_with(data: &[u8], max_code_width: u8, reset_policy: ResetPolicy) -> Vec<u8> {
+        let options = CompressOptions {
+            max_code_width,
+            reset_policy,
+            ..Default::default()
+        };
+        let mut compressor = ZCompressor::with_options(Vec::new(), &options).unwrap();
+        compressor.write_all(data).unwrap();
+        compressor.finish().unwrap()
+    }
+
+    fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        ZDecompressor::new(data)?.read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    mod vectors {
+        use super::*;
+
+        // The vectors decompress with `uncompress` and `gzip -d`
+        const HELLO: &[u8] = include_bytes!("../../tests/vectors/hello.Z");
+        const TEXT_B16: &[u8] = include_bytes!("../../tests/vectors/text-b16.Z");
+        const TEXT_B9: &[u8] = include_bytes!("../../tests/vectors/text-b9.Z");
+        const TEXT_B10_WHEN_FULL: &[u8] = include_bytes!("../../tests/vectors/text-b10-full.Z");
+
+        const HELLO_TEXT: &[u8] = b"TOBEORNOTTOBEORTOBEORNOT\n";
+
+        #[test]
+        fn test_vectors_decompress() {
+            assert_eq!(decompress(HELLO).unwrap(), HELLO_TEXT);
+            assert_eq!(decompress(TEXT_B16).unwrap(), vector_text());
+            assert_eq!(decompress(TEXT_B9).unwrap(), vector_text());
+            assert_eq!(decompress(TEXT_B10_WHEN_FULL).unwrap(), vector_text());
+        }
+
+        #[test]
+        fn test_compressor_reproduces_vectors() {
+            // Arrange
+            let text = vector_text();
+
+            // Act
+            let mut hello = ZCompressor::new(Vec::new());
+            hello.write_all(HELLO_TEXT).unwrap();
+
+            // Assert
+            assert_eq!(hello.finish().unwrap(), HELLO);
+            assert_eq!(compress_with(&text, 16, ResetPolicy::OnRatioDrop), TEXT_B16);
+            assert_eq!(compress_with(&text, 9, ResetPolicy::OnRatioDrop), TEXT_B9);
+            assert_eq!(
+                compress_with(&text, 10, ResetPolicy::WhenFull),
+                TEXT_B10_WHEN_FULL
+            );
+        }
+
+        #[test]
+        fn test_without_block_mode() {
+            // Arrange
+            // Codes 'a', 'b' and 256 ("ab") at 9 bits; 256 is a word without block mode
+            let data = [0x1F, 0x9D, 0x10, 0x61, 0xC4, 0x00, 0x04];
+
+            // Act
+            let decompressor = ZDecompressor::new(&data[..]).unwrap();
+            let block_mode = decompressor.block_mode();
+
+            // Assert
+            assert!(!block_mode);
+            assert_eq!(decompress(&data).unwrap(), b"abab");
+        }
+    }
+
+    #[test]
+    fn test_round_trip_at_every_width() {
+        // Arrange
+        let mut input = vector_text();
+        input.extend((0..50_000u32).map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8));
+
+        for width in 9..=16 {
+            for policy in [ResetPolicy::WhenFull, ResetPolicy::OnRatioDrop] {
+                // Act
+                let compressed = compress_with(&input, width, policy);
+
+                // Assert
+                assert_eq!(compressed[2], 0x80 | width);
+                assert!(
+                    decompress(&compressed).unwrap() == input,
+                    "round trip failed at width {} with {:?}",
+                    width,
+                    policy
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_input_is_header_only() {
+        // Act
+        let compressed = ZCompressor::new(Vec::new()).finish().unwrap();
+
+        // Assert
+        assert_eq!(compressed, [0x1F, 0x9D, 0x90]);
+        assert_eq!(decompress(&compressed).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_tiff_options_are_rejected() {
+        // Act
+        let result = ZCompressor::with_options(Vec::new(), &CompressOptions::tiff());
+
+        // Assert
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn test_huffman_stage_is_rejected() {
+        // Arrange
+        let options = CompressOptions {
+            entropy: EntropyCoding::StaticHuffman,
+            ..Default::default()
+        };
+
+        // Act
+        let result = ZCompressor::with_options(Vec::new(), &options);
+
+        // Assert
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+    }
+
+    #[test]
+    fn test_invalid_headers_are_rejected() {
+        assert!(matches!(decompress(b"PRES"), Err(Error::NotAnArchive)));
+        assert!(matches!(decompress(&[0x1F]), Err(Error::NotAnArchive)));
+        for data in [&[0x1F, 0x9D][..], &[0x1F, 0x9D, 0x88], &[0x1F, 0x9D, 0x91]] {
+            assert!(matches!(decompress(data), Err(Error::CorruptCodeStream(_))));
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::checksum::Adler32;
+use crate::compressor::{DeflateDecoder, DeflateEncoder};
+use crate::Error;
+
+/// Compression method id of DEFLATE in the low bits of the first header byte.
+const METHOD_DEFLATE: u8 = 8;
+
+/// The largest window size zlib allows, as the base-2 logarithm minus 8.
+const MAX_WINDOW_INFO: u8 = 7;
+
+/// Flag bit of the second header byte: a preset dictionary id follows.
+const FLAG_DICTIONARY: u8 = 0x20;
+
+/// The header written by [`ZlibCompressor`]: DEFLATE with a 32 KiB window and
+/// the default compression level.
+const HEADER: [u8; 2] = [0x78, 0x9C];
+
+/// Streaming compressor writing zlib streams (RFC 1950), as read by zlib's
+/// `inflate` and the PNG, PDF `FlateDecode` and HTTP `deflate` decoders.
+///
+/// The output is a two-byte header, the DEFLATE compressed data (see
+/// [`DeflateEncoder`]) and the Adler-32 of the uncompressed data.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::compressor::{decompress_zlib, ZlibCompressor};
+/// use std::io::Write;
+///
+/// let mut compressor = ZlibCompressor::new(Vec::new()).unwrap();
+/// compressor.write_all(b"hello hello hello").unwrap();
+/// let compressed = compressor.finish().unwrap();
+///
+/// assert_eq!(&compressed[..2], &[0x78, 0x9C]);
+/// assert_eq!(decompress_zlib(&compressed).unwrap(), b"hello hello hello");
+/// ```
+pub struct ZlibCompressor<W>
+where
+    W: Write,
+{
+    encoder: DeflateEncoder<W>,
+    adler: Adler32,
+}
+
+impl<W> ZlibCompressor<W>
+where
+    W: Write,
+{
+    /// Writes the header to `writer` and returns a compressor for the data.
+    pub fn new(mut writer: W) -> Result<Self, Error> {
+        writer.write_all(&HEADER)?;
+        Ok(Self {
+            encoder: DeflateEncoder::new(writer),
+            adler: Adler32::new(),
+        })
+    }
+
+    /// Finishes the compressed data, writes the Adler-32 trailer and returns
+    /// the underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        let mut writer = self.encoder.finish()?;
+        writer.write_all(&self.adler.finalize().to_be_bytes())?;
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W> Write for ZlibCompressor<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.encoder.write(buf)?;
+        self.adler.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.encoder.flush()
+    }
+}
+
+/// Streaming decompressor reading zlib streams (RFC 1950).
+///
+/// The Adler-32 trailer is verified once the data has been read to the end; a
+/// mismatch is reported as an [`io::Error`] wrapping [`Error::ChecksumMismatch`].
+/// The underlying reader is left right after the trailer, so data following
+/// the stream can still be read.
+pub struct ZlibDecompressor<R>
+where
+    R: Read,
+{
+    decoder: DeflateDecoder<R>,
+    adler: Adler32,
+    verified: bool,
+}
+
+impl<R> ZlibDecompressor<R>
+where
+    R: Read,
+{
+    /// Reads and validates the header from `reader`.
+    ///
+    /// Fails with [`Error::NotAnArchive`] if the header check bits do not match,
+    /// with [`Error::UnsupportedCodec`] for compression methods other than
+    /// DEFLATE and with [`Error::CorruptCodeStream`] for streams that need a
+    /// preset dictionary.
+    pub fn new(mut reader: R) -> Result<Self, Error> {
+        let mut header = Vec::with_capacity(2);
+        (&mut reader).take(2).read_to_end(&mut header)?;
+        if header.len() < 2 || u16::from_be_bytes([header[0], header[1]]) % 31 != 0 {
+            return Err(Error::NotAnArchive);
+        }
+        let (method, window_info, flags) = (header[0] & 0x0F, header[0] >> 4, header[1]);
+        if method != METHOD_DEFLATE {
+            return Err(Error::UnsupportedCodec(method));
+        }
+        if window_info > MAX_WINDOW_INFO {
+            return Err(Error::CorruptCodeStream(format!(
+                "invalid window size of {} bits",
+                window_info + 8
+            )));
+        }
+        if flags & FLAG_DICTIONARY != 0 {
+            return Err(Error::CorruptCodeStream(
+                "streams with a preset dictionary are not supported".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            decoder: DeflateDecoder::new(reader),
+            adler: Adler32::new(),
+            verified: false,
+        })
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.decoder.into_inner()
+    }
+
+    fn verify_trailer(&mut self) -> Result<(), Error> {
+        self.verified = true;
+        let mut trailer = [0u8; 4];
+        self.decoder
+            .get_mut()
+            .read_exact(&mut trailer)
+            .map_err(|e| {
+                if e.kind() == ErrorKind::UnexpectedEof {
+                    Error::CorruptCodeStream("checksum trailer is missing".to_string())
+                } else {
+                    Error::Io(e)
+                }
+            })?;
+        if u32::from_be_bytes(trailer) != self.adler.finalize() {
+            return Err(Error::ChecksumMismatch { entry: None });
+        }
+        Ok(())
+    }
+}
+
+impl<R> Read for ZlibDecompressor<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.decoder.read(buf)?;
+        self.adler.update(&buf[..read]);
+        if read == 0 && !buf.is_empty() && !self.verified {
+            self.verify_trailer()?;
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decompress(data: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        ZlibDecompressor::new(data)?.read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut compressor = ZlibCompressor::new(Vec::new()).unwrap();
+        compressor.write_all(data).unwrap();
+        compressor.finish().unwrap()
+    }
+
+    #[test]
+    fn test_vectors_decompress() {
+        // Arrange
+        // Written by Python's `zlib.compress` at levels 6, 1 and 9
+        let text = [
+            0x78, 0x9C, 0x0B, 0xF1, 0x77, 0x72, 0xF5, 0x0F, 0xF2, 0xF3, 0x0F, 0x09, 0x01, 0x33,
+            0x42, 0x60, 0x5C, 0x2E, 0x00, 0x61, 0xE5, 0x07, 0x4E,
+        ];
+        let empty = [0x78, 0x9C, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let fast = [0x78, 0x01, 0x4B, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62];
+        let best = [0x78, 0xDA, 0x4B, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62];
+
+        // Act & Assert
+        assert_eq!(decompress(&text).unwrap(), b"TOBEORNOTTOBEORTOBEORNOT\n");
+        assert_eq!(decompress(&empty).unwrap(), b"");
+        assert_eq!(decompress(&fast).unwrap(), b"a");
+        assert_eq!(decompress(&best).unwrap(), b"a");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // Arrange
+        let text = b"zlib framed DEFLATE data, zlib framed DEFLATE data ".repeat(500);
+
+        // Act
+        let compressed = compress(&text);
+
+        // Assert
+        assert_eq!(compressed[..2], HEADER);
+        assert_eq!(decompress(&compressed).unwrap(), text);
+        let adler = u32::from_be_bytes(compressed[compressed.len() - 4..].try_into().unwrap());
+        assert_eq!(adler, crate::checksum::adler32(&text));
+    }
+
+    #[test]
+    fn test_reader_is_left_after_the_trailer() {
+        // Arrange
+        let mut data = compress(b"payload");
+        data.extend_from_slice(b"next");
+        let mut decompressor = ZlibDecompressor::new(data.as_slice()).unwrap();
+        let mut output = Vec::new();
+
+        // Act
+        decompressor.read_to_end(&mut output).unwrap();
+        let mut rest = Vec::new();
+        decompressor.into_inner().read_to_end(&mut rest).unwrap();
+
+        // Assert
+        assert_eq!(output, b"payload");
+        assert_eq!(rest, b"next");
+    }
+
+    #[test]
+    fn test_damaged_streams_are_rejected() {
+        // Arrange
+        let compressed = compress(b"some payload");
+        let mut bad_adler = compressed.clone();
+        let last = bad_adler.len() - 1;
+        bad_adler[last] ^= 0x01;
+        let missing = &compressed[..compressed.len() - 4];
+
+        // Act & Assert
+        assert!(matches!(
+            decompress(&bad_adler),
+            Err(Error::ChecksumMismatch { entry: None })
+        ));
+        assert!(matches!(
+            decompress(missing),
+            Err(Error::CorruptCodeStream(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_headers_are_rejected() {
+        // 0x789D fails the header check, 0x7918 names compression method 9
+        assert!(matches!(
+            decompress(&[0x78, 0x9D]),
+            Err(Error::NotAnArchive)
+        ));
+        assert!(matches!(decompress(&[0x78]), Err(Error::NotAnArchive)));
+        assert!(matches!(
+            decompress(&[0x79, 0x18]),
+            Err(Error::UnsupportedCodec(9))
+        ));
+        // Window of 2^16 bytes
+        assert!(matches!(
+            decompress(&[0x88, 0x98]),
+            Err(Error::CorruptCodeStream(_))
+        ));
+        // Preset dictionary
+        assert!(matches!(
+            decompress(&[0x78, 0xBB]),
+            Err(Error::CorruptCodeStream(_))
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fmt, io, path::PathBuf};
+
+/// The error type returned by the compressor and packager entry points.
+///
+/// Every function that reads untrusted data (compressed streams, packed archives)
+/// reports malformed input through this type instead of panicking.
+#[derive(Debug)]
+pub enum Error {
+    /// An underlying I/O operation failed.
+    Io(io::Error),
+
+    /// The compressed code stream is malformed (invalid code, unexpected end, etc.).
+    CorruptCodeStream(String),
+
+    /// The archive ended in the middle of an entry.
+    TruncatedArchive,
+
+    /// An archive header could not be parsed.
+    BadHeader(String),
+
+    /// A path cannot be stored in the archive or restored from it.
+    InvalidPath(PathBuf),
+
+    /// An archive entry name would be extracted outside the destination directory.
+    UnsafePath(String),
+
+    /// The data does not start with the press_rs magic bytes (or the header of
+    /// the format being read, such as `.Z`, gzip or zlib).
+    NotAnArchive,
+
+    /// The archive was written with a container format version this crate cannot read.
+    UnsupportedVersion(u8),
+
+    /// The archive payload uses a codec this crate does not know.
+    UnsupportedCodec(u8),
+
+    /// Options passed to the compressor or packager are out of range.
+    InvalidOptions(String),
+
+    /// Data does not match its stored checksum. `entry` names the damaged archive
+    /// entry, or is `None` if the checksum of the whole decompressed stream failed.
+    ChecksumMismatch { entry: Option<String> },
+
+    /// The archive has no entry with the given name.
+    EntryNotFound(String),
+
+    /// The archive was written without the trailing index needed for random
+    /// access.
+    MissingIndex,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::CorruptCodeStream(msg) => write!(f, "corrupt code stream: {}", msg),
+            Error::TruncatedArchive => write!(f, "archive is truncated"),
+            Error::BadHeader(msg) => write!(f, "bad archive header: {}", msg),
+            Error::InvalidPath(path) => write!(f, "invalid path: {}", path.display()),
+            Error::UnsafePath(name) => write!(f, "unsafe entry path: {}", name),
+            Error::NotAnArchive => write!(f, "not a press_rs archive"),
+            Error::UnsupportedVersion(version) => {
+                write!(f, "unsupported archive format version {}", version)
+            }
+            Error::UnsupportedCodec(id) => write!(f, "unsupported codec id {}", id),
+            Error::InvalidOptions(msg) => write!(f, "invalid options: {}", msg),
+            Error::ChecksumMismatch { entry: Some(name) } => {
+                write!(f, "checksum mismatch in entry '{}'", name)
+            }
+            Error::ChecksumMismatch { entry: None } => {
+                write!(f, "checksum mismatch in decompressed data")
+            }
+            Error::EntryNotFound(name) => write!(f, "entry '{}' not found in archive", name),
+            Error::MissingIndex => write!(f, "archive has no index"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    /// Unwraps an [`Error`] that was smuggled through an [`io::Error`]
+    /// (e.g. by a `Read`/`Write` adapter), otherwise wraps the I/O error.
+    fn from(e: io::Error) -> Self {
+        if e.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = e.into_inner().expect("inner error was checked above");
+            return *inner
+                .downcast::<Error>()
+                .expect("inner type was checked above");
+        }
+        Error::Io(e)
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_round_trip_preserves_variant() {
+        // Arrange
+        let original = Error::BadHeader("unknown entry type".to_string());
+
+        // Act
+        let io_error: io::Error = original.into();
+        let restored: Error = io_error.into();
+
+        // Assert
+        assert!(matches!(restored, Error::BadHeader(msg) if msg == "unknown entry type"));
+    }
+
+    #[test]
+    fn test_plain_io_error_is_wrapped() {
+        // Arrange
+        let io_error = io::Error::new(io::ErrorKind::NotFound, "missing");
+
+        // Act
+        let error: Error = io_error.into();
+
+        // Assert
+        assert!(matches!(error, Error::Io(e) if e.kind() == io::ErrorKind::NotFound));
+    }
+}
@@ -1,6 +1,10 @@
+pub mod checksum;
 pub mod compressor;
+mod error;
 pub mod packager;
 
+pub use error::Error;
+
 #[cfg(test)]
 mod tests {
     use crate::compressor::{compress_raw, decompress_raw};
@@ -14,8 +18,8 @@ mod tests {
             let text = "hello world ".repeat(100);
             let input = text.as_bytes();
             // Act
-            let compressed = compress_raw(input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
             let ratio = input.len() as f64 / compressed.len() as f64;
 
             // Assert
@@ -32,8 +36,8 @@ mod tests {
             }
 
             // Act
-            let compressed = compress_raw(&input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(&input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
             let ratio = input.len() as f64 / compressed.len() as f64;
 
             // Assert
@@ -52,8 +56,8 @@ mod tests {
             let input = json.as_bytes();
 
             // Act
-            let compressed = compress_raw(input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
             let ratio = input.len() as f64 / compressed.len() as f64;
 
             // Assert
@@ -73,8 +77,8 @@ mod tests {
             let input: Vec<u8> = (0..10000).map(|_| rng.random()).collect();
 
             // Act
-            let compressed = compress_raw(&input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(&input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
             let ratio = input.len() as f64 / compressed.len() as f64;
 
             // Assert
@@ -94,8 +98,8 @@ mod tests {
             let data: Vec<u8> = (0..5000).map(|_| rng.random()).collect();
 
             // Act
-            let compressed = compress_raw(&data);
-            let compressed_again = compress_raw(&compressed);
+            let compressed = compress_raw(&data).unwrap();
+            let compressed_again = compress_raw(&compressed).unwrap();
             let ratio = compressed.len() as f64 / compressed_again.len() as f64;
 
             // Assert
@@ -106,14 +110,44 @@ mod tests {
             );
         }
 
+        #[test]
+        fn test_wider_codes_compress_large_text_better() {
+            use crate::compressor::{compress_raw_with_options, CompressOptions};
+            use rand::Rng;
+            // Arrange
+            let mut rng = rand::rng();
+            let words = ["lorem", "ipsum", "dolor", "sit", "amet", "press", "archive"];
+            let text: String = (0..100_000)
+                .map(|_| words[rng.random_range(0..words.len())])
+                .collect::<Vec<_>>()
+                .join(" ");
+            let wide = CompressOptions {
+                max_code_width: 16,
+                ..Default::default()
+            };
+
+            // Act
+            let narrow_size = compress_raw(text.as_bytes()).unwrap().len();
+            let compressed = compress_raw_with_options(text.as_bytes(), &wide).unwrap();
+
+            // Assert
+            assert!(
+                compressed.len() < narrow_size,
+                "16-bit codes should beat 12-bit codes: {} vs {}",
+                compressed.len(),
+                narrow_size
+            );
+            assert_eq!(decompress_raw(&compressed).unwrap(), text.as_bytes());
+        }
+
         #[test]
         fn test_compression_ratio_zeroes() {
             // Arrange
             let input = vec![0u8; 10000];
 
             // Act
-            let compressed = compress_raw(&input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(&input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
             let ratio = input.len() as f64 / compressed.len() as f64;
 
             // Assert
@@ -145,8 +179,8 @@ mod tests {
             }
 
             // Act
-            let compressed = compress_raw(&input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(&input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
             let ratio = input.len() as f64 / compressed.len() as f64;
 
             // Assert
@@ -164,8 +198,8 @@ mod tests {
             let input = b"";
 
             // Act
-            let compressed = compress_raw(input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
 
             // Assert
             assert_eq!(input, &decompressed[..]);
@@ -178,22 +212,179 @@ mod tests {
                 let input = vec![0xAB; size];
 
                 // Act
-                let compressed = compress_raw(&input);
-                let decompressed = decompress_raw(&compressed);
+                let compressed = compress_raw(&input).unwrap();
+                let decompressed = decompress_raw(&compressed).unwrap();
 
                 // Assert
                 assert_eq!(input, decompressed, "Roundtrip failed for size {}", size);
             }
         }
 
+        #[test]
+        fn test_decompress_garbage_returns_error() {
+            // Arrange
+            let garbage = [0xFFu8; 64];
+
+            // Act
+            let result = decompress_raw(&garbage);
+
+            // Assert
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_decompress_bare_stream_is_not_an_archive() {
+            // Arrange
+            let bare = crate::compressor::compress_bare(b"no container header");
+
+            // Act
+            let result = decompress_raw(&bare);
+
+            // Assert
+            assert!(matches!(result, Err(crate::Error::NotAnArchive)));
+            assert_eq!(
+                crate::compressor::decompress_bare(&bare).unwrap(),
+                b"no container header"
+            );
+        }
+
+        #[test]
+        fn test_decompress_missing_path_returns_io_error() {
+            // Arrange
+            let dir = tempfile::tempdir().expect("Failed to create temp dir");
+
+            // Act
+            let result = crate::compressor::decompress_from_path_to_path(
+                dir.path().join("missing.pressrs"),
+                dir.path().join("out"),
+            );
+
+            // Assert
+            assert!(matches!(result, Err(crate::Error::Io(_))));
+        }
+
+        #[test]
+        fn test_list_from_path_lists_without_unpacking() {
+            // Arrange
+            let dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let source = dir.path().join("source");
+            std::fs::create_dir_all(source.join("nested")).unwrap();
+            std::fs::write(source.join("nested/file.txt"), "listed content").unwrap();
+            let archive = crate::compressor::compress_from_path(&source).unwrap();
+            let archive_path = dir.path().join("listed.pressrs");
+            std::fs::write(&archive_path, archive).unwrap();
+
+            // Act
+            let entries = crate::compressor::list_from_path(&archive_path).unwrap();
+
+            // Assert
+            let file = entries
+                .iter()
+                .find(|e| e.name.ends_with("nested/file.txt"))
+                .expect("file entry should be listed");
+            assert_eq!(file.entry_type, crate::packager::EntryType::File);
+            assert_eq!(file.size, 14);
+            assert!(entries
+                .iter()
+                .any(|e| e.entry_type == crate::packager::EntryType::Directory));
+            assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+        }
+
+        #[test]
+        fn test_decompress_with_filter_restores_single_file() {
+            // Arrange
+            let dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let source = dir.path().join("backup");
+            std::fs::create_dir_all(source.join("etc")).unwrap();
+            std::fs::write(source.join("etc/app.toml"), "port = 80").unwrap();
+            std::fs::write(source.join("large.bin"), vec![7u8; 64 * 1024]).unwrap();
+            let archive = crate::compressor::compress_from_path(&source).unwrap();
+            let archive_path = dir.path().join("backup.pressrs");
+            std::fs::write(&archive_path, archive).unwrap();
+            let options = crate::packager::UnpackOptions {
+                filter: crate::packager::PathFilter::new().include("etc/app.toml"),
+                ..Default::default()
+            };
+
+            // Act
+            crate::compressor::decompress_from_path_to_path_with_options(
+                &archive_path,
+                dir.path().join("out"),
+                &options,
+            )
+            .unwrap();
+
+            // Assert
+            let out = dir.path().join("out");
+            assert_eq!(
+                std::fs::read_to_string(out.join("etc/app.toml")).unwrap(),
+                "port = 80"
+            );
+            assert!(!out.join("large.bin").exists());
+        }
+
+        #[test]
+        fn test_compress_with_pack_options_honours_gitignore() {
+            // Arrange
+            let dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let source = dir.path().join("project");
+            std::fs::create_dir_all(source.join("node_modules/lib")).unwrap();
+            std::fs::write(source.join("node_modules/lib/index.js"), "// dep").unwrap();
+            std::fs::write(source.join("main.js"), "// app").unwrap();
+            std::fs::write(source.join(".gitignore"), "node_modules/\n").unwrap();
+            let pack_options = crate::packager::PackOptions {
+                ignore_files: true,
+                ..Default::default()
+            };
+
+            // Act
+            let archive = crate::compressor::compress_from_path_with_pack_options(
+                &source,
+                &crate::compressor::CompressOptions::default(),
+                &pack_options,
+            )
+            .unwrap();
+
+            // Assert
+            let packed = decompress_raw(&archive).unwrap();
+            let entries = crate::packager::list(packed.as_slice()).unwrap();
+            assert!(entries.iter().any(|e| e.name == "main.js"));
+            assert!(entries.iter().all(|e| !e.name.starts_with("node_modules")));
+        }
+
+        #[test]
+        fn test_damaged_archive_fails_checksum_on_unpack() {
+            // Arrange
+            let dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let source = dir.path().join("source.txt");
+            std::fs::write(&source, "checksummed content").unwrap();
+            let mut archive = crate::compressor::compress_from_path(&source).unwrap();
+            let last = archive.len() - 1;
+            archive[last] ^= 0xFF;
+            let archive_path = dir.path().join("damaged.pressrs");
+            std::fs::write(&archive_path, archive).unwrap();
+
+            // Act
+            let result = crate::compressor::decompress_from_path_to_path(
+                &archive_path,
+                dir.path().join("out"),
+            );
+
+            // Assert
+            assert!(matches!(
+                result,
+                Err(crate::Error::ChecksumMismatch { entry: None })
+            ));
+        }
+
         #[test]
         fn test_all_byte_values() {
             // Arrange
             let input: Vec<u8> = (0..=255).cycle().take(1000).collect();
 
             // Act
-            let compressed = compress_raw(&input);
-            let decompressed = decompress_raw(&compressed);
+            let compressed = compress_raw(&input).unwrap();
+            let decompressed = decompress_raw(&compressed).unwrap();
 
             // Assert
             assert_eq!(input, decompressed);
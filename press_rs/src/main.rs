@@ -1,6 +1,9 @@
 mod utils;
 
-use press_rs::compressor::{compress_from_path, decompress_from_path_to_path, EXTENSION};
+use press_rs::compressor::{
+    compress_from_path, decompress_from_path_to_path, list_from_path, EXTENSION,
+};
+use press_rs::packager::entries_to_json;
 use std::{
     io::{self, Write},
     path::Path,
@@ -8,11 +11,23 @@ use std::{
 use utils::{get_file_or_folder_size, print_with_size_formats};
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_command(&args);
+    }
+
     loop {
         println!("\n--- PressRS Menu ---");
-        match prompt("1. Compress\n2. Decompress\n(q to quit)\n>> ").as_str() {
+        match prompt("1. Compress\n2. Decompress\n3. List\n(q to quit)\n>> ").as_str() {
             "1" => run_compress(),
             "2" => run_decompress(),
+            "3" => {
+                let input = prompt("Path to list: ");
+                let json = prompt("JSON output? (y/N): ").eq_ignore_ascii_case("y");
+                if let Err(e) = run_list(Path::new(&input), json) {
+                    println!("{}", e);
+                }
+            }
             "q" | "exit" => break,
             _ => println!("Invalid option"),
         }
@@ -58,8 +73,55 @@ fn run_decompress() {
     std::fs::create_dir_all(output_dir).ok();
 
     println!("Decompressing...");
-    decompress_from_path_to_path(path, output_dir);
-    println!("Done. Output in: {}", output_dir);
+    match decompress_from_path_to_path(path, output_dir) {
+        Ok(()) => println!("Done. Output in: {}", output_dir),
+        Err(e) => println!("Decompression failed: {}", e),
+    }
+}
+
+/// Runs a command given on the command line instead of the interactive menu.
+///
+/// Usage: `press_rs list [--json] <archive>`
+fn run_command(args: &[String]) {
+    match args {
+        [command, rest @ ..] if command == "list" => {
+            let json = rest.iter().any(|arg| arg == "--json");
+            let paths: Vec<&String> = rest.iter().filter(|arg| *arg != "--json").collect();
+            match paths.as_slice() {
+                [path] => {
+                    if let Err(e) = run_list(Path::new(path), json) {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+                _ => {
+                    eprintln!("Usage: press_rs list [--json] <archive>");
+                    std::process::exit(2);
+                }
+            }
+        }
+        _ => {
+            eprintln!("Unknown command. Usage: press_rs list [--json] <archive>");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Prints the entries of an archive, returning the message to report on failure.
+fn run_list(path: &Path, json: bool) -> Result<(), String> {
+    if !path.exists() {
+        return Err("Error: Path does not exist.".to_string());
+    }
+
+    let entries = list_from_path(path).map_err(|e| format!("Listing failed: {}", e))?;
+    if json {
+        println!("{}", entries_to_json(&entries));
+    } else {
+        for entry in entries {
+            println!("{}", entry);
+        }
+    }
+    Ok(())
 }
 
 fn prompt(msg: &str) -> String {
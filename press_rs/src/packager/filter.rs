@@ -0,0 +1,415 @@
+/// Selects archive entries by name, using lists of paths and glob patterns to
+/// include and to exclude.
+///
+/// Used by [`UnpackOptions::filter`](crate::packager::UnpackOptions::filter) to
+/// choose the entries to extract and by
+/// [`PackOptions::filter`](crate::packager::PackOptions::filter) to choose the
+/// files to pack, with names relative to the packed directory.
+///
+/// An entry is selected if it matches at least one include pattern (or no
+/// include pattern was given) and no exclude pattern. A pattern matches an
+/// entry if it matches its name or the name of one of its parent directories,
+/// so a directory pattern selects everything below it. Patterns may use:
+///
+/// - `*` for any run of characters within one path component,
+/// - `?` for any single character,
+/// - `[abc]`, `[a-z]` and `[!abc]` for character classes,
+/// - `**` as a whole component for any number of directories,
+/// - `\` to match the next character literally.
+///
+/// A pattern without wildcards is an exact path or directory prefix. Leading
+/// `./` and `/` and trailing `/` are ignored, both in patterns and entry names.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::packager::PathFilter;
+///
+/// let filter = PathFilter::new()
+///     .include("etc/app.toml")
+///     .include("src/**/*.rs")
+///     .exclude("src/generated");
+///
+/// assert!(filter.is_match("etc/app.toml"));
+/// assert!(filter.is_match("src/main.rs"));
+/// assert!(filter.is_match("src/net/tcp.rs"));
+/// assert!(!filter.is_match("src/generated/schema.rs"));
+/// assert!(!filter.is_match("README.md"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// Creates a filter selecting every entry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pattern selecting entries.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(Pattern::new(pattern));
+        self
+    }
+
+    /// Adds a pattern of entries to leave out, even if they match an include pattern.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(Pattern::new(pattern));
+        self
+    }
+
+    /// Returns `true` if the filter has no patterns and selects every entry.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Returns `true` if the entry with the given name is selected.
+    pub fn is_match(&self, name: &str) -> bool {
+        self.is_included(name) && !self.is_excluded(name)
+    }
+
+    /// Returns `true` if there are no include patterns or one of them matches.
+    pub(crate) fn is_included(&self, name: &str) -> bool {
+        let components = components(name);
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches_path(&components))
+    }
+
+    /// Returns `true` if one of the exclude patterns matches.
+    pub(crate) fn is_excluded(&self, name: &str) -> bool {
+        let components = components(name);
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches_path(&components))
+    }
+}
+
+/// A glob pattern compiled into path components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern {
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// `**`: any number of components, including none.
+    AnyDepth,
+    /// A single component, matched token by token.
+    Component(Vec<Token>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyRun,
+    /// `[...]`: inclusive character ranges, negated with `[!...]` or `[^...]`.
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Pattern {
+    pub(crate) fn new(pattern: &str) -> Self {
+        let segments = components(pattern)
+            .into_iter()
+            .map(|component| match component {
+                "**" => Segment::AnyDepth,
+                component => Segment::Component(parse_component(component)),
+            })
+            .collect();
+        Pattern { segments }
+    }
+
+    /// Returns `true` if the pattern matches the whole path.
+    pub(crate) fn matches(&self, path: &[&str]) -> bool {
+        match_segments(&self.segments, path, false)
+    }
+
+    /// Returns `true` if the pattern matches the path or one of its parent directories.
+    pub(crate) fn matches_path(&self, path: &[&str]) -> bool {
+        match_segments(&self.segments, path, true)
+    }
+}
+
+/// Splits a name into its path components, dropping empty and `.` components.
+pub(crate) fn components(name: &str) -> Vec<&str> {
+    name.split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect()
+}
+
+fn parse_component(component: &str) -> Vec<Token> {
+    let chars: Vec<char> = component.chars().collect();
+    let mut tokens = Vec::with_capacity(chars.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' => {
+                // Consecutive stars within a component are a single run
+                if tokens.last() != Some(&Token::AnyRun) {
+                    tokens.push(Token::AnyRun);
+                }
+            }
+            '?' => tokens.push(Token::AnyChar),
+            '\\' if i + 1 < chars.len() => {
+                i += 1;
+                tokens.push(Token::Literal(chars[i]));
+            }
+            '[' => match parse_class(&chars[i + 1..]) {
+                Some((token, consumed)) => {
+                    tokens.push(token);
+                    i += consumed;
+                }
+                // An unclosed bracket is taken literally
+                None => tokens.push(Token::Literal('[')),
+            },
+            c => tokens.push(Token::Literal(c)),
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// Parses the inside of a character class, returning the class and the number
+/// of characters consumed including the closing bracket.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    let mut i = usize::from(negated);
+    let mut ranges = Vec::new();
+
+    // A `]` right after the opening bracket is part of the class
+    while i < chars.len() && (chars[i] != ']' || ranges.is_empty()) {
+        let start = chars[i];
+        if i + 2 < chars.len() && chars[i + 1] == '-' && chars[i + 2] != ']' {
+            ranges.push((start, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((start, start));
+            i += 1;
+        }
+    }
+
+    if i >= chars.len() {
+        return None;
+    }
+    Some((Token::Class { negated, ranges }, i + 1))
+}
+
+/// Matches path components against the segments, like [`match_tokens`] does
+/// characters against tokens, with `**` in the role of `*`. With `prefix`, the
+/// pattern may end before the path does.
+fn match_segments(segments: &[Segment], path: &[&str], prefix: bool) -> bool {
+    let (mut s, mut p) = (0, 0);
+    // The segment after the last `**` and the first component it has not consumed
+    let mut any_depth: Option<(usize, usize)> = None;
+
+    while p < path.len() {
+        match segments.get(s) {
+            None if prefix => return true,
+            Some(Segment::AnyDepth) => {
+                any_depth = Some((s + 1, p));
+                s += 1;
+            }
+            Some(Segment::Component(tokens)) if match_component(tokens, path[p]) => {
+                s += 1;
+                p += 1;
+            }
+            // Let the last `**` consume one more component and retry from there
+            _ => match any_depth {
+                Some((after, consumed)) => {
+                    any_depth = Some((after, consumed + 1));
+                    s = after;
+                    p = consumed + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    segments[s..]
+        .iter()
+        .all(|segment| *segment == Segment::AnyDepth)
+}
+
+fn match_component(tokens: &[Token], component: &str) -> bool {
+    let chars: Vec<char> = component.chars().collect();
+    match_tokens(tokens, &chars)
+}
+
+/// Matches characters against the tokens in O(tokens × chars) time.
+///
+/// Only the last `*` is backtracked to: whatever an earlier `*` could consume
+/// instead, the last one can consume as well.
+fn match_tokens(tokens: &[Token], chars: &[char]) -> bool {
+    let (mut t, mut c) = (0, 0);
+    // The token after the last `*` and the first character it has not consumed
+    let mut any_run: Option<(usize, usize)> = None;
+
+    while c < chars.len() {
+        match tokens.get(t) {
+            Some(Token::AnyRun) => {
+                any_run = Some((t + 1, c));
+                t += 1;
+            }
+            Some(token) if match_char(token, chars[c]) => {
+                t += 1;
+                c += 1;
+            }
+            _ => match any_run {
+                Some((after, consumed)) => {
+                    any_run = Some((after, consumed + 1));
+                    t = after;
+                    c = consumed + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..].iter().all(|token| *token == Token::AnyRun)
+}
+
+fn match_char(token: &Token, c: char) -> bool {
+    match token {
+        Token::Literal(literal) => *literal == c,
+        Token::AnyChar => true,
+        Token::AnyRun => unreachable!("runs are handled by match_tokens"),
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|&(start, end)| start <= c && c <= end) != *negated
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod pattern_tests {
+        use super::*;
+        use std::time::{Duration, Instant};
+
+        fn matches(pattern: &str, name: &str) -> bool {
+            Pattern::new(pattern).matches(&components(name))
+        }
+
+        #[test]
+        fn test_literal_pattern_matches_exact_path_only() {
+            assert!(matches("etc/app.toml", "etc/app.toml"));
+            assert!(matches("./etc/app.toml", "etc/app.toml/"));
+            assert!(!matches("etc/app.toml", "etc/app.tom"));
+            assert!(!matches("etc/app.toml", "etc"));
+        }
+
+        #[test]
+        fn test_star_stays_within_one_component() {
+            assert!(matches("src/*.rs", "src/main.rs"));
+            assert!(matches("src/*", "src/.hidden"));
+            assert!(!matches("src/*.rs", "src/net/tcp.rs"));
+            assert!(!matches("*.rs", "src/main.rs"));
+        }
+
+        #[test]
+        fn test_double_star_matches_any_number_of_directories() {
+            assert!(matches("src/**/*.rs", "src/main.rs"));
+            assert!(matches("src/**/*.rs", "src/a/b/c/lib.rs"));
+            assert!(matches("**/Cargo.toml", "Cargo.toml"));
+            assert!(matches("**/Cargo.toml", "crates/core/Cargo.toml"));
+            assert!(!matches("src/**/*.rs", "tests/main.rs"));
+        }
+
+        #[test]
+        fn test_question_mark_and_character_classes() {
+            assert!(matches("log?.txt", "log1.txt"));
+            assert!(!matches("log?.txt", "log.txt"));
+            assert!(matches("log[0-9].txt", "log7.txt"));
+            assert!(!matches("log[!0-9].txt", "log7.txt"));
+            assert!(matches("[]a].txt", "].txt"));
+        }
+
+        #[test]
+        fn test_many_wildcards_match_long_names_quickly() {
+            // Arrange
+            let name = "a".repeat(10_000);
+            let path = format!("{}/b", vec!["a"; 1_000].join("/"));
+            let now = Instant::now();
+
+            // Act
+            let stars = matches("*a*a*a*a*a*a*a*a*a*a*a*a*b", &name);
+            let double_stars = matches("**/a/**/a/**/a/**/a/**/a/**/a/**/c", &path);
+            let found = matches("*a*a*a*a*a*a*a*a*a*a*a*a*", &name);
+
+            // Assert
+            assert!(!stars);
+            assert!(!double_stars);
+            assert!(found);
+            assert!(now.elapsed() < Duration::from_secs(1));
+        }
+
+        #[test]
+        fn test_escapes_and_unclosed_brackets_are_literal() {
+            assert!(matches("a\\*b", "a*b"));
+            assert!(!matches("a\\*b", "axb"));
+            assert!(matches("file[1", "file[1"));
+        }
+    }
+
+    mod filter_tests {
+        use super::*;
+
+        #[test]
+        fn test_empty_filter_selects_everything() {
+            // Arrange
+            let filter = PathFilter::new();
+
+            // Act & Assert
+            assert!(filter.is_empty());
+            assert!(filter.is_match("any/path"));
+            assert!(filter.is_match(""));
+        }
+
+        #[test]
+        fn test_directory_include_selects_its_contents() {
+            // Arrange
+            let filter = PathFilter::new().include("config/");
+
+            // Act & Assert
+            assert!(filter.is_match("config"));
+            assert!(filter.is_match("config/nested/app.toml"));
+            assert!(!filter.is_match("configs/app.toml"));
+            assert!(!filter.is_match(""));
+        }
+
+        #[test]
+        fn test_exclude_wins_over_include() {
+            // Arrange
+            let filter = PathFilter::new()
+                .include("src/**/*.rs")
+                .exclude("**/*_test.rs");
+
+            // Act & Assert
+            assert!(filter.is_match("src/lib.rs"));
+            assert!(!filter.is_match("src/lib_test.rs"));
+            assert!(!filter.is_match("src/lib.c"));
+        }
+
+        #[test]
+        fn test_exclude_only_filter_keeps_everything_else() {
+            // Arrange
+            let filter = PathFilter::new().exclude("target").exclude("*.log");
+
+            // Act & Assert
+            assert!(filter.is_match("src/main.rs"));
+            assert!(!filter.is_match("target/debug/app"));
+            assert!(!filter.is_match("build.log"));
+            assert!(filter.is_match("logs/build.log"));
+        }
+    }
+}
@@ -1,37 +1,75 @@
+use crate::Error;
+
 pub const NAME_SIZE: usize = 156;
 pub const SIZE: usize = 8;
 pub const TYPEFLAG_SIZE: usize = 1;
 
 pub const ENTRY_SIZE: usize = NAME_SIZE + SIZE + TYPEFLAG_SIZE;
 
-#[derive(PartialEq, Eq, Debug)]
+/// The name stored in the header block of a [`EntryType::LongName`] record.
+pub const LONG_NAME_MARKER: &str = "././@LongLink";
+
+/// The name stored in the header block of a [`EntryType::Metadata`] record.
+pub const METADATA_MARKER: &str = "././@PaxHeader";
+
+/// The kind of an archive entry, stored in the header typeflag.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum EntryType {
     File,
     Directory,
+    /// A symbolic link; its data is the link target.
+    Symlink,
+    /// A hard link; its data is the name of an earlier entry in the archive.
+    HardLink,
+    /// Carries the full name of the following entry as its data, for names
+    /// longer than [`NAME_SIZE`] bytes (like GNU tar `L` records).
+    LongName,
+    /// Carries the [`Metadata`](crate::packager::Metadata) of the following
+    /// entry as PAX-style records (like POSIX tar `x` records).
+    Metadata,
 }
 
 impl EntryType {
-    pub fn new(byte: u8) -> EntryType {
+    pub fn new(byte: u8) -> Result<EntryType, Error> {
         match byte {
-            b'0' => EntryType::File,
-            b'1' => EntryType::Directory,
-            _ => panic!("Unknown entry type"),
+            b'0' => Ok(EntryType::File),
+            b'1' => Ok(EntryType::Directory),
+            b'2' => Ok(EntryType::Symlink),
+            b'3' => Ok(EntryType::HardLink),
+            b'L' => Ok(EntryType::LongName),
+            b'x' => Ok(EntryType::Metadata),
+            _ => Err(Error::BadHeader(format!(
+                "unknown entry type {:#04x}",
+                byte
+            ))),
         }
     }
     pub fn as_byte(&self) -> u8 {
         match *self {
             EntryType::File => b'0',
             EntryType::Directory => b'1',
+            EntryType::Symlink => b'2',
+            EntryType::HardLink => b'3',
+            EntryType::LongName => b'L',
+            EntryType::Metadata => b'x',
         }
     }
 }
 
+/// A raw archive header block: the entry name, data size and typeflag.
+#[derive(Debug, Clone)]
 pub struct Header {
     pub name: [u8; NAME_SIZE],
     pub size: [u8; SIZE],
     pub typeflag: [u8; TYPEFLAG_SIZE],
 }
 
+impl Default for Header {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Header {
     pub fn new() -> Header {
         Header {
@@ -46,7 +84,7 @@ impl Header {
         header.set_name(name);
         header.set_size(size);
         header.set_typeflag(typeflag);
-        return header;
+        header
     }
 
     pub fn get_name(&self) -> String {
@@ -55,15 +93,18 @@ impl Header {
     }
 
     pub fn get_size(&self) -> usize {
-        let bytes: [u8; 8] = self.size.try_into().expect("Slice with incorrect length");
-        u64::from_le_bytes(bytes) as usize
+        u64::from_le_bytes(self.size) as usize
     }
 
+    /// Stores `name`, truncated to [`NAME_SIZE`] bytes without splitting a UTF-8 character.
     pub fn set_name(&mut self, name: String) {
-        let bytes = name.as_bytes();
-        let len = bytes.len().min(NAME_SIZE);
+        let mut len = name.len().min(NAME_SIZE);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
 
-        self.name[..len].copy_from_slice(&bytes[..len]);
+        self.name = [0; NAME_SIZE];
+        self.name[..len].copy_from_slice(&name.as_bytes()[..len]);
     }
 
     pub fn set_size(&mut self, size: usize) {
@@ -80,7 +121,7 @@ impl Header {
         bytes[..NAME_SIZE].copy_from_slice(&self.name);
         bytes[NAME_SIZE..NAME_SIZE + SIZE].copy_from_slice(&self.size);
         bytes[NAME_SIZE + SIZE..NAME_SIZE + SIZE + TYPEFLAG_SIZE].copy_from_slice(&self.typeflag);
-        return bytes;
+        bytes
     }
 
     pub fn from_bytes(bytes: [u8; ENTRY_SIZE]) -> Header {
@@ -105,14 +146,23 @@ mod tests {
         let dir_byte = EntryType::Directory.as_byte();
 
         // Act
-        let file_type = EntryType::new(file_byte);
-        let dir_type = EntryType::new(dir_byte);
+        let file_type = EntryType::new(file_byte).unwrap();
+        let dir_type = EntryType::new(dir_byte).unwrap();
 
         // Assert
         assert_eq!(file_type, EntryType::File);
         assert_eq!(dir_type, EntryType::Directory);
     }
 
+    #[test]
+    fn test_unknown_entry_type_is_rejected() {
+        // Act
+        let result = EntryType::new(b'z');
+
+        // Assert
+        assert!(matches!(result, Err(Error::BadHeader(_))));
+    }
+
     #[test]
     fn test_header_round_trip() {
         // Arrange
@@ -149,6 +199,21 @@ mod tests {
         assert_eq!(stored_name, "a".repeat(NAME_SIZE));
     }
 
+    #[test]
+    fn test_name_truncation_keeps_utf8_intact() {
+        // Arrange
+        let mut header = Header::new();
+        // 'ж' is 2 bytes, so NAME_SIZE (even) bytes would end mid-character after "a"
+        let long_name = format!("a{}", "ж".repeat(NAME_SIZE));
+
+        // Act
+        header.set_name(long_name);
+        let stored_name = header.get_name();
+
+        // Assert
+        assert_eq!(stored_name, format!("a{}", "ж".repeat((NAME_SIZE - 1) / 2)));
+    }
+
     #[test]
     fn test_size_parsing_zero() {
         // Arrange
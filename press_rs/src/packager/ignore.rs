@@ -0,0 +1,238 @@
+use std::{fs, path::Path};
+
+use crate::packager::filter::{components, Pattern};
+use crate::Error;
+
+/// The files read from every packed directory, in order of precedence (later wins).
+pub(crate) const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The ignore rules of the directories being packed, from the packed root down
+/// to the current directory.
+///
+/// Rules follow the `.gitignore` syntax: blank lines and `#` comments are
+/// skipped, `!` re-includes, a trailing `/` matches directories only, and a
+/// pattern containing a `/` is relative to the directory of its file while one
+/// without matches at any depth below it. The last matching rule wins, and
+/// rules of deeper directories win over those of their parents.
+#[derive(Debug, Default)]
+pub(crate) struct IgnoreStack {
+    files: Vec<IgnoreFile>,
+}
+
+/// The rules found in one directory.
+#[derive(Debug)]
+struct IgnoreFile {
+    /// The number of components between the packed root and the directory.
+    depth: usize,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreStack {
+    /// Reads the ignore files of `dir`, which lies `depth` components below the packed root.
+    pub(crate) fn push(&mut self, dir: &Path, depth: usize) -> Result<(), Error> {
+        let mut rules = Vec::new();
+        for name in IGNORE_FILES {
+            let path = dir.join(name);
+            if path.is_file() {
+                rules.extend(parse_rules(&String::from_utf8_lossy(&fs::read(path)?)));
+            }
+        }
+        self.files.push(IgnoreFile { depth, rules });
+        Ok(())
+    }
+
+    /// Drops the rules of the directory pushed last.
+    pub(crate) fn pop(&mut self) {
+        self.files.pop();
+    }
+
+    /// Returns `true` if the entry at `relative` (to the packed root) is ignored.
+    ///
+    /// `.git` directories are always ignored.
+    pub(crate) fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        let components = components(relative);
+        if is_dir && components.last() == Some(&".git") {
+            return true;
+        }
+
+        let mut ignored = false;
+        for file in &self.files {
+            let Some(path) = components.get(file.depth..) else {
+                continue;
+            };
+            for rule in &file.rules {
+                if (is_dir || !rule.dir_only) && rule.pattern.matches(path) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+fn parse_rules(text: &str) -> Vec<Rule> {
+    text.lines().filter_map(parse_rule).collect()
+}
+
+fn parse_rule(line: &str) -> Option<Rule> {
+    let line = trim_trailing_spaces(line.strip_suffix('\r').unwrap_or(line));
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negated, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    // `\#` and `\!` start patterns with a literal `#` or `!`
+    let line = match line.strip_prefix('\\') {
+        Some(rest) if rest.starts_with(['#', '!']) => rest,
+        _ => line,
+    };
+    let (dir_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+    if line.is_empty() {
+        return None;
+    }
+
+    // A pattern with a slash is anchored to its directory, others match at any depth
+    let pattern = if line.contains('/') {
+        Pattern::new(line)
+    } else {
+        Pattern::new(&format!("**/{}", line))
+    };
+    Some(Rule {
+        pattern,
+        negated,
+        dir_only,
+    })
+}
+
+/// Removes trailing spaces, except one escaped with a backslash.
+fn trim_trailing_spaces(line: &str) -> &str {
+    let trimmed = line.trim_end_matches(' ');
+    if trimmed.ends_with('\\') && trimmed.len() < line.len() {
+        &line[..trimmed.len() + 1]
+    } else {
+        trimmed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn stack(rules: &str) -> IgnoreStack {
+        IgnoreStack {
+            files: vec![IgnoreFile {
+                depth: 0,
+                rules: parse_rules(rules),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines_are_skipped() {
+        // Arrange
+        let rules = parse_rules("# build output\n\n   \ntarget\n\\#notes\n");
+
+        // Assert
+        assert_eq!(rules.len(), 2);
+    }
+
+    #[test]
+    fn test_unanchored_pattern_matches_at_any_depth() {
+        // Arrange
+        let ignore = stack("*.log\nnode_modules/\n");
+
+        // Act & Assert
+        assert!(ignore.is_ignored("build.log", false));
+        assert!(ignore.is_ignored("logs/deep/build.log", false));
+        assert!(ignore.is_ignored("web/node_modules", true));
+        assert!(!ignore.is_ignored("web/node_modules", false));
+        assert!(!ignore.is_ignored("src/main.rs", false));
+    }
+
+    #[test]
+    fn test_pattern_with_slash_is_anchored() {
+        // Arrange
+        let ignore = stack("/target\ndocs/*.html\n");
+
+        // Act & Assert
+        assert!(ignore.is_ignored("target", true));
+        assert!(!ignore.is_ignored("crates/core/target", true));
+        assert!(ignore.is_ignored("docs/index.html", false));
+        assert!(!ignore.is_ignored("site/docs/index.html", false));
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        // Arrange
+        let ignore = stack("*.txt\n!keep.txt\n");
+
+        // Act & Assert
+        assert!(ignore.is_ignored("notes.txt", false));
+        assert!(!ignore.is_ignored("keep.txt", false));
+    }
+
+    #[test]
+    fn test_nested_rules_are_relative_to_their_directory() {
+        // Arrange
+        let mut ignore = stack("*.tmp\n");
+        ignore.files.push(IgnoreFile {
+            depth: 1,
+            rules: parse_rules("/generated\n!important.tmp\n"),
+        });
+
+        // Act & Assert
+        assert!(ignore.is_ignored("web/generated", true));
+        assert!(!ignore.is_ignored("generated", true));
+        assert!(ignore.is_ignored("web/cache.tmp", false));
+        assert!(!ignore.is_ignored("web/important.tmp", false));
+    }
+
+    #[test]
+    fn test_hostile_rule_does_not_stall_matching() {
+        // Arrange
+        let ignore = stack("*a*a*a*a*a*a*a*a*a*a*a*a*b\n**/a/**/a/**/a/**/a/**/c\n");
+        let name = "a".repeat(10_000);
+        let path = format!("{}/b", vec!["a"; 1_000].join("/"));
+        let now = Instant::now();
+
+        // Act
+        let name_ignored = ignore.is_ignored(&name, false);
+        let path_ignored = ignore.is_ignored(&path, false);
+
+        // Assert
+        assert!(!name_ignored);
+        assert!(!path_ignored);
+        assert!(now.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_git_directory_is_always_ignored() {
+        // Arrange
+        let ignore = IgnoreStack::default();
+
+        // Act & Assert
+        assert!(ignore.is_ignored(".git", true));
+        assert!(ignore.is_ignored("vendor/lib/.git", true));
+        assert!(!ignore.is_ignored(".gitignore", false));
+    }
+
+    #[test]
+    fn test_escaped_trailing_space_is_kept() {
+        assert_eq!(trim_trailing_spaces("name   "), "name");
+        assert_eq!(trim_trailing_spaces("name\\  "), "name\\ ");
+    }
+}
@@ -0,0 +1,419 @@
+use std::{
+    fmt::{self, Write as _},
+    io::{self, Read},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::packager::{ArchiveReader, EntryType, Metadata};
+use crate::Error;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// An entry of an archive as returned by [`list`](crate::packager::list), without its data.
+///
+/// Its [`Display`](fmt::Display) implementation prints a `tar -tv`-style line:
+///
+/// ```text
+/// -rw-r--r-- 1000/1000       42 2023-11-14 22:13 docs/readme.txt
+/// ```
+///
+/// Fields missing from the [`Metadata`] are printed as `?`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListEntry {
+    /// The path of the entry within the archive.
+    pub name: String,
+
+    /// The kind of the entry.
+    pub entry_type: EntryType,
+
+    /// The size of the entry data in bytes (always 0 for directories and links).
+    pub size: u64,
+
+    /// The target of a symbolic or hard link entry.
+    pub link_target: Option<String>,
+
+    /// The metadata recorded for the entry, empty if the archive has none.
+    pub metadata: Metadata,
+}
+
+impl ListEntry {
+    /// Serializes the entry as a single-line JSON object.
+    ///
+    /// Missing metadata fields are written as `null`, the modification time as
+    /// seconds since the Unix epoch and the checksum as a hexadecimal string.
+    pub fn to_json(&self) -> String {
+        let metadata = &self.metadata;
+        let mut json = String::from("{");
+        let _ = write!(json, "\"name\":{}", json_string(&self.name));
+        let _ = write!(json, ",\"type\":\"{}\"", type_name(self.entry_type));
+        let _ = write!(json, ",\"size\":{}", self.size);
+        let _ = write!(
+            json,
+            ",\"link_target\":{}",
+            json_or_null(self.link_target.as_deref().map(json_string))
+        );
+        let _ = write!(json, ",\"mode\":{}", json_or_null(metadata.mode));
+        let _ = write!(
+            json,
+            ",\"mtime\":{}",
+            json_or_null(metadata.mtime.map(unix_seconds))
+        );
+        let _ = write!(json, ",\"uid\":{}", json_or_null(metadata.uid));
+        let _ = write!(json, ",\"gid\":{}", json_or_null(metadata.gid));
+        let _ = write!(
+            json,
+            ",\"uname\":{}",
+            json_or_null(metadata.uname.as_deref().map(json_string))
+        );
+        let _ = write!(
+            json,
+            ",\"gname\":{}",
+            json_or_null(metadata.gname.as_deref().map(json_string))
+        );
+        let _ = write!(
+            json,
+            ",\"crc32\":{}",
+            json_or_null(metadata.crc32.map(|crc| format!("\"{:08x}\"", crc)))
+        );
+        json.push('}');
+        json
+    }
+}
+
+impl fmt::Display for ListEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metadata = &self.metadata;
+        let owner = metadata
+            .uname
+            .clone()
+            .or_else(|| metadata.uid.map(|uid| uid.to_string()))
+            .unwrap_or_else(|| "?".to_string());
+        let group = metadata
+            .gname
+            .clone()
+            .or_else(|| metadata.gid.map(|gid| gid.to_string()))
+            .unwrap_or_else(|| "?".to_string());
+        let mtime = metadata
+            .mtime
+            .map(format_date)
+            .unwrap_or_else(|| "????-??-?? ??:??".to_string());
+
+        write!(
+            f,
+            "{}{} {}/{} {:>8} {} {}",
+            type_char(self.entry_type),
+            mode_string(metadata.mode),
+            owner,
+            group,
+            self.size,
+            mtime,
+            self.name
+        )?;
+        match (self.entry_type, &self.link_target) {
+            (EntryType::Symlink, Some(target)) => write!(f, " -> {}", target),
+            (EntryType::HardLink, Some(target)) => write!(f, " link to {}", target),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reads every entry of the archive, discarding the data, and returns what was found.
+///
+/// The data is still read through each entry, so entries with a checksum in
+/// their metadata are verified.
+pub(crate) fn list_entries(archive: impl Read) -> Result<Vec<ListEntry>, Error> {
+    let mut reader = ArchiveReader::new(archive);
+    let mut entries = Vec::new();
+
+    for entry in reader.entries() {
+        let mut entry = entry?;
+        io::copy(&mut entry, &mut io::sink())?;
+
+        entries.push(ListEntry {
+            name: entry.name().to_string(),
+            entry_type: entry.entry_type(),
+            size: entry.size(),
+            link_target: entry.link_target().map(str::to_string),
+            metadata: entry.metadata().clone(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Serializes the entries as a JSON array, one entry per line.
+pub fn entries_to_json(entries: &[ListEntry]) -> String {
+    if entries.is_empty() {
+        return "[]".to_string();
+    }
+
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|entry| format!("  {}", entry.to_json()))
+        .collect();
+    format!("[\n{}\n]", lines.join(",\n"))
+}
+
+fn type_char(entry_type: EntryType) -> char {
+    match entry_type {
+        EntryType::Directory => 'd',
+        EntryType::Symlink => 'l',
+        EntryType::HardLink => 'h',
+        _ => '-',
+    }
+}
+
+fn type_name(entry_type: EntryType) -> &'static str {
+    match entry_type {
+        EntryType::File => "file",
+        EntryType::Directory => "directory",
+        EntryType::Symlink => "symlink",
+        EntryType::HardLink => "hardlink",
+        EntryType::LongName => "long_name",
+        EntryType::Metadata => "metadata",
+    }
+}
+
+/// Formats permission bits like `ls -l`, including setuid, setgid and sticky bits.
+fn mode_string(mode: Option<u32>) -> String {
+    let Some(mode) = mode else {
+        return "?????????".to_string();
+    };
+
+    let mut result = String::with_capacity(9);
+    for (shift, special, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')] {
+        let bits = (mode >> shift) & 0o7;
+        result.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        result.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        result.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    result
+}
+
+/// Returns the whole seconds between the Unix epoch and `time`, negative before it.
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => {
+            let d = e.duration();
+            // Round towards negative infinity, so times before the epoch stay in their minute
+            -(d.as_secs() as i64) - i64::from(d.subsec_nanos() > 0)
+        }
+    }
+}
+
+/// Formats `time` as `YYYY-MM-DD HH:MM` in UTC.
+fn format_date(time: SystemTime) -> String {
+    let seconds = unix_seconds(time);
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        of_day / 3600,
+        of_day % 3600 / 60
+    )
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian `(year, month, day)`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Shift the epoch to 0000-03-01, so leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn json_or_null(value: Option<impl fmt::Display>) -> String {
+    value.map_or_else(|| "null".to_string(), |value| value.to_string())
+}
+
+/// Quotes and escapes a string for JSON.
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packager::{pack_entries, FileEntry, Link};
+    use std::time::Duration;
+
+    fn sample_entries() -> Vec<FileEntry> {
+        vec![
+            FileEntry {
+                name: "docs".to_string(),
+                is_dir: true,
+                ..Default::default()
+            },
+            FileEntry {
+                name: "docs/readme.txt".to_string(),
+                data: b"Hello, listing!".to_vec(),
+                metadata: Metadata {
+                    mode: Some(0o644),
+                    mtime: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+                    uid: Some(1000),
+                    gid: Some(1000),
+                    uname: Some("alice".to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            FileEntry {
+                name: "latest".to_string(),
+                link: Some(Link::Symbolic("docs/readme.txt".to_string())),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_list_returns_names_types_and_sizes() {
+        // Arrange
+        let archive = pack_entries(sample_entries()).unwrap();
+
+        // Act
+        let entries = list_entries(archive.as_slice()).unwrap();
+
+        // Assert
+        let summary: Vec<(&str, EntryType, u64)> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.entry_type, e.size))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("docs", EntryType::Directory, 0),
+                ("docs/readme.txt", EntryType::File, 15),
+                ("latest", EntryType::Symlink, 0),
+            ]
+        );
+        assert_eq!(entries[1].metadata.uname.as_deref(), Some("alice"));
+        assert_eq!(entries[2].link_target.as_deref(), Some("docs/readme.txt"));
+    }
+
+    #[test]
+    fn test_list_verifies_entry_checksums() {
+        // Arrange
+        let archive = pack_entries(vec![FileEntry {
+            name: "a.txt".to_string(),
+            data: b"checked data".to_vec(),
+            metadata: Metadata {
+                crc32: Some(0xdead_beef),
+                ..Default::default()
+            },
+            ..Default::default()
+        }])
+        .unwrap();
+
+        // Act
+        let result = list_entries(archive.as_slice());
+
+        // Assert
+        assert!(matches!(result, Err(Error::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_display_matches_tar_verbose_listing() {
+        // Arrange
+        let entries = list_entries(pack_entries(sample_entries()).unwrap().as_slice()).unwrap();
+
+        // Act
+        let lines: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+
+        // Assert
+        assert_eq!(lines[0], "d????????? ?/?        0 ????-??-?? ??:?? docs");
+        assert_eq!(
+            lines[1],
+            "-rw-r--r-- alice/1000       15 2023-11-14 22:13 docs/readme.txt"
+        );
+        assert_eq!(
+            lines[2],
+            "l????????? ?/?        0 ????-??-?? ??:?? latest -> docs/readme.txt"
+        );
+    }
+
+    #[test]
+    fn test_mode_string_shows_special_bits() {
+        assert_eq!(mode_string(Some(0o755)), "rwxr-xr-x");
+        assert_eq!(mode_string(Some(0o4755)), "rwsr-xr-x");
+        assert_eq!(mode_string(Some(0o2640)), "rw-r-S---");
+        assert_eq!(mode_string(Some(0o1777)), "rwxrwxrwt");
+        assert_eq!(mode_string(None), "?????????");
+    }
+
+    #[test]
+    fn test_format_date_handles_leap_years_and_pre_epoch_times() {
+        assert_eq!(format_date(UNIX_EPOCH), "1970-01-01 00:00");
+        assert_eq!(
+            format_date(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29 00:00"
+        );
+        assert_eq!(
+            format_date(UNIX_EPOCH - Duration::from_millis(500)),
+            "1969-12-31 23:59"
+        );
+    }
+
+    #[test]
+    fn test_json_output_escapes_names_and_uses_null_for_missing_fields() {
+        // Arrange
+        let entry = ListEntry {
+            name: "quote\"and\\slash\n".to_string(),
+            entry_type: EntryType::File,
+            size: 3,
+            link_target: None,
+            metadata: Metadata {
+                mode: Some(0o600),
+                crc32: Some(0x0012_abcd),
+                ..Default::default()
+            },
+        };
+
+        // Act
+        let json = entries_to_json(&[entry]);
+
+        // Assert
+        assert_eq!(
+            json,
+            "[\n  {\"name\":\"quote\\\"and\\\\slash\\n\",\"type\":\"file\",\"size\":3,\
+             \"link_target\":null,\"mode\":384,\"mtime\":null,\"uid\":null,\"gid\":null,\
+             \"uname\":null,\"gname\":null,\"crc32\":\"0012abcd\"}\n]"
+        );
+        assert_eq!(entries_to_json(&[]), "[]");
+    }
+}
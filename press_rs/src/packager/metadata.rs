@@ -0,0 +1,426 @@
+#[cfg(unix)]
+use std::{collections::HashMap, sync::OnceLock};
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::packager::OwnershipPolicy;
+use crate::Error;
+
+/// File metadata stored alongside an archive entry.
+///
+/// Every field is optional: entries created in memory usually carry no metadata,
+/// while [`pack`](crate::packager::pack) records what the filesystem provides.
+/// The metadata is written as an [`EntryType::Metadata`](crate::packager::EntryType::Metadata)
+/// record of PAX-style `"<length> <key>=<value>\n"` lines preceding the entry.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::packager::{FileEntry, Metadata};
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let script = FileEntry {
+///     name: "run.sh".to_string(),
+///     data: b"#!/bin/sh\necho hi\n".to_vec(),
+///     metadata: Metadata {
+///         mode: Some(0o755),
+///         mtime: Some(UNIX_EPOCH + Duration::new(1_700_000_000, 5)),
+///         ..Default::default()
+///     },
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// Unix permission bits (including setuid, setgid and sticky bits).
+    pub mode: Option<u32>,
+
+    /// Modification time, with nanosecond precision.
+    pub mtime: Option<SystemTime>,
+
+    /// Numeric user id of the owner.
+    pub uid: Option<u32>,
+
+    /// Numeric group id of the owner.
+    pub gid: Option<u32>,
+
+    /// User name of the owner. [`from_fs`](Metadata::from_fs) looks it up in
+    /// `/etc/passwd`, so users of other sources (such as LDAP) have none.
+    pub uname: Option<String>,
+
+    /// Group name of the owner, looked up in `/etc/group` like the user name.
+    pub gname: Option<String>,
+
+    /// CRC-32 of the entry data, verified when the entry is read to the end.
+    pub crc32: Option<u32>,
+}
+
+impl Metadata {
+    /// Captures the metadata of a file or directory on disk.
+    pub fn from_fs(metadata: &fs::Metadata) -> Metadata {
+        let mut result = Metadata {
+            mtime: metadata.modified().ok(),
+            ..Default::default()
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            result.mode = Some(metadata.mode() & 0o7777);
+            result.uid = Some(metadata.uid());
+            result.gid = Some(metadata.gid());
+            result.uname = user_name(metadata.uid());
+            result.gname = group_name(metadata.gid());
+        }
+
+        result
+    }
+
+    /// Returns `true` if no field is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    /// Serializes the metadata into PAX-style records.
+    pub(crate) fn to_records(&self) -> Vec<u8> {
+        let mut records = Vec::new();
+        if let Some(mode) = self.mode {
+            push_record(&mut records, "mode", &format!("{:o}", mode));
+        }
+        if let Some(mtime) = self.mtime {
+            push_record(&mut records, "mtime", &format_time(mtime));
+        }
+        if let Some(uid) = self.uid {
+            push_record(&mut records, "uid", &uid.to_string());
+        }
+        if let Some(gid) = self.gid {
+            push_record(&mut records, "gid", &gid.to_string());
+        }
+        if let Some(uname) = &self.uname {
+            push_record(&mut records, "uname", uname);
+        }
+        if let Some(gname) = &self.gname {
+            push_record(&mut records, "gname", gname);
+        }
+        if let Some(crc32) = self.crc32 {
+            push_record(&mut records, "crc32", &format!("{:08x}", crc32));
+        }
+        records
+    }
+
+    /// Parses PAX-style records. Unknown keys are ignored.
+    pub(crate) fn from_records(data: &[u8]) -> Result<Metadata, Error> {
+        let mut metadata = Metadata::default();
+
+        for (key, value) in parse_records(data)? {
+            let bad_value = || Error::BadHeader(format!("invalid metadata value for '{}'", key));
+            match key {
+                "mode" => {
+                    metadata.mode = Some(u32::from_str_radix(value, 8).map_err(|_| bad_value())?)
+                }
+                "mtime" => metadata.mtime = Some(parse_time(value).ok_or_else(bad_value)?),
+                "uid" => metadata.uid = Some(value.parse().map_err(|_| bad_value())?),
+                "gid" => metadata.gid = Some(value.parse().map_err(|_| bad_value())?),
+                "uname" => metadata.uname = Some(value.to_string()),
+                "gname" => metadata.gname = Some(value.to_string()),
+                "crc32" => {
+                    metadata.crc32 = Some(u32::from_str_radix(value, 16).map_err(|_| bad_value())?)
+                }
+                _ => {}
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// Applies the metadata to the extracted file or directory at `path`.
+    ///
+    /// Ownership is applied first (changing the owner may clear setuid bits),
+    /// then the modification time and finally the permissions, which may make
+    /// the file read-only. The setuid and setgid bits are only restored along
+    /// with the recorded owner, so that they never apply to the extracting user.
+    pub(crate) fn apply(&self, path: &Path, ownership: OwnershipPolicy) -> Result<(), Error> {
+        #[cfg(unix)]
+        let mut owner_restored = false;
+        #[cfg(unix)]
+        if ownership != OwnershipPolicy::Skip && (self.uid.is_some() || self.gid.is_some()) {
+            match std::os::unix::fs::chown(path, self.uid, self.gid) {
+                Ok(()) => owner_restored = self.uid.is_some() && self.gid.is_some(),
+                Err(e)
+                    if e.kind() == io::ErrorKind::PermissionDenied
+                        && ownership == OwnershipPolicy::RestoreIfPermitted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = ownership;
+
+        if let Some(mtime) = self.mtime {
+            set_modified(path, mtime)?;
+        }
+
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            let mask = if owner_restored { 0o7777 } else { 0o1777 };
+            fs::set_permissions(path, fs::Permissions::from_mode(mode & mask))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    static USERS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    USERS
+        .get_or_init(|| read_id_names("/etc/passwd"))
+        .get(&uid)
+        .cloned()
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    static GROUPS: OnceLock<HashMap<u32, String>> = OnceLock::new();
+    GROUPS
+        .get_or_init(|| read_id_names("/etc/group"))
+        .get(&gid)
+        .cloned()
+}
+
+/// Reads the id to name mapping of a `/etc/passwd` or `/etc/group` file, once
+/// per process. A missing or unreadable file yields no names.
+#[cfg(unix)]
+fn read_id_names(path: &str) -> HashMap<u32, String> {
+    fs::read_to_string(path)
+        .map(|text| parse_id_names(&text))
+        .unwrap_or_default()
+}
+
+/// Parses `name:password:id:...` lines, keeping the first name of every id.
+#[cfg(unix)]
+fn parse_id_names(text: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let mut fields = line.split(':');
+        let (Some(name), Some(id)) = (fields.next(), fields.nth(1)) else {
+            continue;
+        };
+        if let Ok(id) = id.parse() {
+            names.entry(id).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(unix)]
+fn set_modified(path: &Path, mtime: SystemTime) -> io::Result<()> {
+    File::open(path)?.set_modified(mtime)
+}
+
+#[cfg(not(unix))]
+fn set_modified(path: &Path, mtime: SystemTime) -> io::Result<()> {
+    // Directories cannot be opened as files here, so only files get their time restored
+    if path.is_dir() {
+        return Ok(());
+    }
+    File::options().write(true).open(path)?.set_modified(mtime)
+}
+
+/// Appends a `"<length> <key>=<value>\n"` record, where the length counts the whole record.
+fn push_record(records: &mut Vec<u8>, key: &str, value: &str) {
+    // Length of " key=value\n" without the length prefix
+    let rest = key.len() + value.len() + 3;
+    let mut len = rest + 1;
+    while len.to_string().len() + rest != len {
+        len = len.to_string().len() + rest;
+    }
+    records.extend_from_slice(format!("{} {}={}\n", len, key, value).as_bytes());
+}
+
+fn parse_records(mut data: &[u8]) -> Result<Vec<(&str, &str)>, Error> {
+    let bad_record = || Error::BadHeader("malformed metadata record".to_string());
+    let mut records = Vec::new();
+
+    while !data.is_empty() {
+        let space = data
+            .iter()
+            .position(|&b| b == b' ')
+            .ok_or_else(bad_record)?;
+        let len: usize = std::str::from_utf8(&data[..space])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(bad_record)?;
+        if len <= space + 1 || len > data.len() || data[len - 1] != b'\n' {
+            return Err(bad_record());
+        }
+
+        let record = std::str::from_utf8(&data[space + 1..len - 1]).map_err(|_| bad_record())?;
+        let (key, value) = record.split_once('=').ok_or_else(bad_record)?;
+        records.push((key, value));
+        data = &data[len..];
+    }
+
+    Ok(records)
+}
+
+/// Formats a time as signed seconds since the Unix epoch with 9 fractional digits.
+fn format_time(time: SystemTime) -> String {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => format!("{}.{:09}", d.as_secs(), d.subsec_nanos()),
+        Err(e) => {
+            let d = e.duration();
+            format!("-{}.{:09}", d.as_secs(), d.subsec_nanos())
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Option<SystemTime> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let (secs, frac) = value.split_once('.').unwrap_or((value, ""));
+    if frac.len() > 9 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let secs: u64 = secs.parse().ok()?;
+    let nanos: u32 = format!("{:0<9}", frac).parse().ok()?;
+    let offset = Duration::new(secs, nanos);
+
+    if negative {
+        UNIX_EPOCH.checked_sub(offset)
+    } else {
+        UNIX_EPOCH.checked_add(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_round_trip() {
+        // Arrange
+        let metadata = Metadata {
+            mode: Some(0o4755),
+            mtime: Some(UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789)),
+            uid: Some(1000),
+            gid: Some(100),
+            uname: Some("alice".to_string()),
+            gname: Some("users = staff".to_string()),
+            crc32: Some(0xCBF4_3926),
+        };
+
+        // Act
+        let records = metadata.to_records();
+        let decoded = Metadata::from_records(&records).unwrap();
+
+        // Assert
+        assert_eq!(decoded, metadata);
+    }
+
+    #[test]
+    fn test_record_length_includes_prefix() {
+        // Arrange
+        let mut records = Vec::new();
+
+        // Act
+        push_record(&mut records, "uid", "1000");
+        push_record(&mut records, "uname", &"x".repeat(95));
+
+        // Assert
+        assert_eq!(&records[..12], b"12 uid=1000\n");
+        assert_eq!(records.len() - 12, 106);
+        assert!(records[12..].starts_with(b"106 uname="));
+    }
+
+    #[test]
+    fn test_time_before_epoch() {
+        // Arrange
+        let time = UNIX_EPOCH - Duration::new(10, 500_000_000);
+
+        // Act
+        let formatted = format_time(time);
+
+        // Assert
+        assert_eq!(formatted, "-10.500000000");
+        assert_eq!(parse_time(&formatted), Some(time));
+        assert_eq!(
+            parse_time("5.5"),
+            Some(UNIX_EPOCH + Duration::new(5, 500_000_000))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_setuid_is_stripped_without_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Arrange
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = dir.path().join("tool");
+        fs::write(&path, b"x").unwrap();
+        let metadata = Metadata {
+            mode: Some(0o6755),
+            uid: Some(0),
+            gid: Some(0),
+            ..Default::default()
+        };
+
+        // Act
+        metadata.apply(&path, OwnershipPolicy::Skip).unwrap();
+
+        // Assert
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o755);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_id_names_are_parsed() {
+        // Arrange
+        let passwd = "# local users\nroot:x:0:0:root:/root:/bin/sh\ntoor:x:0:0::/:/bin/sh\nalice:x:1000:100::/home/alice:/bin/sh\nbroken\nbad:x:abc:1::/:/bin/sh\n";
+
+        // Act
+        let names = parse_id_names(passwd);
+
+        // Assert
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&0], "root");
+        assert_eq!(names[&1000], "alice");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_from_fs_fills_owner_names() {
+        use std::os::unix::fs::MetadataExt;
+
+        // Arrange
+        let dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let fs_metadata = fs::metadata(dir.path()).unwrap();
+
+        // Act
+        let metadata = Metadata::from_fs(&fs_metadata);
+
+        // Assert
+        assert_eq!(metadata.uname, user_name(fs_metadata.uid()));
+        assert_eq!(metadata.gname, group_name(fs_metadata.gid()));
+    }
+
+    #[test]
+    fn test_malformed_records_are_rejected() {
+        for data in [&b"5 a=b"[..], b"99 mode=7\n", b"x mode=7\n", b"10 mode=9\n"] {
+            assert!(
+                matches!(Metadata::from_records(data), Err(Error::BadHeader(_))),
+                "{:?} should be rejected",
+                String::from_utf8_lossy(data)
+            );
+        }
+    }
+}
@@ -1,13 +1,31 @@
-use header::ENTRY_SIZE;
-use pack::{pack_directory, pack_file};
-use std::{fs, path::Path};
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
 
-use crate::packager::{pack::pack_from_file_entries, unpack::unpack_to_file_entries};
+use crate::packager::{
+    list::list_entries,
+    pack::{pack_from_file_entries, pack_path, pack_path_with_index},
+    unpack::{unpack_to_file_entries, unpack_to_filtered_entries},
+};
+use crate::Error;
 
+mod filter;
 mod header;
+mod ignore;
+mod list;
+mod metadata;
 mod pack;
 mod unpack;
 
+pub use filter::PathFilter;
+pub use header::{EntryType, Header};
+pub use list::{entries_to_json, ListEntry};
+pub use metadata::Metadata;
+pub use pack::ArchiveWriter;
+pub use unpack::{ArchiveEntry, ArchiveReader, Entries};
+
 /// Represents a single entry (file or directory) within the archive.
 ///
 /// This struct is primarily used when working with in-memory archives via [`pack_entries`]
@@ -23,6 +41,7 @@ mod unpack;
 ///     name: "documents/notes.txt".to_string(),
 ///     data: b"Remember to buy milk".to_vec(),
 ///     is_dir: false,
+///     ..Default::default()
 /// };
 /// ```
 ///
@@ -34,9 +53,10 @@ mod unpack;
 ///     name: "documents/".to_string(),
 ///     data: Vec::new(), // Directories have no content
 ///     is_dir: true,
+///     ..Default::default()
 /// };
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct FileEntry {
     /// The relative path of the file or directory within the archive.
     ///
@@ -55,6 +75,156 @@ pub struct FileEntry {
     /// * If `true`, the entry is treated as a folder structure.
     /// * If `false`, the entry is treated as a regular file containing [`data`](Self::data).
     pub is_dir: bool,
+
+    /// Permissions, modification time and ownership of the entry.
+    ///
+    /// Empty for entries created in memory unless set explicitly; see [`Metadata`].
+    pub metadata: Metadata,
+
+    /// Makes the entry a symbolic or hard link instead of a file or directory.
+    ///
+    /// Link entries carry no [`data`](Self::data).
+    pub link: Option<Link>,
+}
+
+/// The target of a link entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Link {
+    /// A symbolic link to the given path, relative to the directory containing the link.
+    Symbolic(String),
+    /// A hard link to the entry with the given name, which must appear earlier in the archive.
+    Hard(String),
+}
+
+/// The position of an entry in a packed archive, recorded by [`ArchiveWriter`].
+///
+/// Compressed archives written with
+/// [`CompressOptions::index`](crate::compressor::CompressOptions::index) store
+/// these positions in a trailing index, so
+/// [`IndexedArchive::open_entry`](crate::compressor::IndexedArchive::open_entry)
+/// can decompress a single entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The name of the entry.
+    pub name: String,
+
+    /// The kind of the entry.
+    pub entry_type: EntryType,
+
+    /// The offset of the first record of the entry, including any long name
+    /// or metadata record in front of its header.
+    pub offset: u64,
+
+    /// The offset of the entry data (the link target for links).
+    pub data_offset: u64,
+
+    /// The size of the entry data in bytes.
+    pub size: u64,
+}
+
+/// Options controlling how files and directories on disk are packed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::{pack_with_options, PackOptions, PathFilter};
+///
+/// let options = PackOptions {
+///     follow_links: true,
+///     ..Default::default()
+/// };
+/// let archive = pack_with_options("./node_modules", Vec::new(), &options).unwrap();
+///
+/// // Pack a project the way git sees it, without build output
+/// let options = PackOptions {
+///     filter: PathFilter::new().exclude("target").exclude("**/*.log"),
+///     ignore_files: true,
+///     skip_hidden: true,
+///     ..Default::default()
+/// };
+/// let archive = pack_with_options("./my_project", Vec::new(), &options).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    /// Pack the files and directories symbolic links point to instead of storing
+    /// the links themselves. Links that would recurse into one of their parent
+    /// directories and links to missing targets are still stored as links.
+    pub follow_links: bool,
+
+    /// Store a CRC-32 of every file in its [`Metadata`], so damaged entries are
+    /// detected when the archive is read. Files on disk are read twice.
+    pub entry_checksums: bool,
+
+    /// Selects the files and directories to pack by their path relative to the
+    /// packed directory; everything by default. Excluded directories are not
+    /// entered, and directories only matched by the include patterns through
+    /// their contents are stored if something inside them is.
+    pub filter: PathFilter,
+
+    /// Honour `.gitignore` and `.ignore` files found in the packed directories,
+    /// using the `.gitignore` syntax. `.git` directories are skipped too.
+    pub ignore_files: bool,
+
+    /// Do not enter directories deeper than this below the packed directory;
+    /// `Some(1)` packs its direct contents only. Unlimited by default.
+    pub max_depth: Option<usize>,
+
+    /// Skip files and directories whose names start with a dot.
+    pub skip_hidden: bool,
+}
+
+/// Controls whether [`unpack`] restores the owner recorded in the entry [`Metadata`].
+///
+/// Changing the owner of a file usually requires root privileges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OwnershipPolicy {
+    /// Restore the recorded uid/gid and fail if that is not permitted.
+    Restore,
+    /// Restore the recorded uid/gid, silently keeping the current owner if the
+    /// process is not permitted to change it.
+    #[default]
+    RestoreIfPermitted,
+    /// Never change the owner of extracted files.
+    Skip,
+}
+
+/// Options controlling how archives are extracted to the filesystem.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::{unpack_with_options, PathFilter, UnpackOptions};
+/// use std::fs::File;
+///
+/// let options = UnpackOptions {
+///     skip_unsafe_paths: true,
+///     ..Default::default()
+/// };
+/// unpack_with_options(File::open("upload.press").unwrap(), "./output", &options).unwrap();
+///
+/// // Restore only the configuration, leaving out backups of it
+/// let options = UnpackOptions {
+///     filter: PathFilter::new().include("etc").exclude("etc/**/*.bak"),
+///     ..Default::default()
+/// };
+/// unpack_with_options(File::open("nightly.press").unwrap(), "./restore", &options).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UnpackOptions {
+    /// Skip entries whose names would escape the destination directory
+    /// (absolute paths, drive prefixes, `..` components) instead of failing
+    /// with [`Error::UnsafePath`].
+    pub skip_unsafe_paths: bool,
+
+    /// Whether to restore the owner of extracted entries. Permissions and
+    /// modification times are always restored when recorded, except for the
+    /// setuid and setgid bits, which are dropped unless the owner is restored.
+    pub ownership: OwnershipPolicy,
+
+    /// Selects the entries to extract; everything by default. The data of the
+    /// other entries is skipped without being written. Hard links whose target
+    /// is not selected are skipped too.
+    pub filter: PathFilter,
 }
 
 /// Packs a file or directory into a binary archive
@@ -65,7 +235,8 @@ pub struct FileEntry {
 ///
 /// # Returns
 ///
-/// Returns a `Vec<u8>` containing the packed archive data.
+/// Returns a `Result` containing the packed archive data, or an [`Error`] if the
+/// path cannot be read.
 ///
 /// # Examples
 ///
@@ -73,23 +244,86 @@ pub struct FileEntry {
 /// use press_rs::packager::pack;
 ///
 /// // Pack a directory
-/// let archive_bytes = pack("./src");
+/// let archive_bytes = pack("./src").unwrap();
 ///
 /// // Or pack a single file
-/// let file_archive = pack("Cargo.toml");
-/// ```
-pub fn pack(path: impl AsRef<Path>) -> Vec<u8> {
-    let mut archive = Vec::<u8>::new();
-    if path.as_ref().is_dir() {
-        archive.extend(pack_directory(path.as_ref(), path.as_ref()));
-    } else {
-        archive.extend(pack_file(path.as_ref(), path.as_ref()));
-    }
+/// let file_archive = pack("Cargo.toml").unwrap();
+/// ```
+pub fn pack(path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
+    pack_to_writer(path, Vec::new())
+}
 
-    // Add 2 empty entries to mark the end of the archive
-    archive.extend(std::iter::repeat(0).take(ENTRY_SIZE * 2));
+/// Packs a file or directory straight into a writer, without holding the archive in memory.
+///
+/// This is the streaming counterpart of [`pack`]; see [`ArchiveWriter`] for building
+/// archives entry by entry.
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to pack.
+/// * `writer` - The sink the archive is written to.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the writer after the archive has been written and flushed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::pack_to_writer;
+/// use std::{fs::File, io::BufWriter};
+///
+/// let file = BufWriter::new(File::create("src.press").unwrap());
+/// pack_to_writer("./src", file).unwrap();
+/// ```
+pub fn pack_to_writer<W: Write>(path: impl AsRef<Path>, writer: W) -> Result<W, Error> {
+    pack_with_options(path, writer, &PackOptions::default())
+}
 
-    return archive;
+/// Packs a file or directory into a writer using the given [`PackOptions`].
+///
+/// # Arguments
+///
+/// * `path` - The path to the file or directory to pack.
+/// * `writer` - The sink the archive is written to.
+/// * `options` - Options controlling how the path is packed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::{pack_with_options, PackOptions};
+/// use std::{fs::File, io::BufWriter};
+///
+/// let file = BufWriter::new(File::create("src.press").unwrap());
+/// pack_with_options("./src", file, &PackOptions::default()).unwrap();
+/// ```
+pub fn pack_with_options<W: Write>(
+    path: impl AsRef<Path>,
+    writer: W,
+    options: &PackOptions,
+) -> Result<W, Error> {
+    pack_path(path.as_ref(), writer, options)
+}
+
+/// Like [`pack_with_options`], but also returns the position of every entry in
+/// the archive, in the order they were written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::{pack_with_index, PackOptions};
+///
+/// let (archive, index) = pack_with_index("./src", Vec::new(), &PackOptions::default()).unwrap();
+/// for entry in index {
+///     println!("{} at offset {}", entry.name, entry.data_offset);
+/// }
+/// ```
+pub fn pack_with_index<W: Write>(
+    path: impl AsRef<Path>,
+    writer: W,
+    options: &PackOptions,
+) -> Result<(W, Vec<IndexEntry>), Error> {
+    pack_path_with_index(path.as_ref(), writer, options)
 }
 
 /// Packs a list of file entries into a binary archive. Useful for non-filesystem use.
@@ -100,7 +334,7 @@ pub fn pack(path: impl AsRef<Path>) -> Vec<u8> {
 ///
 /// # Returns
 ///
-/// Returns a `Vec<u8>` containing the packed archive data.
+/// Returns a `Result` containing the packed archive data.
 ///
 /// # Examples
 ///
@@ -111,24 +345,32 @@ pub fn pack(path: impl AsRef<Path>) -> Vec<u8> {
 ///     FileEntry {
 ///         name: "hello.txt".to_string(),
 ///         data: b"Hello World".to_vec(),
-///         is_dir: false
+///         is_dir: false,
+///         ..Default::default()
 ///     }
 /// ];
 ///
-/// let archive = pack_entries(entries);
+/// let archive = pack_entries(entries).unwrap();
 /// # assert!(!archive.is_empty());
 /// ```
-pub fn pack_entries(entries: Vec<FileEntry>) -> Vec<u8> {
+pub fn pack_entries(entries: Vec<FileEntry>) -> Result<Vec<u8>, Error> {
     pack_from_file_entries(entries)
 }
 
 /// Unpacks the archive and creates directories/files on the specified path.
 ///
+/// Entry names are normalized before extraction; entries that would be written
+/// outside `path` are rejected with [`Error::UnsafePath`].
+///
 /// # Arguments
 ///
 /// * `archive` - The binary archive data to unpack.
 /// * `path` - The path to unpack the archive content to.
 ///
+/// # Errors
+///
+/// Returns an [`Error`] if the archive is malformed or the files cannot be written.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -138,13 +380,62 @@ pub fn pack_entries(entries: Vec<FileEntry>) -> Vec<u8> {
 /// let archive_bytes = fs::read("backup.press").unwrap();
 ///
 /// // Unpack archive content into the "output" directory
-/// unpack(archive_bytes, "./output");
+/// unpack(archive_bytes, "./output").unwrap();
+/// ```
+pub fn unpack(archive: Vec<u8>, path: impl AsRef<Path>) -> Result<(), Error> {
+    unpack_from_reader(archive.as_slice(), path)
+}
+
+/// Unpacks an archive read from any [`Read`] source and creates directories/files
+/// on the specified path.
+///
+/// This is the streaming counterpart of [`unpack`]: entries are extracted one by
+/// one as they are read, see [`ArchiveReader`].
+///
+/// # Arguments
+///
+/// * `reader` - The source of the binary archive data.
+/// * `path` - The path to unpack the archive content to.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::unpack_from_reader;
+/// use std::{fs::File, io::BufReader};
+///
+/// let file = BufReader::new(File::open("backup.press").unwrap());
+/// unpack_from_reader(file, "./output").unwrap();
+/// ```
+pub fn unpack_from_reader(reader: impl Read, path: impl AsRef<Path>) -> Result<(), Error> {
+    unpack_with_options(reader, path, &UnpackOptions::default())
+}
+
+/// Unpacks an archive read from any [`Read`] source using the given [`UnpackOptions`].
+///
+/// # Arguments
+///
+/// * `reader` - The source of the binary archive data.
+/// * `path` - The path to unpack the archive content to.
+/// * `options` - Options controlling the extraction.
+///
+/// # Examples
+///
+/// ```no_run
+/// use press_rs::packager::{unpack_with_options, UnpackOptions};
+/// use std::{fs::File, io::BufReader};
+///
+/// let file = BufReader::new(File::open("backup.press").unwrap());
+/// unpack_with_options(file, "./output", &UnpackOptions::default()).unwrap();
 /// ```
-pub fn unpack(archive: Vec<u8>, path: impl AsRef<Path>) {
+pub fn unpack_with_options(
+    reader: impl Read,
+    path: impl AsRef<Path>,
+    options: &UnpackOptions,
+) -> Result<(), Error> {
     if !path.as_ref().exists() {
-        fs::create_dir_all(path.as_ref()).expect("Cannot create directory");
+        fs::create_dir_all(path.as_ref())?;
     }
-    unpack::unpack_with_dir_creation(archive, path);
+    unpack::unpack_with_dir_creation(reader, path, options)
 }
 
 /// Returns a list of unpacked entries. Does not create directories.
@@ -155,7 +446,8 @@ pub fn unpack(archive: Vec<u8>, path: impl AsRef<Path>) {
 ///
 /// # Returns
 ///
-/// Returns a `Vec<FileEntry>` containing the unpacked entries.
+/// Returns a `Result` containing the unpacked entries, or an [`Error`] if the
+/// archive is malformed.
 ///
 /// # Examples
 ///
@@ -164,16 +456,97 @@ pub fn unpack(archive: Vec<u8>, path: impl AsRef<Path>) {
 /// use std::fs;
 ///
 /// let archive_bytes = fs::read("data.press").unwrap();
-/// let entries = unpack_to_entries(archive_bytes);
+/// let entries = unpack_to_entries(archive_bytes).unwrap();
 ///
 /// for entry in entries {
 ///     println!("Found file: {}", entry.name);
 /// }
 /// ```
-pub fn unpack_to_entries(archive: Vec<u8>) -> Vec<FileEntry> {
+pub fn unpack_to_entries(archive: Vec<u8>) -> Result<Vec<FileEntry>, Error> {
     unpack_to_file_entries(archive)
 }
 
+/// Returns the unpacked entries selected by a [`PathFilter`]. Does not create
+/// directories.
+///
+/// # Arguments
+///
+/// * `archive` - The binary archive data to unpack.
+/// * `filter` - The paths and glob patterns of the entries to keep.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the selected entries, or an [`Error`] if the
+/// archive is malformed.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::packager::{pack_entries, unpack_to_entries_with_filter, FileEntry, PathFilter};
+///
+/// let archive = pack_entries(vec![
+///     FileEntry {
+///         name: "src/main.rs".to_string(),
+///         data: b"fn main() {}".to_vec(),
+///         ..Default::default()
+///     },
+///     FileEntry {
+///         name: "README.md".to_string(),
+///         data: b"# Readme".to_vec(),
+///         ..Default::default()
+///     },
+/// ])
+/// .unwrap();
+///
+/// let filter = PathFilter::new().include("src/**/*.rs");
+/// let entries = unpack_to_entries_with_filter(archive, &filter).unwrap();
+/// assert_eq!(entries.len(), 1);
+/// assert_eq!(entries[0].name, "src/main.rs");
+/// ```
+pub fn unpack_to_entries_with_filter(
+    archive: Vec<u8>,
+    filter: &PathFilter,
+) -> Result<Vec<FileEntry>, Error> {
+    unpack_to_filtered_entries(archive, filter)
+}
+
+/// Lists the entries of an archive read from any [`Read`] source without
+/// extracting them.
+///
+/// Entry data is read and discarded as the archive is streamed, so no more than
+/// one buffer of data is held in memory. Entries carrying a checksum in their
+/// [`Metadata`] are still verified.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the binary archive data.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the names, types, sizes and metadata of the
+/// entries, or an [`Error`] if the archive is malformed.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::packager::{list, pack_entries, FileEntry};
+///
+/// let archive = pack_entries(vec![FileEntry {
+///     name: "notes.txt".to_string(),
+///     data: b"remember the milk".to_vec(),
+///     ..Default::default()
+/// }])
+/// .unwrap();
+///
+/// for entry in list(archive.as_slice()).unwrap() {
+///     // Prints a `tar -tv`-style line
+///     println!("{}", entry);
+/// }
+/// ```
+pub fn list(reader: impl Read) -> Result<Vec<ListEntry>, Error> {
+    list_entries(reader)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -186,10 +559,11 @@ mod tests {
             name: "a/b/c.txt".to_string(),
             data: b"nested content".to_vec(),
             is_dir: false,
+            ..Default::default()
         }];
 
-        let archive = crate::packager::pack_entries(original_entries.clone());
-        let unpacked_entries = unpack_to_entries(archive);
+        let archive = crate::packager::pack_entries(original_entries.clone()).unwrap();
+        let unpacked_entries = unpack_to_entries(archive).unwrap();
 
         assert_eq!(original_entries.len(), unpacked_entries.len());
         assert_eq!(original_entries[0].name, unpacked_entries[0].name);
@@ -209,8 +583,8 @@ mod tests {
         fs::write(src_dir.join(file_name), content).unwrap();
 
         // Act
-        let archive_data = pack(&src_dir);
-        unpack(archive_data, &dst_dir);
+        let archive_data = pack(&src_dir).unwrap();
+        unpack(archive_data, &dst_dir).unwrap();
 
         // Assert
         let unpacked_file_path = dst_dir.join(file_name);
@@ -227,12 +601,12 @@ mod tests {
         let non_existent = dir.path().join("ghost_folder");
 
         // Act
-        let result = std::panic::catch_unwind(|| pack(&non_existent));
+        let result = pack(&non_existent);
 
         // Assert
         assert!(
-            result.is_err() || result.unwrap().is_empty(),
-            "Should handle non-existent path gracefully"
+            matches!(result, Err(Error::Io(_))),
+            "Should report non-existent path as an error"
         );
     }
 }
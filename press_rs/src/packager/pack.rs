@@ -1,91 +1,520 @@
-use std::{fs, path::Path};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::checksum::{crc32, Crc32};
+use crate::packager::{FileEntry, IndexEntry, Link, Metadata, PackOptions};
+use crate::Error;
+
+use super::header::{EntryType, Header, ENTRY_SIZE, LONG_NAME_MARKER, METADATA_MARKER, NAME_SIZE};
+use super::ignore::IgnoreStack;
+
+const ZERO_BLOCK: [u8; ENTRY_SIZE] = [0; ENTRY_SIZE];
+
+/// Streaming archive builder that writes entries straight to a sink.
+///
+/// Every `append_*` call writes the entry [`Header`] block followed by the entry
+/// data padded to a multiple of the block size, so file contents are copied
+/// through a small buffer and never held in memory as a whole. Names longer than
+/// [`NAME_SIZE`] bytes are preceded by an [`EntryType::LongName`] record, and
+/// entries with [`Metadata`] by an [`EntryType::Metadata`] record.
+///
+/// Symbolic links found by [`append_dir_all`](ArchiveWriter::append_dir_all)
+/// are stored as links unless [`PackOptions::follow_links`] is set, and files
+/// that were already packed under another name are stored as hard links (on Unix).
+/// The [`PackOptions`] filters, ignore files, depth limit and hidden file
+/// setting decide which paths on disk are packed.
+/// [`finish`](ArchiveWriter::finish) writes the two empty blocks that mark the end
+/// of the archive.
+///
+/// The position of every entry is recorded as an [`IndexEntry`], returned by
+/// [`finish_with_index`](ArchiveWriter::finish_with_index) for the trailing
+/// index of compressed archives.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::packager::{unpack_to_entries, ArchiveWriter};
+///
+/// let mut archive = ArchiveWriter::new(Vec::new());
+/// archive.append_dir("docs").unwrap();
+/// archive.append_reader("docs/notes.txt", 5, &b"hello"[..]).unwrap();
+/// let bytes = archive.finish().unwrap();
+///
+/// let entries = unpack_to_entries(bytes).unwrap();
+/// assert_eq!(entries[1].data, b"hello");
+/// ```
+pub struct ArchiveWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    options: PackOptions,
+    /// Names of the packed files with more than one hard link, by device and inode.
+    hard_links: HashMap<(u64, u64), String>,
+    /// The number of bytes written so far.
+    position: u64,
+    /// The positions of the entries written so far.
+    index: Vec<IndexEntry>,
+    /// Directories not selected by the include patterns themselves, written
+    /// before the first entry found inside them.
+    pending_dirs: Vec<(String, Metadata)>,
+}
 
-use crate::packager::FileEntry;
+impl<W> ArchiveWriter<W>
+where
+    W: Write,
+{
+    /// Creates a builder that writes the archive to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, PackOptions::default())
+    }
 
-use super::header::{EntryType, Header, ENTRY_SIZE};
+    /// Creates a builder that writes the archive to `writer`, packing paths on
+    /// disk according to `options`.
+    pub fn with_options(writer: W, options: PackOptions) -> Self {
+        Self {
+            writer,
+            options,
+            hard_links: HashMap::new(),
+            position: 0,
+            index: Vec::new(),
+            pending_dirs: Vec::new(),
+        }
+    }
 
-pub fn pack_from_file_entries(entries: Vec<FileEntry>) -> Vec<u8> {
-    let mut stream = Vec::new();
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
 
-    for entry in entries {
-        let entry_type = if entry.is_dir {
-            EntryType::Directory
-        } else {
-            EntryType::File
-        };
+    /// Appends a directory entry named `name`.
+    ///
+    /// Only the entry itself is written; use [`append_dir_all`](Self::append_dir_all)
+    /// to pack the contents of a directory on disk.
+    pub fn append_dir(&mut self, name: &str) -> Result<(), Error> {
+        self.append_dir_with_metadata(name, &Metadata::default())
+    }
 
-        let header = Header::from_values(entry.name, entry.data.len(), entry_type);
+    /// Appends a directory entry named `name` carrying `metadata`.
+    pub fn append_dir_with_metadata(
+        &mut self,
+        name: &str,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        self.write_header(name, 0, EntryType::Directory, metadata)
+    }
 
-        stream.extend(header.to_bytes());
+    /// Appends the directory at `path` under `name`, followed by all of its
+    /// contents. Entry names of the contents are prefixed with `name/`.
+    ///
+    /// The permissions, modification time and ownership of every entry are
+    /// recorded as its [`Metadata`].
+    ///
+    /// When following links, a link pointing back to one of the directories
+    /// being packed is stored as a link instead of being followed forever, and
+    /// so is a link whose target does not exist.
+    pub fn append_dir_all(&mut self, name: &str, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut walk = Walk::new(name);
+        self.append_dir_tree(name, path, &fs::metadata(path)?, &mut walk)
+    }
 
-        if !entry.is_dir {
-            let data = file_as_entries(entry.data);
-            stream.extend(data);
+    /// Appends the file at `path` under `name`, streaming its contents from disk.
+    ///
+    /// The permissions, modification time and ownership of the file are recorded
+    /// as its [`Metadata`].
+    pub fn append_file(&mut self, name: &str, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        self.append_fs_file(name, path, &fs::metadata(path)?)
+    }
+
+    /// Appends a symbolic link named `name` pointing to `target`.
+    pub fn append_symlink(&mut self, name: &str, target: &str) -> Result<(), Error> {
+        self.write_link(name, EntryType::Symlink, target)
+    }
+
+    /// Appends a hard link named `name` to the entry named `target`, which must
+    /// appear earlier in the archive.
+    pub fn append_hard_link(&mut self, name: &str, target: &str) -> Result<(), Error> {
+        self.write_link(name, EntryType::HardLink, target)
+    }
+
+    /// Appends a file entry named `name` whose `len` bytes of data are read from `reader`.
+    ///
+    /// Returns an error if `reader` ends before `len` bytes were read; any extra
+    /// data after the first `len` bytes is ignored.
+    pub fn append_reader(&mut self, name: &str, len: u64, reader: impl Read) -> Result<(), Error> {
+        self.append_reader_with_metadata(name, len, reader, &Metadata::default())
+    }
+
+    /// Like [`append_reader`](Self::append_reader), but the entry carries `metadata`.
+    pub fn append_reader_with_metadata(
+        &mut self,
+        name: &str,
+        len: u64,
+        reader: impl Read,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        self.write_header(name, len, EntryType::File, metadata)?;
+
+        let copied = io::copy(&mut reader.take(len), &mut self.writer)?;
+        self.position += copied;
+        if copied != len {
+            return Err(Error::Io(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("entry '{}' is shorter than its declared length", name),
+            )));
         }
+
+        self.write_padding(len)
     }
 
-    // Add 2 empty entries to mark the end of the archive
-    stream.extend(std::iter::repeat(0).take(ENTRY_SIZE * 2));
-    stream
-}
+    /// Appends an in-memory [`FileEntry`].
+    ///
+    /// With [`PackOptions::entry_checksums`], the CRC-32 of the data is added to
+    /// the entry metadata.
+    pub fn append_entry(&mut self, entry: &FileEntry) -> Result<(), Error> {
+        if let Some(link) = &entry.link {
+            match link {
+                Link::Symbolic(target) => self.append_symlink(&entry.name, target),
+                Link::Hard(target) => self.append_hard_link(&entry.name, target),
+            }
+        } else if entry.is_dir {
+            self.append_dir_with_metadata(&entry.name, &entry.metadata)
+        } else {
+            let mut metadata = entry.metadata.clone();
+            if self.options.entry_checksums {
+                metadata.crc32 = Some(crc32(&entry.data));
+            }
+            self.append_reader_with_metadata(
+                &entry.name,
+                entry.data.len() as u64,
+                entry.data.as_slice(),
+                &metadata,
+            )
+        }
+    }
 
-pub fn pack_directory(root: &Path, path: &Path) -> Vec<u8> {
-    let mut stream = Vec::new();
+    /// Writes the two empty blocks marking the end of the archive, flushes the
+    /// sink and returns it.
+    pub fn finish(self) -> Result<W, Error> {
+        Ok(self.finish_with_index()?.0)
+    }
 
-    let rel = path.strip_prefix(root).expect("path must start with root");
-    let rel_str = rel.to_string_lossy().replace('\\', "/");
+    /// Like [`finish`](Self::finish), but also returns the position of every
+    /// entry in the archive, in the order they were written.
+    pub fn finish_with_index(mut self) -> Result<(W, Vec<IndexEntry>), Error> {
+        self.write_bytes(&ZERO_BLOCK)?;
+        self.write_bytes(&ZERO_BLOCK)?;
+        self.writer.flush()?;
+        Ok((self.writer, self.index))
+    }
 
-    let header = Header::from_values(rel_str, 0, EntryType::Directory);
+    /// Appends the directory at `path` and its contents.
+    fn append_dir_tree(
+        &mut self,
+        name: &str,
+        path: &Path,
+        fs_metadata: &fs::Metadata,
+        walk: &mut Walk,
+    ) -> Result<(), Error> {
+        let metadata = Metadata::from_fs(fs_metadata);
+        let pending = self.pending_dirs.len();
+        if self.options.filter.is_included(walk.relative(name)) {
+            self.append_dir_with_metadata(name, &metadata)?;
+        } else {
+            // Its contents may still match, e.g. `src/**/*.rs` for `src`
+            self.pending_dirs.push((name.to_string(), metadata));
+        }
+
+        if walk.depth < self.options.max_depth.unwrap_or(usize::MAX) {
+            if self.options.follow_links {
+                walk.ancestors.push(fs::canonicalize(path)?);
+            }
+            if self.options.ignore_files {
+                walk.ignore.push(path, walk.depth)?;
+            }
+            walk.depth += 1;
+
+            for entry in fs::read_dir(path)? {
+                let entry = entry?;
+                let entry_path = entry.path();
+                let entry_name = child_name(name, &entry_path)?;
+                self.append_path(&entry_name, &entry_path, walk)?;
+            }
+
+            walk.depth -= 1;
+            if self.options.ignore_files {
+                walk.ignore.pop();
+            }
+            if self.options.follow_links {
+                walk.ancestors.pop();
+            }
+        }
 
-    stream.extend(header.to_bytes());
+        // Nothing inside was packed, so the directory is left out
+        self.pending_dirs.truncate(pending);
+        Ok(())
+    }
 
-    for entry in fs::read_dir(path).expect("Cannot read directory") {
-        let entry = entry.expect("Cannot read directory entry");
-        let entry_path = entry.path();
+    /// Appends a directory entry found on disk, storing or following symbolic
+    /// links, unless the options leave it out.
+    fn append_path(&mut self, name: &str, path: &Path, walk: &mut Walk) -> Result<(), Error> {
+        let relative = walk.relative(name);
+        if (self.options.skip_hidden && is_hidden(relative))
+            || self.options.filter.is_excluded(relative)
+        {
+            return Ok(());
+        }
+        let mut fs_metadata = fs::symlink_metadata(path)?;
+
+        if fs_metadata.file_type().is_symlink() {
+            let followed = if self.options.follow_links {
+                fs::metadata(path).ok()
+            } else {
+                None
+            };
+            match followed {
+                Some(target)
+                    if !(target.is_dir() && walk.ancestors.contains(&fs::canonicalize(path)?)) =>
+                {
+                    fs_metadata = target
+                }
+                _ if self.skips(relative, false, walk) => return Ok(()),
+                _ => return self.append_symlink(name, &link_target(path)?),
+            }
+        }
 
-        if entry.file_type().unwrap().is_dir() {
-            stream.extend(pack_directory(root, &entry_path));
+        if self.skips(relative, fs_metadata.is_dir(), walk) {
+            Ok(())
+        } else if fs_metadata.is_dir() {
+            self.append_dir_tree(name, path, &fs_metadata, walk)
         } else {
-            stream.extend(pack_file(root, &entry_path));
+            self.append_fs_file(name, path, &fs_metadata)
+        }
+    }
+
+    /// Returns `true` if an ignore file rule or the include patterns leave out
+    /// the entry. Directories are entered even if no include pattern matches them.
+    fn skips(&self, relative: &str, is_dir: bool, walk: &Walk) -> bool {
+        walk.ignore.is_ignored(relative, is_dir)
+            || (!is_dir && !self.options.filter.is_included(relative))
+    }
+
+    /// Appends the file at `path`, or a hard link if the same file was already packed.
+    fn append_fs_file(
+        &mut self,
+        name: &str,
+        path: &Path,
+        fs_metadata: &fs::Metadata,
+    ) -> Result<(), Error> {
+        if let Some(key) = hard_link_key(fs_metadata) {
+            if let Some(target) = self.hard_links.get(&key).cloned() {
+                return self.append_hard_link(name, &target);
+            }
+            self.hard_links.insert(key, name.to_string());
+        }
+
+        let mut metadata = Metadata::from_fs(fs_metadata);
+        if self.options.entry_checksums {
+            metadata.crc32 = Some(file_crc32(path)?);
         }
+
+        let file = File::open(path)?;
+        self.append_reader_with_metadata(name, fs_metadata.len(), file, &metadata)
     }
 
-    stream
+    /// Writes a link entry whose data is the link target.
+    fn write_link(&mut self, name: &str, entry_type: EntryType, target: &str) -> Result<(), Error> {
+        self.write_header(name, target.len() as u64, entry_type, &Metadata::default())?;
+        self.write_bytes(target.as_bytes())?;
+        self.write_padding(target.len() as u64)
+    }
+
+    /// Writes the header block of an entry, preceded by a metadata record if
+    /// `metadata` is not empty and a long name record if `name` does not fit
+    /// into the header.
+    fn write_header(
+        &mut self,
+        name: &str,
+        size: u64,
+        entry_type: EntryType,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        if !self.pending_dirs.is_empty() {
+            for (dir_name, dir_metadata) in std::mem::take(&mut self.pending_dirs) {
+                self.append_dir_with_metadata(&dir_name, &dir_metadata)?;
+            }
+        }
+
+        let offset = self.position;
+        if !metadata.is_empty() {
+            self.write_record(METADATA_MARKER, EntryType::Metadata, &metadata.to_records())?;
+        }
+        if name.len() > NAME_SIZE {
+            self.write_record(LONG_NAME_MARKER, EntryType::LongName, name.as_bytes())?;
+        }
+
+        let header = Header::from_values(name.to_string(), size as usize, entry_type);
+        self.write_bytes(&header.to_bytes())?;
+        self.index.push(IndexEntry {
+            name: name.to_string(),
+            entry_type,
+            offset,
+            data_offset: self.position,
+            size,
+        });
+        Ok(())
+    }
+
+    /// Writes an auxiliary record describing the following entry.
+    fn write_record(
+        &mut self,
+        marker: &str,
+        entry_type: EntryType,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let header = Header::from_values(marker.to_string(), data.len(), entry_type);
+        self.write_bytes(&header.to_bytes())?;
+        self.write_bytes(data)?;
+        self.write_padding(data.len() as u64)
+    }
+
+    /// Pads `len` bytes of entry data up to a multiple of [`ENTRY_SIZE`].
+    fn write_padding(&mut self, len: u64) -> Result<(), Error> {
+        let rem = (len % ENTRY_SIZE as u64) as usize;
+        if rem != 0 {
+            self.write_bytes(&ZERO_BLOCK[..ENTRY_SIZE - rem])?;
+        }
+        Ok(())
+    }
+
+    /// Writes `bytes` to the sink, keeping track of the position.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
 }
 
-pub fn pack_file(root: &Path, path: &Path) -> Vec<u8> {
-    let mut stream = Vec::new();
-    let rel_str: String;
+/// Computes the CRC-32 of the file at `path`.
+fn file_crc32(path: &Path) -> Result<u32, Error> {
+    let mut file = File::open(path)?;
+    let mut crc = Crc32::new();
+    let mut buffer = [0u8; 8 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(crc.finalize());
+        }
+        crc.update(&buffer[..read]);
+    }
+}
 
-    if root == path {
-        rel_str = path.file_name().unwrap().to_str().unwrap().to_string();
-    } else {
-        let rel = path.strip_prefix(root).expect("path must start with root");
-        rel_str = rel.to_string_lossy().replace('\\', "/");
+/// Reads the target of the symbolic link at `path`.
+fn link_target(path: &Path) -> Result<String, Error> {
+    let target = fs::read_link(path)?;
+    target
+        .to_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::InvalidPath(target.clone()))
+}
+
+/// Identifies files with several hard links, so later names can be stored as links.
+#[cfg(unix)]
+fn hard_link_key(fs_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    (fs_metadata.nlink() > 1).then(|| (fs_metadata.dev(), fs_metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn hard_link_key(_fs_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// State of a directory walk started by [`ArchiveWriter::append_dir_all`].
+struct Walk {
+    /// Length of the name of the walked directory, stripped from entry names
+    /// before they are matched against the options.
+    root_len: usize,
+    /// The number of components between the walked directory and the one being packed.
+    depth: usize,
+    /// Canonical paths of the directories being packed while following links.
+    ancestors: Vec<PathBuf>,
+    /// Ignore rules of the directories being packed.
+    ignore: IgnoreStack,
+}
+
+impl Walk {
+    fn new(root: &str) -> Self {
+        Walk {
+            root_len: root.len(),
+            depth: 0,
+            ancestors: Vec::new(),
+            ignore: IgnoreStack::default(),
+        }
     }
 
-    let header = Header::from_values(
-        rel_str,
-        path.metadata().unwrap().len() as usize,
-        EntryType::File,
-    );
+    /// Returns the name of an entry relative to the walked directory.
+    fn relative<'a>(&self, name: &'a str) -> &'a str {
+        name[self.root_len..].trim_start_matches('/')
+    }
+}
 
-    stream.extend(header.to_bytes());
+/// Returns `true` if the last component of `name` starts with a dot.
+fn is_hidden(name: &str) -> bool {
+    name.rsplit('/')
+        .next()
+        .is_some_and(|file| file.starts_with('.'))
+}
+
+/// Returns the archive name of `path` inside the directory entry `parent`.
+fn child_name(parent: &str, path: &Path) -> Result<String, Error> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::InvalidPath(path.to_path_buf()))?;
+
+    if parent.is_empty() {
+        Ok(file_name.to_string())
+    } else {
+        Ok(format!("{}/{}", parent, file_name))
+    }
+}
 
-    let mut data = fs::read(path).expect("Cannot read file");
-    data = file_as_entries(data);
-    stream.extend(data);
+/// Packs the file or directory at `path` into `writer` and returns the writer.
+///
+/// A directory becomes an unnamed root entry followed by its contents, a single
+/// file is stored under its file name.
+pub fn pack_path<W: Write>(path: &Path, writer: W, options: &PackOptions) -> Result<W, Error> {
+    Ok(pack_path_with_index(path, writer, options)?.0)
+}
 
-    stream
+/// Like [`pack_path`], but also returns the position of every entry.
+pub fn pack_path_with_index<W: Write>(
+    path: &Path,
+    writer: W,
+    options: &PackOptions,
+) -> Result<(W, Vec<IndexEntry>), Error> {
+    let mut archive = ArchiveWriter::with_options(writer, options.clone());
+    if path.is_dir() {
+        archive.append_dir_all("", path)?;
+    } else {
+        archive.append_file(&child_name("", path)?, path)?;
+    }
+    archive.finish_with_index()
 }
 
-pub fn file_as_entries(mut file: Vec<u8>) -> Vec<u8> {
-    let rem = file.len() % ENTRY_SIZE;
-    if rem != 0 {
-        let pad = ENTRY_SIZE - rem;
-        file.extend(std::iter::repeat(0).take(pad));
+pub fn pack_from_file_entries(entries: Vec<FileEntry>) -> Result<Vec<u8>, Error> {
+    let mut archive = ArchiveWriter::new(Vec::new());
+    for entry in &entries {
+        archive.append_entry(entry)?;
     }
-    file
+    archive.finish()
 }
 
 #[cfg(test)]
@@ -101,51 +530,396 @@ mod tests {
                 name: "test.txt".to_string(),
                 data: b"hello binary".to_vec(),
                 is_dir: false,
+                ..Default::default()
             },
             FileEntry {
                 name: "folder".to_string(),
                 data: vec![],
                 is_dir: true,
+                ..Default::default()
             },
         ];
 
-        let result = pack_from_file_entries(entries);
-        assert!(result.len() >= ENTRY_SIZE * 4); // 2 headers + 1 file + 2 empty
+        let result = pack_from_file_entries(entries).unwrap();
+        assert_eq!(result.len(), ENTRY_SIZE * 5); // 2 headers + 1 file block + 2 empty
     }
 
     #[test]
-    fn test_pack_file_logic() {
+    fn test_append_file_logic() {
         // Arrange
         let dir = tempdir().expect("Failed to create temp dir");
         let test_file = dir.path().join("unit_test.txt");
         let content = b"test content for unit packing";
         fs::write(&test_file, content).unwrap();
+        let mut archive = ArchiveWriter::new(Vec::new());
 
         // Act
-        let result = pack_file(dir.path(), &test_file);
+        archive.append_file("unit_test.txt", &test_file).unwrap();
+        let result = archive.get_ref();
 
         // Assert
-        assert!(!result.is_empty());
-        assert!(result.len() >= crate::packager::header::ENTRY_SIZE + content.len());
+        // Metadata header + 1 block of records, file header + 1 data block
+        assert_eq!(result.len(), ENTRY_SIZE * 4);
+        assert_eq!(result[ENTRY_SIZE - 1], EntryType::Metadata.as_byte());
+        assert_eq!(
+            &result[ENTRY_SIZE * 3..ENTRY_SIZE * 3 + content.len()],
+            content
+        );
     }
 
     #[test]
-    fn test_pack_directory_recursive_logic() {
+    fn test_append_dir_all_recursive_logic() {
         // Arrange
         let dir = tempdir().expect("Failed to create temp dir");
         let sub = dir.path().join("nested");
         fs::create_dir_all(&sub).unwrap();
         fs::write(dir.path().join("1.txt"), "c1").unwrap();
         fs::write(sub.join("2.txt"), "c2").unwrap();
+        let mut archive = ArchiveWriter::new(Vec::new());
+
+        // Act
+        archive.append_dir_all("", dir.path()).unwrap();
+        let result = archive.finish().unwrap();
+
+        // Assert
+        // Root + nested directory headers, 2 file headers with 1 data block each,
+        // a 2 block metadata record for each of the 4 entries, 2 empty
+        assert_eq!(result.len(), ENTRY_SIZE * 16);
+    }
+
+    #[test]
+    fn test_append_reader_pads_data() {
+        // Arrange
+        let data = vec![7u8; ENTRY_SIZE + 1];
+        let mut archive = ArchiveWriter::new(Vec::new());
+
+        // Act
+        archive
+            .append_reader("data.bin", data.len() as u64, data.as_slice())
+            .unwrap();
+        let result = archive.get_ref();
+
+        // Assert
+        assert_eq!(result.len(), ENTRY_SIZE * 3);
+        assert!(result[ENTRY_SIZE * 2 + 1..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_index_records_entry_positions() {
+        // Arrange
+        let long_name = format!("docs/{}", "n".repeat(NAME_SIZE));
+        let mut archive = ArchiveWriter::new(Vec::new());
+        archive.append_dir("docs").unwrap();
+        archive.append_reader(&long_name, 3, &b"abc"[..]).unwrap();
+        archive.append_symlink("latest", "docs").unwrap();
+
+        // Act
+        let (bytes, index) = archive.finish_with_index().unwrap();
+
+        // Assert
+        let positions: Vec<_> = index
+            .iter()
+            .map(|entry| {
+                (
+                    entry.entry_type,
+                    entry.offset,
+                    entry.data_offset,
+                    entry.size,
+                )
+            })
+            .collect();
+        let block = ENTRY_SIZE as u64;
+        assert_eq!(
+            positions,
+            [
+                (EntryType::Directory, 0, block, 0),
+                (EntryType::File, block, 4 * block, 3),
+                (EntryType::Symlink, 5 * block, 6 * block, 4),
+            ]
+        );
+        assert_eq!(index[1].name, long_name);
+        let file = &index[1];
+        assert_eq!(
+            &bytes[file.data_offset as usize..(file.data_offset + file.size) as usize],
+            b"abc"
+        );
+    }
+
+    #[test]
+    fn test_long_name_adds_record() {
+        // Arrange
+        let name = "dir/".repeat(50) + "file.txt";
+        let mut archive = ArchiveWriter::new(Vec::new());
+
+        // Act
+        archive.append_dir(&name).unwrap();
+        let result = archive.get_ref();
+
+        // Assert
+        // Long name header + 2 blocks of name data + directory header
+        assert_eq!(result.len(), ENTRY_SIZE * 4);
+        assert_eq!(result[ENTRY_SIZE - 1], EntryType::LongName.as_byte());
+        assert_eq!(
+            &result[ENTRY_SIZE..ENTRY_SIZE + name.len()],
+            name.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_append_reader_short_data_is_error() {
+        // Arrange
+        let mut archive = ArchiveWriter::new(Vec::new());
+
+        // Act
+        let result = archive.append_reader("short.bin", 10, &b"abc"[..]);
+
+        // Assert
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_append_file_missing_is_io_error() {
+        // Arrange
+        let dir = tempdir().expect("Failed to create temp dir");
+        let missing = dir.path().join("missing.txt");
+        let mut archive = ArchiveWriter::new(Vec::new());
 
         // Act
-        let result = pack_directory(dir.path(), dir.path());
+        let result = archive.append_file("missing.txt", &missing);
 
         // Assert
-        assert!(!result.is_empty());
-        // Should contain entries for 2 files and potentially directories
-        assert!(result.len() > crate::packager::header::ENTRY_SIZE * 2);
+        assert!(matches!(result, Err(Error::Io(_))));
+    }
+
+    #[cfg(unix)]
+    mod links {
+        use super::*;
+        use crate::packager::unpack_to_entries;
+        use std::os::unix::fs::symlink;
+
+        fn pack_dir(path: &Path, follow_links: bool) -> Vec<FileEntry> {
+            let options = PackOptions {
+                follow_links,
+                ..Default::default()
+            };
+            let mut archive = ArchiveWriter::with_options(Vec::new(), options);
+            archive.append_dir_all("", path).unwrap();
+            unpack_to_entries(archive.finish().unwrap()).unwrap()
+        }
+
+        fn find<'a>(entries: &'a [FileEntry], name: &str) -> &'a FileEntry {
+            entries.iter().find(|e| e.name == name).unwrap()
+        }
+
+        #[test]
+        fn test_symlinks_are_stored_by_default() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            fs::write(dir.path().join("real.txt"), "data").unwrap();
+            symlink("real.txt", dir.path().join("link.txt")).unwrap();
+
+            // Act
+            let entries = pack_dir(dir.path(), false);
+
+            // Assert
+            let link = find(&entries, "link.txt");
+            assert_eq!(link.link, Some(Link::Symbolic("real.txt".to_string())));
+            assert!(link.data.is_empty());
+        }
+
+        #[test]
+        fn test_followed_symlinks_are_packed_as_files() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            fs::write(dir.path().join("real.txt"), "data").unwrap();
+            symlink("real.txt", dir.path().join("link.txt")).unwrap();
+            symlink("missing.txt", dir.path().join("dangling")).unwrap();
+
+            // Act
+            let entries = pack_dir(dir.path(), true);
+
+            // Assert
+            let link = find(&entries, "link.txt");
+            assert_eq!(link.link, None);
+            assert_eq!(link.data, b"data");
+            let dangling = find(&entries, "dangling");
+            assert_eq!(
+                dangling.link,
+                Some(Link::Symbolic("missing.txt".to_string()))
+            );
+        }
 
-        fs::remove_dir_all(dir).ok();
+        #[test]
+        fn test_symlink_cycle_is_stored_as_link() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            let nested = dir.path().join("nested");
+            fs::create_dir(&nested).unwrap();
+            fs::write(nested.join("file.txt"), "x").unwrap();
+            symlink("..", nested.join("parent")).unwrap();
+
+            // Act
+            let entries = pack_dir(dir.path(), true);
+
+            // Assert
+            assert_eq!(entries.len(), 4);
+            let parent = find(&entries, "nested/parent");
+            assert_eq!(parent.link, Some(Link::Symbolic("..".to_string())));
+        }
+
+        #[test]
+        fn test_hard_links_are_detected() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            fs::write(dir.path().join("a.txt"), "shared").unwrap();
+            fs::hard_link(dir.path().join("a.txt"), dir.path().join("b.txt")).unwrap();
+
+            // Act
+            let entries = pack_dir(dir.path(), false);
+
+            // Assert
+            let files: Vec<_> = entries
+                .iter()
+                .filter(|e| e.link.is_none() && !e.is_dir)
+                .collect();
+            let links: Vec<_> = entries.iter().filter(|e| e.link.is_some()).collect();
+            assert_eq!(files.len(), 1);
+            assert_eq!(files[0].data, b"shared");
+            assert_eq!(links.len(), 1);
+            assert_eq!(links[0].link, Some(Link::Hard(files[0].name.clone())));
+        }
+    }
+
+    mod selection {
+        use super::*;
+        use crate::packager::{unpack_to_entries, PathFilter};
+
+        fn project() -> tempfile::TempDir {
+            let dir = tempdir().expect("Failed to create temp dir");
+            let root = dir.path();
+            for path in ["src/net", "target/debug", ".git", "docs/api"] {
+                fs::create_dir_all(root.join(path)).unwrap();
+            }
+            for (path, content) in [
+                ("src/main.rs", "fn main() {}"),
+                ("src/net/tcp.rs", "// tcp"),
+                ("src/net/notes.txt", "todo"),
+                ("target/debug/app", "binary"),
+                (".git/HEAD", "ref: refs/heads/main"),
+                (".env", "SECRET=1"),
+                ("docs/api/index.html", "<html>"),
+                ("build.log", "ok"),
+            ] {
+                fs::write(root.join(path), content).unwrap();
+            }
+            dir
+        }
+
+        fn pack_names(path: &Path, options: PackOptions) -> Vec<String> {
+            let mut archive = ArchiveWriter::with_options(Vec::new(), options);
+            archive.append_dir_all("", path).unwrap();
+            let mut names: Vec<String> = unpack_to_entries(archive.finish().unwrap())
+                .unwrap()
+                .into_iter()
+                .map(|e| e.name)
+                .collect();
+            names.sort();
+            names
+        }
+
+        #[test]
+        fn test_include_patterns_keep_only_matching_files_and_their_directories() {
+            // Arrange
+            let dir = project();
+            let options = PackOptions {
+                filter: PathFilter::new().include("src/**/*.rs"),
+                ..Default::default()
+            };
+
+            // Act
+            let names = pack_names(dir.path(), options);
+
+            // Assert
+            assert_eq!(
+                names,
+                vec!["", "src", "src/main.rs", "src/net", "src/net/tcp.rs"]
+            );
+        }
+
+        #[test]
+        fn test_exclude_patterns_skip_whole_directories() {
+            // Arrange
+            let dir = project();
+            let options = PackOptions {
+                filter: PathFilter::new().exclude("target").exclude("*.log"),
+                ..Default::default()
+            };
+
+            // Act
+            let names = pack_names(dir.path(), options);
+
+            // Assert
+            assert!(names.iter().all(|n| !n.starts_with("target")));
+            assert!(!names.contains(&"build.log".to_string()));
+            assert!(names.contains(&"src/net/notes.txt".to_string()));
+        }
+
+        #[test]
+        fn test_gitignore_and_ignore_files_are_honoured() {
+            // Arrange
+            let dir = project();
+            fs::write(dir.path().join(".gitignore"), "/target/\n*.log\n").unwrap();
+            fs::write(dir.path().join("src/.ignore"), "*.txt\n").unwrap();
+            let options = PackOptions {
+                ignore_files: true,
+                ..Default::default()
+            };
+
+            // Act
+            let names = pack_names(dir.path(), options);
+
+            // Assert
+            assert!(names.iter().all(|n| !n.starts_with("target")));
+            assert!(names.iter().all(|n| !n.starts_with(".git/")));
+            assert!(!names.contains(&"build.log".to_string()));
+            assert!(!names.contains(&"src/net/notes.txt".to_string()));
+            assert!(names.contains(&"src/net/tcp.rs".to_string()));
+            assert!(names.contains(&".gitignore".to_string()));
+        }
+
+        #[test]
+        fn test_max_depth_limits_recursion() {
+            // Arrange
+            let dir = project();
+            let options = PackOptions {
+                max_depth: Some(1),
+                ..Default::default()
+            };
+
+            // Act
+            let names = pack_names(dir.path(), options);
+
+            // Assert
+            assert!(names.contains(&"src".to_string()));
+            assert!(names.contains(&"build.log".to_string()));
+            assert!(names.iter().all(|n| n.matches('/').count() == 0));
+        }
+
+        #[test]
+        fn test_hidden_files_are_skipped() {
+            // Arrange
+            let dir = project();
+            let options = PackOptions {
+                skip_hidden: true,
+                ..Default::default()
+            };
+
+            // Act
+            let names = pack_names(dir.path(), options);
+
+            // Assert
+            assert!(names.iter().all(|n| !n.starts_with('.')));
+            assert!(names.contains(&"src/main.rs".to_string()));
+        }
     }
 }
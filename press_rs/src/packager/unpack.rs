@@ -1,120 +1,721 @@
-use std::{fs, path::Path};
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
 
-use crate::packager::FileEntry;
+use crate::checksum::Crc32;
+use crate::packager::{FileEntry, Link, Metadata, PathFilter, UnpackOptions};
+use crate::Error;
 
 use super::header::{EntryType, Header, ENTRY_SIZE};
 
-pub fn unpack_to_file_entries(archive: Vec<u8>) -> Vec<FileEntry> {
-    let mut entries = Vec::new();
-    let mut i = 0;
-    let mut block_was_empty = false;
+/// Upper bound for the name carried by a long name record, to bound memory
+/// usage on malformed archives.
+const MAX_LONG_NAME_SIZE: u64 = 64 * 1024;
 
-    while i + ENTRY_SIZE <= archive.len() {
-        let block = &archive[i..i + ENTRY_SIZE];
+/// Upper bound for the data of a metadata record.
+const MAX_METADATA_SIZE: u64 = 64 * 1024;
 
-        if block_is_empty(block) {
-            if block_was_empty {
-                break;
-            }
-            block_was_empty = true;
-            i += ENTRY_SIZE;
-            continue;
+/// Upper bound for the buffer reserved up front for the data of an entry.
+const MAX_PREALLOCATION: u64 = 64 * 1024;
+
+/// Streaming archive parser that yields entries lazily from any [`Read`] source.
+///
+/// Entries are read one at a time via [`entries`](ArchiveReader::entries). Each
+/// [`ArchiveEntry`] is a bounded reader over its own data; whatever the caller
+/// does not read, including the block padding, is skipped when the iterator
+/// moves on, so the archive never has to be held in memory.
+///
+/// Entries must be processed in order: once the iterator has advanced, reading
+/// from a previous entry fails.
+///
+/// If the entry [`Metadata`] carries a CRC-32, it is verified once the entry
+/// data has been read to the end; a mismatch fails the last read with
+/// [`Error::ChecksumMismatch`] naming the entry.
+///
+/// # Examples
+///
+/// ```
+/// use press_rs::packager::{pack_entries, ArchiveReader, FileEntry};
+/// use std::io::Read;
+///
+/// let archive = pack_entries(vec![FileEntry {
+///     name: "hello.txt".to_string(),
+///     data: b"Hello World".to_vec(),
+///     ..Default::default()
+/// }])
+/// .unwrap();
+///
+/// let mut reader = ArchiveReader::new(archive.as_slice());
+/// for entry in reader.entries() {
+///     let mut entry = entry.unwrap();
+///     let mut content = String::new();
+///     entry.read_to_string(&mut content).unwrap();
+///     assert_eq!(entry.name(), "hello.txt");
+///     assert_eq!(content, "Hello World");
+/// }
+/// ```
+pub struct ArchiveReader<R>
+where
+    R: Read,
+{
+    state: RefCell<ReaderState<R>>,
+}
+
+struct ReaderState<R> {
+    reader: R,
+    /// Index of the entry whose data is currently being read.
+    current: usize,
+    /// Unread data bytes of the current entry.
+    remaining: u64,
+    /// Padding bytes following the data of the current entry.
+    padding: u64,
+    finished: bool,
+}
+
+/// An entry header with the auxiliary records preceding it resolved.
+struct ParsedEntry {
+    header: Header,
+    entry_type: EntryType,
+    name: String,
+    metadata: Metadata,
+    link_target: Option<String>,
+}
+
+impl<R> ArchiveReader<R>
+where
+    R: Read,
+{
+    /// Creates a reader that parses the archive from `reader`.
+    pub fn new(reader: R) -> Self {
+        Self {
+            state: RefCell::new(ReaderState {
+                reader,
+                current: 0,
+                remaining: 0,
+                padding: 0,
+                finished: false,
+            }),
         }
-        block_was_empty = false;
+    }
 
-        let mut bin_header = [0u8; ENTRY_SIZE];
-        bin_header.copy_from_slice(block);
-        let header = Header::from_bytes(bin_header);
-        i += ENTRY_SIZE;
+    /// Returns an iterator over the entries of the archive.
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        Entries { archive: self }
+    }
 
-        let name = header.get_name();
-        let entry_type = EntryType::new(header.typeflag[0]);
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.state.into_inner().reader
+    }
+}
 
-        match entry_type {
-            EntryType::Directory => {
-                entries.push(FileEntry {
-                    name,
-                    data: vec![],
-                    is_dir: true,
-                });
+impl<R> ReaderState<R>
+where
+    R: Read,
+{
+    /// Skips the unread data and padding of the current entry.
+    fn skip_current(&mut self) -> Result<(), Error> {
+        let skipped = io::copy(
+            &mut (&mut self.reader).take(self.remaining),
+            &mut io::sink(),
+        )?;
+        if skipped != self.remaining {
+            return Err(Error::TruncatedArchive);
+        }
+        self.remaining = 0;
+
+        // Archives that end right after the last entry's data are accepted
+        let skipped = io::copy(&mut (&mut self.reader).take(self.padding), &mut io::sink())?;
+        if skipped != self.padding {
+            self.finished = true;
+        }
+        self.padding = 0;
+        Ok(())
+    }
+
+    /// Reads the next header block, returning `None` at the end of the archive.
+    fn read_header(&mut self) -> Result<Option<Header>, Error> {
+        let mut block_was_empty = false;
+
+        loop {
+            let mut block = [0u8; ENTRY_SIZE];
+            let filled = read_full(&mut self.reader, &mut block)?;
+
+            if block_is_empty(&block[..filled]) {
+                // A trailing partial block of zeros is treated like the end marker
+                if filled < ENTRY_SIZE || block_was_empty {
+                    return Ok(None);
+                }
+                block_was_empty = true;
+                continue;
+            }
+            if filled < ENTRY_SIZE {
+                return Err(Error::TruncatedArchive);
             }
-            EntryType::File => {
-                let size = header.get_size();
-                let file_data = archive[i..i + size].to_vec();
 
-                entries.push(FileEntry {
-                    name,
-                    data: file_data,
-                    is_dir: false,
-                });
+            return Ok(Some(Header::from_bytes(block)));
+        }
+    }
 
-                i += size;
-                if size % ENTRY_SIZE != 0 {
-                    i += ENTRY_SIZE - (size % ENTRY_SIZE);
+    /// Reads the next entry, resolving any long name or metadata records preceding it.
+    fn next_entry(&mut self) -> Result<Option<ParsedEntry>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        self.skip_current()?;
+
+        let mut long_name = None;
+        let mut metadata = None;
+        loop {
+            let header = match self.read_header_unless_finished()? {
+                Some(header) => header,
+                None if long_name.is_some() || metadata.is_some() => {
+                    return Err(Error::BadHeader(
+                        "auxiliary record is not followed by an entry".to_string(),
+                    ))
+                }
+                None => return Ok(None),
+            };
+            let entry_type = EntryType::new(header.typeflag[0])?;
+
+            match entry_type {
+                EntryType::LongName => {
+                    long_name = Some(self.read_long_name(&header)?);
+                    continue;
                 }
+                EntryType::Metadata => {
+                    let records = self.read_record(&header, MAX_METADATA_SIZE, "metadata")?;
+                    metadata = Some(Metadata::from_records(&records)?);
+                    continue;
+                }
+                _ => {}
             }
+
+            self.current += 1;
+            let mut link_target = None;
+            match entry_type {
+                EntryType::File => {
+                    let size = header.get_size() as u64;
+                    self.remaining = size;
+                    self.padding = padded_size(size)? - size;
+                }
+                EntryType::Symlink | EntryType::HardLink => {
+                    let target = self.read_record(&header, MAX_LONG_NAME_SIZE, "link target")?;
+                    link_target = Some(String::from_utf8_lossy(&target).into_owned());
+                }
+                _ => {}
+            }
+
+            let name = long_name.unwrap_or_else(|| header.get_name());
+            return Ok(Some(ParsedEntry {
+                header,
+                entry_type,
+                name,
+                metadata: metadata.unwrap_or_default(),
+                link_target,
+            }));
         }
     }
-    entries
+
+    fn read_header_unless_finished(&mut self) -> Result<Option<Header>, Error> {
+        if self.finished {
+            return Ok(None);
+        }
+        let header = self.read_header()?;
+        if header.is_none() {
+            self.finished = true;
+        }
+        Ok(header)
+    }
+
+    /// Reads the data of a long name record and skips its padding.
+    fn read_long_name(&mut self, header: &Header) -> Result<String, Error> {
+        let mut name = self.read_record(header, MAX_LONG_NAME_SIZE, "long name")?;
+
+        // GNU tar terminates the name with NUL bytes
+        while name.last() == Some(&0) {
+            name.pop();
+        }
+        Ok(String::from_utf8_lossy(&name).into_owned())
+    }
+
+    /// Reads the data of an auxiliary record of at most `limit` bytes and skips its padding.
+    fn read_record(&mut self, header: &Header, limit: u64, kind: &str) -> Result<Vec<u8>, Error> {
+        let size = header.get_size() as u64;
+        if size > limit {
+            return Err(Error::BadHeader(format!(
+                "{} record of {} bytes exceeds the limit of {} bytes",
+                kind, size, limit
+            )));
+        }
+
+        let mut data = Vec::with_capacity(size as usize);
+        (&mut self.reader).take(size).read_to_end(&mut data)?;
+        if data.len() as u64 != size {
+            return Err(Error::TruncatedArchive);
+        }
+        self.padding = padded_size(size)? - size;
+        self.skip_current()?;
+        Ok(data)
+    }
 }
 
-/// Unpacks the archive and creates directories/files on the specified path.
-pub fn unpack_with_dir_creation(archive: Vec<u8>, path: impl AsRef<Path>) {
-    let mut i = 0;
-    let mut block_was_empty = false;
-
-    while i + ENTRY_SIZE <= archive.len() {
-        if block_is_empty(&archive[i..i + ENTRY_SIZE]) && block_was_empty {
-            println!("Finished unpacking");
-            return;
-        } else if block_is_empty(&archive[i..i + ENTRY_SIZE]) {
-            block_was_empty = true;
-            i += ENTRY_SIZE;
+/// Iterator over the entries of an [`ArchiveReader`].
+pub struct Entries<'a, R>
+where
+    R: Read,
+{
+    archive: &'a ArchiveReader<R>,
+}
+
+impl<'a, R> Iterator for Entries<'a, R>
+where
+    R: Read,
+{
+    type Item = Result<ArchiveEntry<'a, R>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut state = self.archive.state.borrow_mut();
+        match state.next_entry() {
+            Ok(Some(parsed)) => Some(Ok(ArchiveEntry {
+                crc: parsed.metadata.crc32.map(|_| Crc32::new()),
+                header: parsed.header,
+                name: parsed.name,
+                entry_type: parsed.entry_type,
+                metadata: parsed.metadata,
+                link_target: parsed.link_target,
+                size: state.remaining,
+                index: state.current,
+                archive: self.archive,
+            })),
+            Ok(None) => None,
+            Err(e) => {
+                state.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A single entry of an archive read by [`ArchiveReader`].
+///
+/// Implements [`Read`] over the entry data, bounded to the size in its header.
+pub struct ArchiveEntry<'a, R>
+where
+    R: Read,
+{
+    header: Header,
+    name: String,
+    entry_type: EntryType,
+    metadata: Metadata,
+    link_target: Option<String>,
+    size: u64,
+    index: usize,
+    /// Running checksum of the data read so far, if the entry has one to verify.
+    crc: Option<Crc32>,
+    archive: &'a ArchiveReader<R>,
+}
+
+impl<R> ArchiveEntry<'_, R>
+where
+    R: Read,
+{
+    /// Returns the parsed header block of the entry.
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Returns the path of the entry within the archive.
+    ///
+    /// Unlike [`Header::get_name`], this is the full name even when it was
+    /// stored in a long name record.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the entry.
+    pub fn entry_type(&self) -> EntryType {
+        self.entry_type
+    }
+
+    /// Returns the metadata recorded for the entry, empty if the archive has none.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Returns the target of a symbolic or hard link entry.
+    pub fn link_target(&self) -> Option<&str> {
+        self.link_target.as_deref()
+    }
+
+    /// Returns the size of the entry data in bytes (always 0 for directories and links).
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Compares the checksum of the data read so far with the one in the metadata.
+    fn verify_checksum(&self) -> Result<(), Error> {
+        match (&self.crc, self.metadata.crc32) {
+            (Some(crc), Some(expected)) if crc.finalize() != expected => {
+                Err(Error::ChecksumMismatch {
+                    entry: Some(self.name.clone()),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R> Read for ArchiveEntry<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.archive.state.borrow_mut();
+        if state.current != self.index {
+            return Err(io::Error::other(
+                "archive entry was read after the reader moved past it",
+            ));
+        }
+
+        let max = buf
+            .len()
+            .min(state.remaining.min(usize::MAX as u64) as usize);
+        if max == 0 {
+            self.verify_checksum()?;
+            return Ok(0);
+        }
+
+        let read = state.reader.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(Error::TruncatedArchive.into());
+        }
+        state.remaining -= read as u64;
+
+        if let Some(crc) = &mut self.crc {
+            crc.update(&buf[..read]);
+        }
+        if state.remaining == 0 {
+            self.verify_checksum()?;
+        }
+        Ok(read)
+    }
+}
+
+/// Reads until `buf` is full or the reader is exhausted, returning the bytes read.
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+pub fn unpack_to_file_entries(archive: Vec<u8>) -> Result<Vec<FileEntry>, Error> {
+    unpack_to_filtered_entries(archive, &PathFilter::default())
+}
+
+/// Returns the entries selected by `filter`, skipping the data of the others.
+pub fn unpack_to_filtered_entries(
+    archive: Vec<u8>,
+    filter: &PathFilter,
+) -> Result<Vec<FileEntry>, Error> {
+    let mut reader = ArchiveReader::new(archive.as_slice());
+    let mut entries = Vec::new();
+
+    for entry in reader.entries() {
+        let mut entry = entry?;
+        if !filter.is_match(entry.name()) {
             continue;
-        } else {
-            block_was_empty = false;
         }
+        // The size comes from the untrusted header, so the buffer grows with the data read
+        let mut data = Vec::with_capacity(entry.size().min(MAX_PREALLOCATION) as usize);
+        entry.read_to_end(&mut data)?;
+
+        entries.push(FileEntry {
+            name: entry.name().to_string(),
+            data,
+            is_dir: entry.entry_type() == EntryType::Directory,
+            metadata: entry.metadata().clone(),
+            link: entry_link(&entry),
+        });
+    }
+    Ok(entries)
+}
 
-        let mut bin_header = [0u8; ENTRY_SIZE];
-        bin_header.copy_from_slice(&archive[i..i + ENTRY_SIZE]);
-        let header = Header::from_bytes(bin_header);
-        i += ENTRY_SIZE;
+/// Unpacks the archive and creates directories/files on the specified path.
+pub fn unpack_with_dir_creation(
+    archive: impl Read,
+    path: impl AsRef<Path>,
+    options: &UnpackOptions,
+) -> Result<(), Error> {
+    let root = path.as_ref();
+    let mut reader = ArchiveReader::new(archive);
+    // Directory metadata is applied last, as extracting their contents changes the mtime
+    let mut directories = Vec::new();
+    // Paths that the targets of extracted symbolic links lead through
+    let mut link_paths = HashSet::new();
 
-        let name = header.get_name();
-        let target_path = path.as_ref().join(name);
+    for entry in reader.entries() {
+        let mut entry = entry?;
+        if !options.filter.is_match(entry.name()) {
+            continue;
+        }
+        let Some(target_path) = skip_unsafe(extraction_path(root, entry.name()), options)? else {
+            continue;
+        };
+        remove_link_or_file(&target_path)?;
 
-        match EntryType::new(header.typeflag[0]) {
+        match entry.entry_type() {
             EntryType::Directory => {
-                fs::create_dir_all(target_path).expect("Cannot create directory");
+                fs::create_dir_all(&target_path)?;
+                if !entry.metadata().is_empty() {
+                    directories.push((target_path, entry.metadata().clone()));
+                }
             }
             EntryType::File => {
-                let size = header.get_size();
+                if let Some(parent) = target_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
 
+                let mut file = File::create(&target_path)?;
+                io::copy(&mut entry, &mut file)?;
+                drop(file);
+                entry.metadata().apply(&target_path, options.ownership)?;
+            }
+            EntryType::Symlink => {
+                let target = entry.link_target().unwrap_or_default();
+                let checked = check_symlink_target(entry.name(), target).and_then(|()| {
+                    check_symlink_chain(root, entry.name(), target, &mut link_paths)
+                });
+                if skip_unsafe(checked, options)?.is_none() {
+                    continue;
+                }
                 if let Some(parent) = target_path.parent() {
-                    fs::create_dir_all(parent).ok();
+                    fs::create_dir_all(parent)?;
+                }
+                create_symlink(target, &target_path)?;
+            }
+            EntryType::HardLink => {
+                let target = entry.link_target().unwrap_or_default();
+                // The target was not extracted, so there is nothing to link to
+                if !options.filter.is_match(target) {
+                    continue;
                 }
+                let Some(source_path) = skip_unsafe(extraction_path(root, target), options)? else {
+                    continue;
+                };
+                if let Some(parent) = target_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::hard_link(source_path, &target_path)?;
+            }
+            EntryType::LongName | EntryType::Metadata => {
+                unreachable!("auxiliary records are resolved by the reader")
+            }
+        }
+    }
 
-                let file = archive[i..i + size].to_vec();
-                fs::write(target_path, file).expect("Cannot write file");
+    // Children come after their parents, so read-only parents are restored last
+    for (target_path, metadata) in directories.iter().rev() {
+        metadata.apply(target_path, options.ownership)?;
+    }
 
-                i += size;
+    println!("Finished unpacking");
+    Ok(())
+}
 
-                if size % ENTRY_SIZE != 0 {
-                    i += ENTRY_SIZE - (size % ENTRY_SIZE);
-                }
+fn entry_link<R: Read>(entry: &ArchiveEntry<'_, R>) -> Option<Link> {
+    let target = entry.link_target()?.to_string();
+    match entry.entry_type() {
+        EntryType::Symlink => Some(Link::Symbolic(target)),
+        EntryType::HardLink => Some(Link::Hard(target)),
+        _ => None,
+    }
+}
+
+/// Turns an [`Error::UnsafePath`] into `None` if the options ask to skip unsafe entries.
+fn skip_unsafe<T>(result: Result<T, Error>, options: &UnpackOptions) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::UnsafePath(_)) if options.skip_unsafe_paths => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Resolves the path an entry is extracted to, rejecting names that escape
+/// `root` or lead through a symbolic link created by an earlier entry.
+fn extraction_path(root: &Path, name: &str) -> Result<PathBuf, Error> {
+    let relative_path = safe_relative_path(name)?;
+
+    let mut current = root.to_path_buf();
+    for component in relative_path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+    {
+        current.push(component);
+        match fs::symlink_metadata(&current) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(Error::UnsafePath(name.to_string()))
             }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+
+    Ok(root.join(relative_path))
+}
+
+/// Checks that a symbolic link named `name` pointing to `target` stays inside
+/// the extraction directory.
+fn check_symlink_target(name: &str, target: &str) -> Result<(), Error> {
+    let unsafe_link = || Error::UnsafePath(format!("{} -> {}", name, target));
+    if target.is_empty() || target.starts_with(['/', '\\']) {
+        return Err(unsafe_link());
+    }
+
+    // The link is resolved relative to the directory containing it
+    let mut depth = safe_relative_path(name)?
+        .components()
+        .count()
+        .saturating_sub(1);
+    for (i, component) in target.split(['/', '\\']).enumerate() {
+        match component {
+            "" | "." => {}
+            ".." => depth = depth.checked_sub(1).ok_or_else(unsafe_link)?,
+            _ if i == 0 && has_drive_prefix(component) => return Err(unsafe_link()),
+            _ => depth += 1,
         }
     }
+    Ok(())
+}
+
+/// Checks that the target of a symbolic link named `name` does not lead through
+/// another symbolic link, which would resolve the `..` components after it
+/// relative to the other link's target instead of the directory it lies in.
+///
+/// The paths the target leads through are recorded in `link_paths`, so that a
+/// later entry cannot turn one of them into a symbolic link.
+fn check_symlink_chain(
+    root: &Path,
+    name: &str,
+    target: &str,
+    link_paths: &mut HashSet<PathBuf>,
+) -> Result<(), Error> {
+    let unsafe_link = || Error::UnsafePath(format!("{} -> {}", name, target));
+    let link_path = root.join(safe_relative_path(name)?);
+    if link_paths.contains(&link_path) {
+        return Err(unsafe_link());
+    }
+
+    let components: Vec<&str> = target
+        .split(['/', '\\'])
+        .filter(|component| !matches!(*component, "" | "."))
+        .collect();
+    let mut current = link_path.parent().unwrap_or(root).to_path_buf();
+    // The last component is not resolved further, so it may be a link itself
+    for component in &components[..components.len().saturating_sub(1)] {
+        if *component == ".." {
+            current.pop();
+        } else {
+            current.push(component);
+        }
+        if fs::symlink_metadata(&current).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(unsafe_link());
+        }
+        link_paths.insert(current.clone());
+    }
+    Ok(())
+}
+
+/// Removes a file or link at `path` so an entry can replace it without writing
+/// through an existing link.
+fn remove_link_or_file(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.is_dir() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(windows)]
+fn create_symlink(target: &str, path: &Path) -> io::Result<()> {
+    let resolved = path.parent().unwrap_or(path).join(target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create_symlink(_target: &str, _path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symbolic links are not supported on this platform",
+    ))
+}
+
+/// Normalizes an entry name into a path that stays inside the extraction directory.
+///
+/// Both `/` and `\` are accepted as separators and `.` components are dropped.
+/// Absolute paths, drive prefixes (`C:`) and `..` components are rejected with
+/// [`Error::UnsafePath`].
+pub fn safe_relative_path(name: &str) -> Result<PathBuf, Error> {
+    let unsafe_path = || Error::UnsafePath(name.to_string());
+
+    if name.starts_with(['/', '\\']) {
+        return Err(unsafe_path());
+    }
+
+    let mut path = PathBuf::new();
+    for (i, component) in name.split(['/', '\\']).enumerate() {
+        match component {
+            "" | "." => {}
+            ".." => return Err(unsafe_path()),
+            _ if i == 0 && has_drive_prefix(component) => return Err(unsafe_path()),
+            _ => path.push(component),
+        }
+    }
+    Ok(path)
+}
+
+fn has_drive_prefix(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
 }
 
 fn block_is_empty(block: &[u8]) -> bool {
     block.iter().all(|&x| x == 0)
 }
 
+/// Returns the number of bytes `size` bytes of data occupy, including block padding.
+///
+/// Fails with [`Error::BadHeader`] if the padded size does not fit into a `u64`.
+fn padded_size(size: u64) -> Result<u64, Error> {
+    size.div_ceil(ENTRY_SIZE as u64)
+        .checked_mul(ENTRY_SIZE as u64)
+        .ok_or_else(|| Error::BadHeader(format!("entry size {} is too large", size)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::packager::header::{EntryType, Header};
+    use crate::packager::header::{EntryType, Header, NAME_SIZE};
     use std::fs;
     use tempfile::tempdir;
 
@@ -135,7 +736,7 @@ mod tests {
             data.extend_from_slice(content);
 
             // Act
-            let entries = unpack_to_file_entries(data);
+            let entries = unpack_to_file_entries(data).unwrap();
 
             // Assert
             assert_eq!(entries.len(), 1);
@@ -149,7 +750,7 @@ mod tests {
             // Arrange
             let data = vec![];
             // Act
-            let entries = unpack_to_file_entries(data);
+            let entries = unpack_to_file_entries(data).unwrap();
             // Assert
             assert_eq!(entries.len(), 0);
         }
@@ -159,10 +760,51 @@ mod tests {
             // Arrange
             let corrupted = vec![0u8; ENTRY_SIZE - 1];
             // Act
-            let entries = unpack_to_file_entries(corrupted);
+            let entries = unpack_to_file_entries(corrupted).unwrap();
             // Assert
             assert_eq!(entries.len(), 0);
         }
+
+        #[test]
+        fn test_unpack_to_entries_truncated_file_data() {
+            // Arrange
+            let header = Header::from_values("big.bin".to_string(), 1000, EntryType::File);
+            let mut data = header.to_bytes().to_vec();
+            data.extend_from_slice(&[1u8; 10]);
+
+            // Act
+            let result = unpack_to_file_entries(data);
+
+            // Assert
+            assert!(matches!(result, Err(Error::TruncatedArchive)));
+        }
+
+        #[test]
+        fn test_unpack_to_entries_huge_declared_size_is_truncated() {
+            // Arrange
+            let header = Header::from_values("huge.bin".to_string(), 1 << 40, EntryType::File);
+            let mut data = header.to_bytes().to_vec();
+            data.extend_from_slice(&[1u8; 10]);
+
+            // Act
+            let result = unpack_to_file_entries(data);
+
+            // Assert
+            assert!(matches!(result, Err(Error::TruncatedArchive)));
+        }
+
+        #[test]
+        fn test_unpack_to_entries_unknown_typeflag() {
+            // Arrange
+            let mut bytes = Header::from_values("x".to_string(), 0, EntryType::File).to_bytes();
+            bytes[ENTRY_SIZE - 1] = b'?';
+
+            // Act
+            let result = unpack_to_file_entries(bytes.to_vec());
+
+            // Assert
+            assert!(matches!(result, Err(Error::BadHeader(_))));
+        }
     }
 
     mod disk_unpacking {
@@ -185,7 +827,8 @@ mod tests {
             let dir = tempdir().expect("Failed to create temp dir");
 
             // Act
-            unpack_with_dir_creation(data, dir.path());
+            unpack_with_dir_creation(data.as_slice(), dir.path(), &UnpackOptions::default())
+                .unwrap();
 
             // Assert
             let target_file = dir.path().join(file_name);
@@ -196,11 +839,12 @@ mod tests {
         #[test]
         fn test_unpack_empty_buffer() {
             // Arrange
-            let empty_data = vec![];
+            let empty_data: Vec<u8> = vec![];
             let dir = tempdir().expect("Failed to create temp dir");
 
             // Act
-            unpack_with_dir_creation(empty_data, dir.path());
+            unpack_with_dir_creation(empty_data.as_slice(), dir.path(), &UnpackOptions::default())
+                .unwrap();
 
             // Assert
             let files_count = fs::read_dir(dir.path()).unwrap().count();
@@ -215,7 +859,12 @@ mod tests {
 
             // Act & Assert
             // Should not panic due to the boundary check
-            unpack_with_dir_creation(corrupted_data, dir.path());
+            unpack_with_dir_creation(
+                corrupted_data.as_slice(),
+                dir.path(),
+                &UnpackOptions::default(),
+            )
+            .unwrap();
 
             let files_count = fs::read_dir(dir.path()).unwrap().count();
             assert_eq!(
@@ -223,5 +872,646 @@ mod tests {
                 "No files should be created from corrupted data"
             );
         }
+
+        #[test]
+        fn test_maximum_declared_size_is_bad_header() {
+            // Arrange
+            let header = Header::from_values("max.bin".to_string(), usize::MAX, EntryType::File);
+            let data = header.to_bytes().to_vec();
+            let dir = tempdir().expect("Failed to create temp dir");
+
+            // Act
+            let listed = crate::packager::list(data.as_slice());
+            let unpacked =
+                unpack_with_dir_creation(data.as_slice(), dir.path(), &UnpackOptions::default());
+
+            // Assert
+            assert!(matches!(listed, Err(Error::BadHeader(_))));
+            assert!(matches!(unpacked, Err(Error::BadHeader(_))));
+        }
+    }
+
+    mod archive_reader {
+        use super::*;
+        use crate::compressor::{LzwDecoder, LzwEncoder};
+        use crate::packager::ArchiveWriter;
+        use std::io::Write;
+
+        fn sample_archive() -> Vec<u8> {
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir("dir").unwrap();
+            archive
+                .append_reader("dir/a.bin", 400, &[1u8; 400][..])
+                .unwrap();
+            archive.append_reader("dir/b.txt", 3, &b"abc"[..]).unwrap();
+            archive.finish().unwrap()
+        }
+
+        #[test]
+        fn test_entries_expose_headers() {
+            // Arrange
+            let archive = sample_archive();
+            let mut reader = ArchiveReader::new(archive.as_slice());
+
+            // Act
+            let entries: Vec<(String, EntryType, u64)> = reader
+                .entries()
+                .map(|e| {
+                    let e = e.unwrap();
+                    (e.name().to_string(), e.entry_type(), e.size())
+                })
+                .collect();
+
+            // Assert
+            assert_eq!(
+                entries,
+                vec![
+                    ("dir".to_string(), EntryType::Directory, 0),
+                    ("dir/a.bin".to_string(), EntryType::File, 400),
+                    ("dir/b.txt".to_string(), EntryType::File, 3),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_partially_read_entry_is_skipped() {
+            // Arrange
+            let archive = sample_archive();
+            let mut reader = ArchiveReader::new(archive.as_slice());
+            let mut entries = reader.entries();
+            entries.next().unwrap().unwrap();
+
+            // Act
+            let mut first = entries.next().unwrap().unwrap();
+            let mut prefix = [0u8; 10];
+            first.read_exact(&mut prefix).unwrap();
+            let mut second = entries.next().unwrap().unwrap();
+            let mut content = String::new();
+            second.read_to_string(&mut content).unwrap();
+
+            // Assert
+            assert_eq!(prefix, [1u8; 10]);
+            assert_eq!(content, "abc");
+            assert!(first.read(&mut prefix).is_err());
+            assert!(entries.next().is_none());
+        }
+
+        #[test]
+        fn test_truncated_entry_data() {
+            // Arrange
+            let archive = sample_archive();
+            let truncated = &archive[..ENTRY_SIZE * 2 + 100];
+            let mut reader = ArchiveReader::new(truncated);
+
+            // Act
+            let result: Result<Vec<_>, Error> = reader.entries().map(|e| e.map(|_| ())).collect();
+
+            // Assert
+            assert!(matches!(result, Err(Error::TruncatedArchive)));
+        }
+
+        #[test]
+        fn test_streams_from_decoder() {
+            // Arrange
+            let mut encoder = LzwEncoder::new(Vec::new());
+            encoder.write_all(&sample_archive()).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let dir = tempdir().expect("Failed to create temp dir");
+
+            // Act
+            unpack_with_dir_creation(
+                LzwDecoder::new(compressed.as_slice()),
+                dir.path(),
+                &UnpackOptions::default(),
+            )
+            .unwrap();
+
+            // Assert
+            assert_eq!(
+                fs::read(dir.path().join("dir/a.bin")).unwrap(),
+                vec![1u8; 400]
+            );
+            assert_eq!(fs::read(dir.path().join("dir/b.txt")).unwrap(), b"abc");
+        }
+    }
+
+    mod path_safety {
+        use super::*;
+
+        fn archive_with_names(names: &[&str]) -> Vec<u8> {
+            let mut archive = crate::packager::ArchiveWriter::new(Vec::new());
+            for name in names {
+                archive.append_reader(name, 4, &b"evil"[..]).unwrap();
+            }
+            archive.finish().unwrap()
+        }
+
+        #[test]
+        fn test_safe_relative_path_normalizes() {
+            assert_eq!(
+                safe_relative_path("./a//b\\c.txt").unwrap(),
+                Path::new("a").join("b").join("c.txt")
+            );
+            assert_eq!(safe_relative_path("").unwrap(), PathBuf::new());
+        }
+
+        #[test]
+        fn test_safe_relative_path_rejects_traversal() {
+            for name in [
+                "../x",
+                "a/../../x",
+                "/etc/passwd",
+                "\\\\server\\share",
+                "C:\\Windows",
+                "c:relative",
+            ] {
+                assert!(
+                    matches!(safe_relative_path(name), Err(Error::UnsafePath(_))),
+                    "{} should be rejected",
+                    name
+                );
+            }
+        }
+
+        #[test]
+        fn test_unpack_rejects_zip_slip() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            let dest = dir.path().join("dest");
+            fs::create_dir_all(&dest).unwrap();
+            let archive = archive_with_names(&["../../escaped.txt"]);
+
+            // Act
+            let result =
+                unpack_with_dir_creation(archive.as_slice(), &dest, &UnpackOptions::default());
+
+            // Assert
+            assert!(matches!(result, Err(Error::UnsafePath(name)) if name == "../../escaped.txt"));
+            assert!(!dir.path().join("escaped.txt").exists());
+        }
+
+        #[test]
+        fn test_unpack_can_skip_unsafe_entries() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            let archive = archive_with_names(&["/abs.txt", "ok.txt", "../up.txt"]);
+            let options = UnpackOptions {
+                skip_unsafe_paths: true,
+                ..Default::default()
+            };
+
+            // Act
+            unpack_with_dir_creation(archive.as_slice(), dir.path(), &options).unwrap();
+
+            // Assert
+            let names: Vec<_> = fs::read_dir(dir.path())
+                .unwrap()
+                .map(|e| e.unwrap().file_name())
+                .collect();
+            assert_eq!(names, vec!["ok.txt"]);
+        }
+    }
+
+    mod long_names {
+        use super::*;
+        use crate::packager::ArchiveWriter;
+
+        #[test]
+        fn test_long_names_round_trip_in_memory() {
+            // Arrange
+            let long_name = format!("{}/файл.txt", "каталог".repeat(30));
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_reader(&long_name, 2, &b"hi"[..]).unwrap();
+            archive.append_reader("short.txt", 2, &b"yo"[..]).unwrap();
+            let bytes = archive.finish().unwrap();
+
+            // Act
+            let entries = unpack_to_file_entries(bytes).unwrap();
+
+            // Assert
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].name, long_name);
+            assert_eq!(entries[0].data, b"hi");
+            assert_eq!(entries[1].name, "short.txt");
+        }
+
+        #[test]
+        fn test_long_names_round_trip_to_disk() {
+            // Arrange
+            let nested = vec!["d".repeat(60); 4].join("/");
+            let name = format!("{}/file.txt", nested);
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir(&nested).unwrap();
+            archive.append_reader(&name, 4, &b"deep"[..]).unwrap();
+            let bytes = archive.finish().unwrap();
+            let dir = tempdir().expect("Failed to create temp dir");
+
+            // Act
+            unpack_with_dir_creation(bytes.as_slice(), dir.path(), &UnpackOptions::default())
+                .unwrap();
+
+            // Assert
+            assert_eq!(fs::read(dir.path().join(&name)).unwrap(), b"deep");
+        }
+
+        #[test]
+        fn test_dangling_long_name_is_bad_header() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir(&"x".repeat(NAME_SIZE + 1)).unwrap();
+            let mut bytes = archive.get_ref().clone();
+            bytes.truncate(bytes.len() - ENTRY_SIZE);
+
+            // Act
+            let result = unpack_to_file_entries(bytes);
+
+            // Assert
+            assert!(matches!(result, Err(Error::BadHeader(_))));
+        }
+    }
+
+    mod metadata {
+        use super::*;
+        use crate::packager::{ArchiveWriter, OwnershipPolicy};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        fn sample_metadata() -> Metadata {
+            Metadata {
+                mode: Some(0o750),
+                mtime: Some(UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789)),
+                uid: Some(4242),
+                gid: Some(4242),
+                uname: Some("builder".to_string()),
+                gname: None,
+                crc32: None,
+            }
+        }
+
+        #[test]
+        fn test_metadata_round_trip_in_memory() {
+            // Arrange
+            let metadata = sample_metadata();
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive
+                .append_reader_with_metadata("run.sh", 2, &b"hi"[..], &metadata)
+                .unwrap();
+            archive.append_dir("plain").unwrap();
+            let bytes = archive.finish().unwrap();
+
+            // Act
+            let entries = unpack_to_file_entries(bytes).unwrap();
+
+            // Assert
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0].metadata, metadata);
+            assert_eq!(entries[0].data, b"hi");
+            assert!(entries[1].metadata.is_empty());
+        }
+
+        #[cfg(unix)]
+        #[test]
+        fn test_metadata_is_applied_on_unpack() {
+            // Arrange
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = sample_metadata();
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir_with_metadata("bin", &metadata).unwrap();
+            archive
+                .append_reader_with_metadata("bin/run.sh", 2, &b"hi"[..], &metadata)
+                .unwrap();
+            let bytes = archive.finish().unwrap();
+            let dir = tempdir().expect("Failed to create temp dir");
+            let options = UnpackOptions {
+                ownership: OwnershipPolicy::Skip,
+                ..Default::default()
+            };
+
+            // Act
+            unpack_with_dir_creation(bytes.as_slice(), dir.path(), &options).unwrap();
+
+            // Assert
+            for path in [dir.path().join("bin"), dir.path().join("bin/run.sh")] {
+                let fs_metadata = fs::metadata(&path).unwrap();
+                assert_eq!(fs_metadata.permissions().mode() & 0o7777, 0o750);
+                assert_eq!(fs_metadata.modified().unwrap(), metadata.mtime.unwrap());
+            }
+        }
+
+        #[test]
+        fn test_dangling_metadata_is_bad_header() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive
+                .append_dir_with_metadata("dir", &sample_metadata())
+                .unwrap();
+            let mut bytes = archive.get_ref().clone();
+            bytes.truncate(bytes.len() - ENTRY_SIZE);
+
+            // Act
+            let result = unpack_to_file_entries(bytes);
+
+            // Assert
+            assert!(matches!(result, Err(Error::BadHeader(_))));
+        }
+    }
+
+    #[cfg(unix)]
+    mod links {
+        use super::*;
+        use crate::packager::ArchiveWriter;
+
+        fn unpack_archive(
+            archive: ArchiveWriter<Vec<u8>>,
+            options: &UnpackOptions,
+        ) -> (tempfile::TempDir, Result<(), Error>) {
+            let bytes = archive.finish().unwrap();
+            let dir = tempdir().expect("Failed to create temp dir");
+            let result =
+                unpack_with_dir_creation(bytes.as_slice(), dir.path().join("out"), options);
+            (dir, result)
+        }
+
+        #[test]
+        fn test_links_are_recreated() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir("lib").unwrap();
+            archive.append_reader("lib/a.txt", 3, &b"abc"[..]).unwrap();
+            archive.append_symlink("lib/current", "a.txt").unwrap();
+            archive.append_hard_link("b.txt", "lib/a.txt").unwrap();
+
+            // Act
+            let (dir, result) = unpack_archive(archive, &UnpackOptions::default());
+
+            // Assert
+            result.unwrap();
+            let out = dir.path().join("out");
+            assert_eq!(
+                fs::read_link(out.join("lib/current")).unwrap(),
+                Path::new("a.txt")
+            );
+            assert_eq!(fs::read(out.join("lib/current")).unwrap(), b"abc");
+            assert_eq!(fs::read(out.join("b.txt")).unwrap(), b"abc");
+        }
+
+        #[test]
+        fn test_escaping_symlink_is_rejected() {
+            for target in ["/etc/passwd", "../outside", "lib/../../outside"] {
+                // Arrange
+                let mut archive = ArchiveWriter::new(Vec::new());
+                archive.append_symlink("link", target).unwrap();
+
+                // Act
+                let (_dir, result) = unpack_archive(archive, &UnpackOptions::default());
+
+                // Assert
+                assert!(
+                    matches!(result, Err(Error::UnsafePath(_))),
+                    "{} should be rejected",
+                    target
+                );
+            }
+        }
+
+        #[test]
+        fn test_writing_through_symlink_is_rejected() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir("real").unwrap();
+            archive.append_symlink("alias", "real").unwrap();
+            archive
+                .append_reader("alias/file.txt", 1, &b"x"[..])
+                .unwrap();
+            archive.append_reader("ok.txt", 1, &b"y"[..]).unwrap();
+            let options = UnpackOptions {
+                skip_unsafe_paths: true,
+                ..Default::default()
+            };
+
+            // Act
+            let (dir, result) = unpack_archive(archive, &options);
+
+            // Assert
+            result.unwrap();
+            let out = dir.path().join("out");
+            assert!(!out.join("real/file.txt").exists());
+            assert!(out.join("ok.txt").exists());
+        }
+
+        #[test]
+        fn test_file_replaces_existing_symlink() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive
+                .append_reader("target.txt", 4, &b"keep"[..])
+                .unwrap();
+            archive.append_symlink("link", "target.txt").unwrap();
+            archive.append_reader("link", 5, &b"other"[..]).unwrap();
+
+            // Act
+            let (dir, result) = unpack_archive(archive, &UnpackOptions::default());
+
+            // Assert
+            result.unwrap();
+            let out = dir.path().join("out");
+            assert_eq!(fs::read(out.join("target.txt")).unwrap(), b"keep");
+            assert_eq!(fs::read(out.join("link")).unwrap(), b"other");
+        }
+
+        #[test]
+        fn test_symlink_through_extracted_symlink_is_rejected() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir("a").unwrap();
+            archive.append_dir("a/b").unwrap();
+            archive.append_symlink("a/b/l", "../..").unwrap();
+            archive.append_symlink("q", "a/b/l/..").unwrap();
+
+            // Act
+            let (dir, result) = unpack_archive(archive, &UnpackOptions::default());
+
+            // Assert
+            assert!(matches!(result, Err(Error::UnsafePath(name)) if name == "q -> a/b/l/.."));
+            assert!(fs::symlink_metadata(dir.path().join("out/q")).is_err());
+        }
+
+        #[test]
+        fn test_symlink_created_under_earlier_symlink_target_is_rejected() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_symlink("q", "a/b/l/..").unwrap();
+            archive.append_dir("a").unwrap();
+            archive.append_dir("a/b").unwrap();
+            archive.append_symlink("a/b/l", "../..").unwrap();
+
+            // Act
+            let (_dir, result) = unpack_archive(archive, &UnpackOptions::default());
+
+            // Assert
+            assert!(matches!(result, Err(Error::UnsafePath(name)) if name == "a/b/l -> ../.."));
+        }
+
+        #[test]
+        fn test_symlink_to_symlink_is_recreated() {
+            // Arrange
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_reader("lib.so.1.2", 3, &b"abc"[..]).unwrap();
+            archive.append_symlink("lib.so.1", "lib.so.1.2").unwrap();
+            archive.append_symlink("lib.so", "lib.so.1").unwrap();
+
+            // Act
+            let (dir, result) = unpack_archive(archive, &UnpackOptions::default());
+
+            // Assert
+            result.unwrap();
+            assert_eq!(fs::read(dir.path().join("out/lib.so")).unwrap(), b"abc");
+        }
+    }
+
+    mod checksums {
+        use super::*;
+        use crate::packager::{ArchiveWriter, PackOptions};
+
+        fn archive_with_checksums() -> Vec<u8> {
+            let options = PackOptions {
+                entry_checksums: true,
+                ..Default::default()
+            };
+            let mut archive = ArchiveWriter::with_options(Vec::new(), options);
+            for (name, data) in [
+                ("first.txt", &b"first file"[..]),
+                ("second.txt", b"second file"),
+            ] {
+                archive
+                    .append_entry(&FileEntry {
+                        name: name.to_string(),
+                        data: data.to_vec(),
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            archive.finish().unwrap()
+        }
+
+        #[test]
+        fn test_entry_checksums_are_stored_and_verified() {
+            // Arrange
+            let archive = archive_with_checksums();
+
+            // Act
+            let entries = unpack_to_file_entries(archive).unwrap();
+
+            // Assert
+            assert_eq!(
+                entries[0].metadata.crc32,
+                Some(crate::checksum::crc32(b"first file"))
+            );
+            assert_eq!(entries[1].data, b"second file");
+        }
+
+        #[test]
+        fn test_damaged_entry_is_named() {
+            // Arrange
+            let mut archive = archive_with_checksums();
+            let data_start = archive
+                .windows(11)
+                .position(|window| window == b"second file")
+                .unwrap();
+            archive[data_start] ^= 0x20;
+
+            // Act
+            let result = unpack_to_file_entries(archive);
+
+            // Assert
+            assert!(matches!(
+                result,
+                Err(Error::ChecksumMismatch { entry: Some(name) }) if name == "second.txt"
+            ));
+        }
+    }
+
+    mod filters {
+        use super::*;
+        use crate::packager::ArchiveWriter;
+
+        fn backup_archive() -> Vec<u8> {
+            let mut archive = ArchiveWriter::new(Vec::new());
+            archive.append_dir("etc").unwrap();
+            archive
+                .append_reader("etc/app.toml", 4, &b"port"[..])
+                .unwrap();
+            archive
+                .append_reader("etc/app.toml.bak", 3, &b"old"[..])
+                .unwrap();
+            archive.append_dir("src/net").unwrap();
+            archive
+                .append_reader("src/net/tcp.rs", 2, &b"rs"[..])
+                .unwrap();
+            archive
+                .append_reader("src/net/tcp.c", 1, &b"c"[..])
+                .unwrap();
+            archive
+                .append_hard_link("app.toml", "etc/app.toml")
+                .unwrap();
+            archive.finish().unwrap()
+        }
+
+        fn names(entries: &[FileEntry]) -> Vec<&str> {
+            entries.iter().map(|e| e.name.as_str()).collect()
+        }
+
+        #[test]
+        fn test_in_memory_unpacker_keeps_selected_entries() {
+            // Arrange
+            let filter = PathFilter::new()
+                .include("etc")
+                .include("src/**/*.rs")
+                .exclude("**/*.bak");
+
+            // Act
+            let entries = unpack_to_filtered_entries(backup_archive(), &filter).unwrap();
+
+            // Assert
+            assert_eq!(
+                names(&entries),
+                vec!["etc", "etc/app.toml", "src/net/tcp.rs"]
+            );
+            assert_eq!(entries[1].data, b"port");
+        }
+
+        #[test]
+        fn test_disk_unpacker_writes_only_selected_entries() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            let options = UnpackOptions {
+                filter: PathFilter::new().include("etc/app.toml"),
+                ..Default::default()
+            };
+
+            // Act
+            unpack_with_dir_creation(backup_archive().as_slice(), dir.path(), &options).unwrap();
+
+            // Assert
+            assert_eq!(fs::read(dir.path().join("etc/app.toml")).unwrap(), b"port");
+            assert!(!dir.path().join("etc/app.toml.bak").exists());
+            assert!(!dir.path().join("src").exists());
+            assert!(!dir.path().join("app.toml").exists());
+        }
+
+        #[test]
+        fn test_hard_link_is_extracted_with_its_target() {
+            // Arrange
+            let dir = tempdir().expect("Failed to create temp dir");
+            let options = UnpackOptions {
+                filter: PathFilter::new().include("*.toml").include("etc/*.toml"),
+                ..Default::default()
+            };
+
+            // Act
+            unpack_with_dir_creation(backup_archive().as_slice(), dir.path(), &options).unwrap();
+
+            // Assert
+            assert_eq!(fs::read(dir.path().join("app.toml")).unwrap(), b"port");
+        }
     }
 }
@@ -17,13 +17,13 @@ pub fn print_with_size_formats(msg: &str, size: usize) {
 pub fn get_file_or_folder_size(path: impl AsRef<Path>) -> Result<u64, io::Error> {
     let path_ref = path.as_ref();
     if path_ref.is_file() {
-        return match path_ref.metadata() {
+        match path_ref.metadata() {
             Ok(metadata) => Ok(metadata.len()),
             Err(e) => Err(e),
-        };
+        }
     } else {
-        return dir_size(path_ref);
-    };
+        dir_size(path_ref)
+    }
 }
 
 pub fn dir_size(path: impl Into<PathBuf>) -> io::Result<u64> {
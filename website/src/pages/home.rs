@@ -11,7 +11,7 @@ use yew::prelude::*;
 #[function_component(Home)]
 pub fn home() -> Html {
     let is_compress = use_state(|| true);
-    let selected_files = use_state(|| Vec::<File>::new());
+    let selected_files = use_state(Vec::<File>::new);
     let is_processing = use_state(|| false);
 
     let on_mode_change = {
@@ -58,24 +58,25 @@ pub fn home() -> Html {
                                 name: file.name(),
                                 data,
                                 is_dir: false,
+                                ..Default::default()
                             });
                         }
                     }
 
-                    let packed_data = press_rs::packager::pack_entries(entries);
-                    let compressed = press_rs::compressor::compress_raw(&packed_data);
-                    download_file("archive.pressrs", &compressed);
-                } else {
-                    if let Some(file) = files.get(0) {
-                        if let Ok(archive_data) = read_as_bytes(file).await {
-                            let decompressed = press_rs::compressor::decompress_raw(&archive_data);
-                            let entries = press_rs::packager::unpack_to_entries(decompressed);
-
-                            for entry in entries {
-                                if !entry.is_dir {
-                                    download_file(&entry.name, &entry.data);
-                                }
-                            }
+                    let compressed = press_rs::packager::pack_entries(entries)
+                        .and_then(|packed_data| press_rs::compressor::compress_raw(&packed_data));
+                    if let Ok(compressed) = compressed {
+                        download_file("archive.pressrs", &compressed);
+                    }
+                } else if let Some(file) = files.first()
+                    && let Ok(archive_data) = read_as_bytes(file).await
+                {
+                    let entries = press_rs::compressor::decompress_raw(&archive_data)
+                        .and_then(press_rs::packager::unpack_to_entries);
+
+                    for entry in entries.unwrap_or_default() {
+                        if !entry.is_dir {
+                            download_file(&entry.name, &entry.data);
                         }
                     }
                 }